@@ -0,0 +1,274 @@
+// minisign-style text files for detached signatures and public keys.
+//
+//   untrusted comment: <free text, not signed>
+//   base64(algorithm || key id || signature)
+//   trusted comment: <free text, signed>
+//
+// minisign covers the trusted comment with a second, global signature. A hash
+// based signature is kilobytes, so here the one signature covers both:
+// it signs H(file) || trusted comment. Public key files have the same first two
+// lines, with the public key in place of the signature.
+use std::{fs, io, path::{Path, PathBuf}};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+
+use crate::lib::{
+    components::{
+        parameters::Parameters,
+        sphincs::{public::{KEY_VERSION, SphincsPublic}, secret::SphincsSigner, signature::SphincsSignature}
+    },
+    helpers::{encoding::DecodeError, hasher::HashSuite}
+};
+
+const UNTRUSTED_PREFIX: &str = "untrusted comment: ";
+const TRUSTED_PREFIX: &str = "trusted comment: ";
+pub const KEY_ID_SIZE: usize = 8;
+
+#[derive(Debug)]
+pub enum ArmorError {
+    Io(io::Error),
+    Format(&'static str), // the line that is missing or malformed
+    Base64,
+    MultilineComment,
+    NoParameterSet, // the const generic parameters are not a named set
+    WrongAlgorithm([u8; 2], [u8; 2]), // expected, got
+    KeyIdMismatch([u8; KEY_ID_SIZE], [u8; KEY_ID_SIZE]), // expected, got
+    Key(DecodeError),
+    InvalidSignature,
+}
+
+impl From<io::Error> for ArmorError {
+    fn from(err: io::Error) -> Self {
+        ArmorError::Io(err)
+    }
+}
+
+impl From<DecodeError> for ArmorError {
+    fn from(err: DecodeError) -> Self {
+        ArmorError::Key(err)
+    }
+}
+
+// 'S' and the parameter set id
+fn algorithm(params: Parameters) -> Result<[u8; 2], ArmorError> {
+    let set = params.parameter_set().ok_or(ArmorError::NoParameterSet)?;
+    Ok([b'S', set.id()])
+}
+
+fn check_comment(comment: &str) -> Result<(), ArmorError> {
+    match comment.contains(['\n', '\r']) {
+        true => Err(ArmorError::MultilineComment),
+        false => Ok(()),
+    }
+}
+
+fn key_id_hex(key_id: &[u8; KEY_ID_SIZE]) -> String {
+    key_id.iter().map(|byte| format!("{byte:02X}")).collect()
+}
+
+// The comment and the decoded blob of a "comment line, base64 line" pair
+fn read_block<'a>(lines: &mut impl Iterator<Item = &'a str>, prefix: &str, name: &'static str) -> Result<(String, Vec<u8>), ArmorError> {
+    let comment = lines.next().and_then(|line| line.strip_prefix(prefix)).ok_or(ArmorError::Format(name))?;
+    let blob = lines.next().ok_or(ArmorError::Format("base64"))?;
+    let blob = STANDARD.decode(blob.trim()).map_err(|_| ArmorError::Base64)?;
+    Ok((comment.to_string(), blob))
+}
+
+// Splits algorithm || key id || rest, checking the algorithm
+fn split_blob(blob: &[u8], expected: [u8; 2]) -> Result<([u8; KEY_ID_SIZE], &[u8]), ArmorError> {
+    let (algorithm, rest) = blob.split_first_chunk::<2>().ok_or(ArmorError::Format("base64"))?;
+    if *algorithm != expected {
+        return Err(ArmorError::WrongAlgorithm(expected, *algorithm));
+    }
+    let (key_id, rest) = rest.split_first_chunk::<KEY_ID_SIZE>().ok_or(ArmorError::Format("base64"))?;
+    Ok((*key_id, rest))
+}
+
+// The signature file of `path`, with ".sig" appended
+pub fn signature_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".sig");
+    PathBuf::from(name)
+}
+
+impl<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite> SphincsPublic<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+    // The first 8 bytes of the hash of PK.seed || PK.root
+    pub fn key_id(&self) -> [u8; KEY_ID_SIZE] {
+        H::hash_message::<32>(&[&self.public_seed[..], &self.key].concat())[..KEY_ID_SIZE].try_into().expect("hashes are longer than 8 bytes")
+    }
+
+    pub fn to_armored(&self) -> Result<String, ArmorError> {
+        let blob = [&algorithm(self.get_params())?[..], &self.key_id(), &self.to_bytes()[2..]].concat();
+        Ok(format!("{UNTRUSTED_PREFIX}rust_sphincs public key {}\n{}\n", key_id_hex(&self.key_id()), STANDARD.encode(blob)))
+    }
+
+    pub fn from_armored(text: &str) -> Result<Self, ArmorError> {
+        let (_, blob) = read_block(&mut text.lines(), UNTRUSTED_PREFIX, "untrusted comment")?;
+        let params = Parameters::sphincs::<K, A, LAYERS, TREE_HEIGHT, N, W, H>();
+        let expected = algorithm(params)?;
+        let (key_id, public) = split_blob(&blob, expected)?;
+
+        let public_key = Self::from_bytes(&[&[KEY_VERSION, expected[1]][..], public].concat())?;
+        match public_key.key_id() == key_id {
+            true => Ok(public_key),
+            false => Err(ArmorError::KeyIdMismatch(public_key.key_id(), key_id)),
+        }
+    }
+
+    // Returns the trusted comment, which is only meaningful if this returns Ok
+    pub fn verify_armored(&self, data: &[u8], signature: &ArmoredSignature<K, A, LAYERS, TREE_HEIGHT, N, W, H>) -> Result<String, ArmorError> {
+        if signature.key_id != self.key_id() {
+            return Err(ArmorError::KeyIdMismatch(self.key_id(), signature.key_id));
+        }
+        let signed = ArmoredSignature::<K, A, LAYERS, TREE_HEIGHT, N, W, H>::signed_message(data, &signature.trusted_comment);
+        match signature.signature.validate(&signed, self) {
+            Ok(_) => Ok(signature.trusted_comment.clone()),
+            Err(_) => Err(ArmorError::InvalidSignature),
+        }
+    }
+
+    // Checks `path` against the signature file `signature_path`
+    pub fn verify_file(&self, path: &Path, signature_path: &Path) -> Result<String, ArmorError> {
+        let signature = ArmoredSignature::read(signature_path)?;
+        self.verify_armored(&fs::read(path)?, &signature)
+    }
+}
+
+impl<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite> SphincsSigner<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+    pub fn sign_armored(&self, data: &[u8], trusted_comment: &str) -> Result<ArmoredSignature<K, A, LAYERS, TREE_HEIGHT, N, W, H>, ArmorError> {
+        check_comment(trusted_comment)?;
+        let public_key = self.public_key();
+        let signed = ArmoredSignature::<K, A, LAYERS, TREE_HEIGHT, N, W, H>::signed_message(data, trusted_comment);
+        Ok(ArmoredSignature {
+            untrusted_comment: format!("signature from rust_sphincs secret key {}", key_id_hex(&public_key.key_id())),
+            trusted_comment: trusted_comment.to_string(),
+            key_id: public_key.key_id(),
+            signature: self.sign(&signed),
+        })
+    }
+
+    // Signs `path` and writes the signature next to it, returning the .sig path
+    pub fn sign_file(&self, path: &Path, trusted_comment: &str) -> Result<PathBuf, ArmorError> {
+        let signature = self.sign_armored(&fs::read(path)?, trusted_comment)?;
+        let out = signature_path(path);
+        signature.write(&out)?;
+        Ok(out)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArmoredSignature<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite> {
+    pub untrusted_comment: String,
+    pub trusted_comment: String,
+    pub key_id: [u8; KEY_ID_SIZE],
+    pub signature: SphincsSignature<K, A, LAYERS, TREE_HEIGHT, N, W, H>,
+}
+
+impl<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite> ArmoredSignature<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+    fn signed_message(data: &[u8], trusted_comment: &str) -> Vec<u8> {
+        [&H::hash_message::<32>(data)[..], trusted_comment.as_bytes()].concat()
+    }
+
+    pub fn to_text(&self) -> Result<String, ArmorError> {
+        check_comment(&self.untrusted_comment)?;
+        check_comment(&self.trusted_comment)?;
+        let blob = [&algorithm(Parameters::sphincs::<K, A, LAYERS, TREE_HEIGHT, N, W, H>())?[..], &self.key_id, &self.signature.to_bytes()].concat();
+        Ok(format!("{UNTRUSTED_PREFIX}{}\n{}\n{TRUSTED_PREFIX}{}\n", self.untrusted_comment, STANDARD.encode(blob), self.trusted_comment))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ArmorError> {
+        let mut lines = text.lines();
+        let (untrusted_comment, blob) = read_block(&mut lines, UNTRUSTED_PREFIX, "untrusted comment")?;
+        let trusted_comment = lines.next().and_then(|line| line.strip_prefix(TRUSTED_PREFIX)).ok_or(ArmorError::Format("trusted comment"))?;
+        if lines.any(|line| !line.trim().is_empty()) {
+            return Err(ArmorError::Format("end of file"));
+        }
+
+        let (key_id, signature) = split_blob(&blob, algorithm(Parameters::sphincs::<K, A, LAYERS, TREE_HEIGHT, N, W, H>())?)?;
+        Ok(Self {
+            untrusted_comment,
+            trusted_comment: trusted_comment.to_string(),
+            key_id,
+            signature: SphincsSignature::from_bytes(signature)?,
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), ArmorError> {
+        Ok(fs::write(path, self.to_text()?)?)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, ArmorError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::components::{parameters::{SphincsSha2_128fPublic, SphincsSha2_128fSigner, SphincsSha2_128sPublic}, sphincs::tests::temp_dir};
+
+    type Armored = ArmoredSignature<33, 6, 22, 3, 16, 256, crate::lib::helpers::hasher::Sha2Suite>;
+
+    fn signer() -> SphincsSha2_128fSigner {
+        SphincsSha2_128fSigner::new([1u8; 16], [2u8; 16])
+    }
+
+    #[test]
+    fn test_public_key_file() {
+        let public_key = signer().public_key();
+        let text = public_key.to_armored().unwrap();
+        assert!(text.starts_with(&format!("untrusted comment: rust_sphincs public key {}\n", key_id_hex(&public_key.key_id()))));
+        assert_eq!(SphincsSha2_128fPublic::from_armored(&text).unwrap(), public_key);
+
+        assert!(matches!(SphincsSha2_128sPublic::from_armored(&text), Err(ArmorError::WrongAlgorithm([b'S', 65], [b'S', 66]))));
+        assert!(matches!(SphincsSha2_128fPublic::from_armored("untrusted comment: x\n!!!\n"), Err(ArmorError::Base64)));
+        assert!(matches!(SphincsSha2_128fPublic::from_armored("a public key\n"), Err(ArmorError::Format("untrusted comment"))));
+    }
+
+    #[test]
+    fn test_signature_text() {
+        let signer = signer();
+        let public_key = signer.public_key();
+        let signature = signer.sign_armored(b"artifact bytes", "file:app-1.2.tar.gz").unwrap();
+
+        let text = signature.to_text().unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("untrusted comment: "));
+        assert_eq!(lines[2], "trusted comment: file:app-1.2.tar.gz");
+
+        let parsed = Armored::parse(&text).unwrap();
+        assert_eq!(parsed, signature);
+        assert_eq!(public_key.verify_armored(b"artifact bytes", &parsed).unwrap(), "file:app-1.2.tar.gz");
+        assert!(matches!(public_key.verify_armored(b"other bytes", &parsed), Err(ArmorError::InvalidSignature)));
+
+        // The untrusted comment can change freely, the trusted one can not
+        let relabelled = text.replace(lines[0], "untrusted comment: anything");
+        assert!(public_key.verify_armored(b"artifact bytes", &Armored::parse(&relabelled).unwrap()).is_ok());
+        let forged = text.replace("file:app-1.2.tar.gz", "file:app-9.9.tar.gz");
+        assert!(matches!(public_key.verify_armored(b"artifact bytes", &Armored::parse(&forged).unwrap()), Err(ArmorError::InvalidSignature)));
+
+        // A signature from another key is refused before validating
+        let other = SphincsSha2_128fSigner::new([3u8; 16], [2u8; 16]).public_key();
+        assert!(matches!(other.verify_armored(b"artifact bytes", &parsed), Err(ArmorError::KeyIdMismatch(..))));
+        assert!(matches!(signer.sign_armored(b"x", "two\nlines"), Err(ArmorError::MultilineComment)));
+    }
+
+    #[test]
+    fn test_sign_and_verify_file() {
+        let dir = temp_dir("armor");
+        let artifact = dir.join("release.bin");
+        fs::write(&artifact, b"release contents").unwrap();
+
+        let signer = signer();
+        let sig_path = signer.sign_file(&artifact, "release 1.0").unwrap();
+        assert_eq!(sig_path, dir.join("release.bin.sig"));
+        assert_eq!(signer.public_key().verify_file(&artifact, &sig_path).unwrap(), "release 1.0");
+
+        fs::write(&artifact, b"release contents, patched").unwrap();
+        assert!(matches!(signer.public_key().verify_file(&artifact, &sig_path), Err(ArmorError::InvalidSignature)));
+        assert!(matches!(signer.public_key().verify_file(&dir.join("missing"), &sig_path), Err(ArmorError::Io(_))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
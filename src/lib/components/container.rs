@@ -0,0 +1,310 @@
+// A self-describing envelope for signatures of either scheme, so a verifier with
+// only the bytes and a keyring can pick the key and the parameters itself:
+//
+//   magic "SPHX" | version | parameter set id | hash suite id | kid length | kid
+//   | payload length (u32, big endian) | payload
+//
+// The payload is the canonical signature encoding of the set's scheme. SLH-DSA
+// signatures in a container are made with an empty context.
+use crate::lib::{
+    components::{
+        parameters::{ParameterSet, Parameters, Scheme},
+        slh_dsa::{hash::SlhDsaHash, public::SlhDsaPublic, secret::SlhDsaSigner, signature::SlhDsaSignature},
+        sphincs::{public::{KEY_VERSION, SphincsPublic}, secret::SphincsSigner, signature::SphincsSignature}
+    },
+    helpers::hasher::{HashSuite, Sha2Suite, Shake256Suite}
+};
+
+pub const MAGIC: [u8; 4] = *b"SPHX";
+pub const CONTAINER_VERSION: u8 = 1;
+const HEADER_SIZE: usize = MAGIC.len() + 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContainerError {
+    BadMagic,
+    UnsupportedVersion(u8),
+    UnknownParameterSet(u8),
+    WrongHashSuite(u8, u8), // expected, got
+    Truncated,
+    TrailingData(usize),
+    KidTooLong(usize),
+    NoParameterSet, // the const generic parameters are not a named set
+    MalformedPayload,
+    NoMatchingKey,
+    InvalidSignature,
+}
+
+// 1 for SHA2, 2 for SHAKE
+pub fn hash_suite_id(params: Parameters) -> u8 {
+    match params.hash {
+        "SHAKE" => 2,
+        _ => 1,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignatureContainer {
+    pub set: ParameterSet,
+    pub kid: Option<Vec<u8>>,
+    pub payload: Vec<u8>,
+}
+
+impl SignatureContainer {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let kid = self.kid.as_deref().unwrap_or_default();
+        let mut out = Vec::with_capacity(HEADER_SIZE + kid.len() + 4 + self.payload.len());
+        out.extend_from_slice(&MAGIC);
+        out.extend_from_slice(&[CONTAINER_VERSION, self.set.id(), hash_suite_id(self.set.params()), kid.len() as u8]);
+        out.extend_from_slice(kid);
+        out.extend_from_slice(&(self.payload.len() as u32).to_be_bytes());
+        out.extend_from_slice(&self.payload);
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ContainerError> {
+        let header = bytes.get(..HEADER_SIZE).ok_or(ContainerError::Truncated)?;
+        if header[..4] != MAGIC {
+            return Err(ContainerError::BadMagic);
+        }
+        let [version, set, hash, kid_len] = header[4..].try_into().expect("header is 8 bytes");
+        if version != CONTAINER_VERSION {
+            return Err(ContainerError::UnsupportedVersion(version));
+        }
+        let set = ParameterSet::from_id(set).ok_or(ContainerError::UnknownParameterSet(set))?;
+        let expected_hash = hash_suite_id(set.params());
+        if hash != expected_hash {
+            return Err(ContainerError::WrongHashSuite(expected_hash, hash));
+        }
+
+        let rest = &bytes[HEADER_SIZE..];
+        let (kid, rest) = rest.split_at_checked(kid_len as usize).ok_or(ContainerError::Truncated)?;
+        let (payload_len, rest) = rest.split_first_chunk::<4>().ok_or(ContainerError::Truncated)?;
+        let (payload, rest) = rest.split_at_checked(u32::from_be_bytes(*payload_len) as usize).ok_or(ContainerError::Truncated)?;
+        if !rest.is_empty() {
+            return Err(ContainerError::TrailingData(rest.len()));
+        }
+
+        let kid = (kid_len > 0).then(|| kid.to_vec());
+        Ok(Self { set, kid, payload: payload.to_vec() })
+    }
+}
+
+fn seal(params: Parameters, kid: Option<&[u8]>, payload: Vec<u8>) -> Result<Vec<u8>, ContainerError> {
+    let set = params.parameter_set().ok_or(ContainerError::NoParameterSet)?;
+    if let Some(kid) = kid.filter(|kid| kid.len() > u8::MAX as usize) {
+        return Err(ContainerError::KidTooLong(kid.len()));
+    }
+    Ok(SignatureContainer { set, kid: kid.map(<[u8]>::to_vec), payload }.to_bytes())
+}
+
+impl<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite> SphincsSigner<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+    pub fn sign_container(&self, message: &[u8], kid: Option<&[u8]>) -> Result<Vec<u8>, ContainerError> {
+        seal(Parameters::sphincs::<K, A, LAYERS, TREE_HEIGHT, N, W, H>(), kid, self.sign(message).to_bytes())
+    }
+}
+
+impl<const N: usize, const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, H: SlhDsaHash> SlhDsaSigner<N, K, A, LAYERS, TREE_HEIGHT, H> {
+    pub fn sign_container(&self, message: &[u8], kid: Option<&[u8]>) -> Result<Vec<u8>, ContainerError> {
+        let signature = self.sign(message, b"").expect("The empty context is not too long");
+        seal(Parameters::slh_dsa::<N, K, A, LAYERS, TREE_HEIGHT, H>(), kid, signature.to_bytes())
+    }
+}
+
+// What a keyring verification established
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verified {
+    pub set: ParameterSet,
+    pub kid: Option<Vec<u8>>,
+    pub timestamp: Option<u128>, // only signatures of the custom scheme carry one
+}
+
+impl Verified {
+    pub fn scheme(&self) -> Scheme {
+        self.set.params().scheme
+    }
+}
+
+fn verify_sphincs<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite>(
+    set: ParameterSet, public_key: &[u8], payload: &[u8], message: &[u8]
+) -> Result<Option<u128>, ContainerError> {
+    let public_key = SphincsPublic::<K, A, LAYERS, TREE_HEIGHT, N, W, H>::from_bytes(&[&[KEY_VERSION, set.id()][..], public_key].concat())
+        .map_err(|_| ContainerError::NoMatchingKey)?;
+    let signature = SphincsSignature::<K, A, LAYERS, TREE_HEIGHT, N, W, H>::from_bytes(payload).map_err(|_| ContainerError::MalformedPayload)?;
+    match signature.validate(message, &public_key) {
+        Ok(result) => Ok(Some(result.timestamp)),
+        Err(_) => Err(ContainerError::InvalidSignature),
+    }
+}
+
+fn verify_slh_dsa<const N: usize, const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, H: SlhDsaHash>(
+    public_key: &[u8], payload: &[u8], message: &[u8]
+) -> Result<Option<u128>, ContainerError> {
+    let public_key = SlhDsaPublic::<N, K, A, LAYERS, TREE_HEIGHT, H>::from_bytes(public_key).map_err(|_| ContainerError::NoMatchingKey)?;
+    let signature = SlhDsaSignature::<N, K, A, LAYERS, TREE_HEIGHT, H>::from_bytes(payload).map_err(|_| ContainerError::MalformedPayload)?;
+    match signature.validate(message, b"", &public_key) {
+        Ok(()) => Ok(None),
+        Err(_) => Err(ContainerError::InvalidSignature),
+    }
+}
+
+// Picks the const generic instantiation of a named set
+fn verify_with(set: ParameterSet, public_key: &[u8], payload: &[u8], message: &[u8]) -> Result<Option<u128>, ContainerError> {
+    match set {
+        ParameterSet::SlhDsaSha2_128s => verify_slh_dsa::<16, 14, 12, 7, 9, Sha2Suite>(public_key, payload, message),
+        ParameterSet::SlhDsaSha2_128f => verify_slh_dsa::<16, 33, 6, 22, 3, Sha2Suite>(public_key, payload, message),
+        ParameterSet::SlhDsaSha2_192s => verify_slh_dsa::<24, 17, 14, 7, 9, Sha2Suite>(public_key, payload, message),
+        ParameterSet::SlhDsaSha2_192f => verify_slh_dsa::<24, 33, 8, 22, 3, Sha2Suite>(public_key, payload, message),
+        ParameterSet::SlhDsaSha2_256s => verify_slh_dsa::<32, 22, 14, 8, 8, Sha2Suite>(public_key, payload, message),
+        ParameterSet::SlhDsaSha2_256f => verify_slh_dsa::<32, 35, 9, 17, 4, Sha2Suite>(public_key, payload, message),
+        ParameterSet::SlhDsaShake128s => verify_slh_dsa::<16, 14, 12, 7, 9, Shake256Suite>(public_key, payload, message),
+        ParameterSet::SlhDsaShake128f => verify_slh_dsa::<16, 33, 6, 22, 3, Shake256Suite>(public_key, payload, message),
+        ParameterSet::SlhDsaShake192s => verify_slh_dsa::<24, 17, 14, 7, 9, Shake256Suite>(public_key, payload, message),
+        ParameterSet::SlhDsaShake192f => verify_slh_dsa::<24, 33, 8, 22, 3, Shake256Suite>(public_key, payload, message),
+        ParameterSet::SlhDsaShake256s => verify_slh_dsa::<32, 22, 14, 8, 8, Shake256Suite>(public_key, payload, message),
+        ParameterSet::SlhDsaShake256f => verify_slh_dsa::<32, 35, 9, 17, 4, Shake256Suite>(public_key, payload, message),
+        ParameterSet::SphincsSha2_128s => verify_sphincs::<14, 12, 7, 9, 16, 256, Sha2Suite>(set, public_key, payload, message),
+        ParameterSet::SphincsSha2_128f => verify_sphincs::<33, 6, 22, 3, 16, 256, Sha2Suite>(set, public_key, payload, message),
+        ParameterSet::SphincsSha2_192s => verify_sphincs::<17, 14, 7, 9, 24, 256, Sha2Suite>(set, public_key, payload, message),
+        ParameterSet::SphincsSha2_192f => verify_sphincs::<33, 8, 22, 3, 24, 256, Sha2Suite>(set, public_key, payload, message),
+        ParameterSet::SphincsSha2_256s => verify_sphincs::<22, 14, 8, 8, 32, 256, Sha2Suite>(set, public_key, payload, message),
+        ParameterSet::SphincsSha2_256f => verify_sphincs::<35, 9, 17, 4, 32, 256, Sha2Suite>(set, public_key, payload, message),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct KeyringEntry {
+    kid: Option<Vec<u8>>,
+    set: ParameterSet,
+    public_key: Vec<u8>, // PK.seed || PK.root
+}
+
+// Public keys of any named set, optionally with a key id
+#[derive(Debug, Clone, Default)]
+pub struct Keyring {
+    keys: Vec<KeyringEntry>,
+}
+
+impl Keyring {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn add_sphincs<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite>(
+        &mut self, kid: Option<&[u8]>, public_key: &SphincsPublic<K, A, LAYERS, TREE_HEIGHT, N, W, H>
+    ) -> Result<(), ContainerError> {
+        let set = public_key.parameter_set().ok_or(ContainerError::NoParameterSet)?;
+        self.keys.push(KeyringEntry { kid: kid.map(<[u8]>::to_vec), set, public_key: public_key.to_bytes()[2..].to_vec() });
+        Ok(())
+    }
+
+    pub fn add_slh_dsa<const N: usize, const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, H: SlhDsaHash>(
+        &mut self, kid: Option<&[u8]>, public_key: &SlhDsaPublic<N, K, A, LAYERS, TREE_HEIGHT, H>
+    ) -> Result<(), ContainerError> {
+        let set = public_key.parameter_set().ok_or(ContainerError::NoParameterSet)?;
+        self.keys.push(KeyringEntry { kid: kid.map(<[u8]>::to_vec), set, public_key: public_key.to_bytes() });
+        Ok(())
+    }
+
+    // With a kid only the keys under that kid are tried, without one every key of
+    // the container's parameter set is
+    pub fn verify(&self, container: &[u8], message: &[u8]) -> Result<Verified, ContainerError> {
+        let container = SignatureContainer::from_bytes(container)?;
+        let mut candidates = self.keys.iter()
+            .filter(|entry| entry.set == container.set)
+            .filter(|entry| container.kid.is_none() || entry.kid == container.kid)
+            .peekable();
+        if candidates.peek().is_none() {
+            return Err(ContainerError::NoMatchingKey);
+        }
+
+        for entry in candidates {
+            match verify_with(container.set, &entry.public_key, &container.payload, message) {
+                Ok(timestamp) => return Ok(Verified { set: container.set, kid: entry.kid.clone(), timestamp }),
+                Err(ContainerError::InvalidSignature) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Err(ContainerError::InvalidSignature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::components::parameters::{SlhDsaShake128fSigner, SphincsSha2_128fSigner, SphincsSha2_128sSigner};
+
+    #[test]
+    fn test_layout() {
+        let container = SignatureContainer { set: ParameterSet::SlhDsaShake128f, kid: Some(b"ab".to_vec()), payload: vec![9, 9, 9] };
+        let bytes = container.to_bytes();
+        assert_eq!(bytes, [b'S', b'P', b'H', b'X', 1, 8, 2, 2, b'a', b'b', 0, 0, 0, 3, 9, 9, 9]);
+        assert_eq!(SignatureContainer::from_bytes(&bytes), Ok(container));
+
+        let mut bad = bytes.clone();
+        bad[0] = b'X';
+        assert_eq!(SignatureContainer::from_bytes(&bad), Err(ContainerError::BadMagic));
+        let mut bad = bytes.clone();
+        bad[4] = 2;
+        assert_eq!(SignatureContainer::from_bytes(&bad), Err(ContainerError::UnsupportedVersion(2)));
+        let mut bad = bytes.clone();
+        bad[5] = 40;
+        assert_eq!(SignatureContainer::from_bytes(&bad), Err(ContainerError::UnknownParameterSet(40)));
+        let mut bad = bytes.clone();
+        bad[6] = 1;
+        assert_eq!(SignatureContainer::from_bytes(&bad), Err(ContainerError::WrongHashSuite(2, 1)));
+        assert_eq!(SignatureContainer::from_bytes(&bytes[..bytes.len() - 1]), Err(ContainerError::Truncated));
+        assert_eq!(SignatureContainer::from_bytes(&[&bytes[..], &[0]].concat()), Err(ContainerError::TrailingData(1)));
+    }
+
+    #[test]
+    fn test_keyring_picks_the_key() {
+        let fast = SphincsSha2_128fSigner::new([1u8; 16], [2u8; 16]);
+        let other_fast = SphincsSha2_128fSigner::new([3u8; 16], [4u8; 16]);
+        let standard = SlhDsaShake128fSigner::new([5u8; 16], [6u8; 16], [7u8; 16]);
+
+        let mut keyring = Keyring::new();
+        keyring.add_sphincs(Some(b"fast-1"), &fast.public_key()).unwrap();
+        keyring.add_sphincs(None, &other_fast.public_key()).unwrap();
+        keyring.add_slh_dsa(Some(b"fips"), &standard.public_key()).unwrap();
+        assert_eq!(keyring.len(), 3);
+
+        let sealed = fast.sign_container(b"release.tar", Some(b"fast-1")).unwrap();
+        let verified = keyring.verify(&sealed, b"release.tar").unwrap();
+        assert_eq!((verified.set, verified.kid.as_deref()), (ParameterSet::SphincsSha2_128f, Some(&b"fast-1"[..])));
+        assert!(verified.timestamp.is_some());
+        assert_eq!(verified.scheme(), Scheme::Sphincs);
+
+        // Without a kid every key of the set is tried
+        let anonymous = other_fast.sign_container(b"release.tar", None).unwrap();
+        assert_eq!(keyring.verify(&anonymous, b"release.tar").unwrap().kid, None);
+
+        let fips = standard.sign_container(b"release.tar", Some(b"fips")).unwrap();
+        let verified = keyring.verify(&fips, b"release.tar").unwrap();
+        assert_eq!((verified.set, verified.timestamp), (ParameterSet::SlhDsaShake128f, None));
+
+        assert_eq!(keyring.verify(&sealed, b"release.tar.gz"), Err(ContainerError::InvalidSignature));
+        // A kid the keyring does not know, or a set it has no key for
+        let unknown = fast.sign_container(b"release.tar", Some(b"fast-2")).unwrap();
+        assert_eq!(keyring.verify(&unknown, b"release.tar"), Err(ContainerError::NoMatchingKey));
+        let small = SphincsSha2_128sSigner::new([1u8; 16], [2u8; 16]).sign_container(b"release.tar", None).unwrap();
+        assert_eq!(keyring.verify(&small, b"release.tar"), Err(ContainerError::NoMatchingKey));
+    }
+
+    #[test]
+    fn test_unnamed_sets_and_long_kids() {
+        let signer = SphincsSigner::<4, 4, 2, 3>::new([1u8; 32], [2u8; 32]);
+        assert_eq!(signer.sign_container(b"message", None), Err(ContainerError::NoParameterSet));
+        assert_eq!(Keyring::new().add_sphincs(None, &signer.public_key()), Err(ContainerError::NoParameterSet));
+
+        let named = SphincsSha2_128fSigner::new([1u8; 16], [2u8; 16]);
+        assert_eq!(named.sign_container(b"message", Some(&[0u8; 256])), Err(ContainerError::KidTooLong(256)));
+    }
+}
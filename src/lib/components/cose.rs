@@ -0,0 +1,329 @@
+// COSE (RFC 9052) for the custom scheme: AKP COSE_Keys and COSE_Sign1 messages.
+//
+// The custom scheme has no registered COSE algorithm, so its sets use ids from
+// the private use range below -65536: -65536 - ParameterSet::id(), e.g. -65602
+// for SPHINCS-SHA2-128f. The AKP key type (7) and its "pub" label (-1) follow
+// the COSE post-quantum drafts.
+use crate::lib::{
+    components::{
+        parameters::Parameters,
+        sphincs::{public::{KEY_VERSION, SphincsPublic}, secret::SphincsSigner, signature::SphincsSignature}
+    },
+    helpers::{cbor::{self, CborError, CborReader, TAG, TEXT}, encoding::DecodeError, hasher::HashSuite}
+};
+
+pub const COSE_SIGN1_TAG: u64 = 18;
+pub const KEY_TYPE_AKP: i64 = 7;
+const PRIVATE_USE_BASE: i64 = -65536;
+
+// COSE_Key labels
+const KEY_KTY: i64 = 1;
+const KEY_KID: i64 = 2;
+const KEY_ALG: i64 = 3;
+const KEY_PUB: i64 = -1;
+
+// Header labels
+const HEADER_ALG: i64 = 1;
+const HEADER_KID: i64 = 4;
+
+#[derive(Debug, PartialEq)]
+pub enum CoseError {
+    Cbor(CborError),
+    NoAlgorithm, // the const generic parameters are not a named set
+    WrongKeyType(i64),
+    WrongAlgorithm(i64, i64), // expected, got
+    MissingLabel(i64),
+    WrongTag(u64),
+    DetachedPayload,
+    Key(DecodeError),
+    InvalidSignature,
+}
+
+impl From<CborError> for CoseError {
+    fn from(err: CborError) -> Self {
+        CoseError::Cbor(err)
+    }
+}
+
+impl From<DecodeError> for CoseError {
+    fn from(err: DecodeError) -> Self {
+        CoseError::Key(err)
+    }
+}
+
+pub fn algorithm_id(params: Parameters) -> Result<i64, CoseError> {
+    params.parameter_set().map(|set| PRIVATE_USE_BASE - set.id() as i64).ok_or(CoseError::NoAlgorithm)
+}
+
+fn check_algorithm(expected: i64, got: Option<i64>) -> Result<(), CoseError> {
+    match got {
+        None => Err(CoseError::MissingLabel(HEADER_ALG)),
+        Some(got) if got != expected => Err(CoseError::WrongAlgorithm(expected, got)),
+        Some(_) => Ok(()),
+    }
+}
+
+// Calls `read` with every integer label of a map and the reader positioned at its
+// value. `read` returns false for labels it does not know, which are skipped,
+// as are text labels.
+fn read_map<'a>(reader: &mut CborReader<'a>, mut read: impl FnMut(i64, &mut CborReader<'a>) -> Result<bool, CoseError>) -> Result<(), CoseError> {
+    for _ in 0..reader.map()? {
+        let known = match reader.peek_major() {
+            Some(TEXT) => { reader.text()?; false }
+            _ => {
+                let label = reader.int()?;
+                read(label, reader)?
+            }
+        };
+        if !known {
+            reader.skip()?;
+        }
+    }
+    Ok(())
+}
+
+// Sig_structure for COSE_Sign1, RFC 9052 section 4.4
+fn signature_input(protected: &[u8], external_aad: &[u8], payload: &[u8]) -> Vec<u8> {
+    cbor::array(&[cbor::text("Signature1"), cbor::bytes(protected), cbor::bytes(external_aad), cbor::bytes(payload)])
+}
+
+impl<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite> SphincsPublic<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+    // {1: 7, 2: kid, 3: alg, -1: PK.seed || PK.root}, in deterministic key order
+    pub fn to_cose_key(&self, kid: Option<&[u8]>) -> Result<Vec<u8>, CoseError> {
+        let alg = algorithm_id(self.get_params())?;
+        let mut pairs = vec![(cbor::int(KEY_KTY), cbor::int(KEY_TYPE_AKP))];
+        if let Some(kid) = kid {
+            pairs.push((cbor::int(KEY_KID), cbor::bytes(kid)));
+        }
+        pairs.push((cbor::int(KEY_ALG), cbor::int(alg)));
+        pairs.push((cbor::int(KEY_PUB), cbor::bytes(&self.to_bytes()[2..])));
+        Ok(cbor::map(&pairs))
+    }
+
+    // alg is required, and has to name this type's parameter set
+    pub fn from_cose_key(bytes: &[u8]) -> Result<Self, CoseError> {
+        let expected = algorithm_id(Parameters::sphincs::<K, A, LAYERS, TREE_HEIGHT, N, W, H>())?;
+        let mut reader = CborReader::new(bytes);
+        let (mut kty, mut alg, mut public) = (None, None, None);
+        read_map(&mut reader, |label, reader| {
+            match label {
+                KEY_KTY => kty = Some(reader.int()?),
+                KEY_ALG => alg = Some(reader.int()?),
+                KEY_PUB => public = Some(reader.bytes()?),
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+        reader.finish()?;
+
+        match kty.ok_or(CoseError::MissingLabel(KEY_KTY))? {
+            KEY_TYPE_AKP => {}
+            kty => return Err(CoseError::WrongKeyType(kty)),
+        }
+        check_algorithm(expected, alg)?;
+        let public = public.ok_or(CoseError::MissingLabel(KEY_PUB))?;
+        let set = Parameters::sphincs::<K, A, LAYERS, TREE_HEIGHT, N, W, H>().parameter_set().ok_or(CoseError::NoAlgorithm)?;
+        Ok(Self::from_bytes(&[&[KEY_VERSION, set.id()][..], public].concat())?)
+    }
+}
+
+// Builds a tagged COSE_Sign1 with alg and, if set, kid in the protected header
+pub struct CoseSign1Builder<'a> {
+    payload: &'a [u8],
+    kid: Option<&'a [u8]>,
+    external_aad: &'a [u8],
+}
+
+impl<'a> CoseSign1Builder<'a> {
+    pub fn new(payload: &'a [u8]) -> Self {
+        Self { payload, kid: None, external_aad: &[] }
+    }
+
+    pub fn kid(self, kid: &'a [u8]) -> Self {
+        Self { kid: Some(kid), ..self }
+    }
+
+    pub fn external_aad(self, external_aad: &'a [u8]) -> Self {
+        Self { external_aad, ..self }
+    }
+
+    pub fn sign<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite>(
+        self, signer: &SphincsSigner<K, A, LAYERS, TREE_HEIGHT, N, W, H>
+    ) -> Result<Vec<u8>, CoseError> {
+        let alg = algorithm_id(Parameters::sphincs::<K, A, LAYERS, TREE_HEIGHT, N, W, H>())?;
+        let mut headers = vec![(cbor::int(HEADER_ALG), cbor::int(alg))];
+        if let Some(kid) = self.kid {
+            headers.push((cbor::int(HEADER_KID), cbor::bytes(kid)));
+        }
+        let protected = cbor::map(&headers);
+
+        let signature = signer.sign(&signature_input(&protected, self.external_aad, self.payload));
+        let message = cbor::array(&[cbor::bytes(&protected), cbor::map(&[]), cbor::bytes(self.payload), cbor::bytes(&signature.to_bytes())]);
+        Ok([cbor::head(TAG, COSE_SIGN1_TAG), message].concat())
+    }
+}
+
+// A decoded COSE_Sign1. Nothing in it is trusted before verify returns Ok.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoseSign1 {
+    pub alg: Option<i64>,
+    pub kid: Option<Vec<u8>>,
+    pub payload: Vec<u8>,
+    protected: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl CoseSign1 {
+    // Tagged or untagged. alg is only taken from the protected header, kid from
+    // either.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CoseError> {
+        let mut reader = CborReader::new(bytes);
+        if reader.peek_major() == Some(TAG) {
+            match reader.tag()? {
+                COSE_SIGN1_TAG => {}
+                tag => return Err(CoseError::WrongTag(tag)),
+            }
+        }
+        match reader.array()? {
+            4 => {}
+            items => return Err(CoseError::Cbor(CborError::UnexpectedLength(4, items))),
+        }
+
+        let protected = reader.bytes()?;
+        let (mut alg, mut kid) = (None, None);
+        if !protected.is_empty() {
+            let mut headers = CborReader::new(protected);
+            read_map(&mut headers, |label, headers| {
+                match label {
+                    HEADER_ALG => alg = Some(headers.int()?),
+                    HEADER_KID => kid = Some(headers.bytes()?.to_vec()),
+                    _ => return Ok(false),
+                }
+                Ok(true)
+            })?;
+            headers.finish()?;
+        }
+        read_map(&mut reader, |label, reader| {
+            match label {
+                HEADER_KID if kid.is_none() => kid = Some(reader.bytes()?.to_vec()),
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+
+        let payload = match reader.peek_major() {
+            Some(cbor::BYTES) => reader.bytes()?.to_vec(),
+            _ => return Err(CoseError::DetachedPayload),
+        };
+        let signature = reader.bytes()?.to_vec();
+        reader.finish()?;
+        Ok(Self { alg, kid, payload, protected: protected.to_vec(), signature })
+    }
+
+    pub fn verify<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite>(
+        &self, public_key: &SphincsPublic<K, A, LAYERS, TREE_HEIGHT, N, W, H>, external_aad: &[u8]
+    ) -> Result<(), CoseError> {
+        check_algorithm(algorithm_id(public_key.get_params())?, self.alg)?;
+        let signature = SphincsSignature::<K, A, LAYERS, TREE_HEIGHT, N, W, H>::from_bytes(&self.signature)?;
+        match signature.validate(&signature_input(&self.protected, external_aad, &self.payload), public_key) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(CoseError::InvalidSignature),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+
+    use super::*;
+    use crate::lib::components::{parameters::{SphincsSha2_128fPublic, SphincsSha2_128fSigner, SphincsSha2_128sPublic}, sphincs::secret::SigningMode};
+
+    fn signer() -> SphincsSha2_128fSigner {
+        SphincsSha2_128fSigner::new([1u8; 16], [2u8; 16]).with_mode(SigningMode::Deterministic)
+    }
+
+    #[test]
+    fn test_cose_key_fixture() {
+        let public_key = signer().public_key();
+        // {1: 7, 2: h'6b6964', 3: -65602, -1: h'<32 bytes>'}
+        let fixture = [&hex!("a4 01 07 02 43 6b6964 03 3a00010041 20 5820")[..], &public_key.to_bytes()[2..]].concat();
+
+        assert_eq!(public_key.to_cose_key(Some(b"kid")).unwrap(), fixture);
+        assert_eq!(SphincsSha2_128fPublic::from_cose_key(&fixture), Ok(public_key.clone()));
+
+        // Unknown integer and text labels are skipped
+        let extended = [&hex!("a5 01 07 03 3a00010041 63637276 f6 20 5820")[..], &public_key.to_bytes()[2..], &hex!("3863 00")[..]].concat();
+        assert_eq!(SphincsSha2_128fPublic::from_cose_key(&extended), Ok(public_key.clone()));
+        // The value of an unknown label is skipped without recursing into it
+        let deep = [&hex!("a1 3863")[..], &[0x81u8; 1_000_000][..]].concat();
+        assert_eq!(SphincsSha2_128fPublic::from_cose_key(&deep), Err(CoseError::Cbor(CborError::Truncated)));
+        assert_eq!(SphincsSha2_128fPublic::from_cose_key(&hex!("a1 3863 bbffffffffffffffff")), Err(CoseError::Cbor(CborError::IntegerOverflow)));
+
+        // OKP (1) instead of AKP, the alg of another set, no alg at all
+        let okp = [&hex!("a3 01 01 03 3a00010041 20 5820")[..], &public_key.to_bytes()[2..]].concat();
+        assert_eq!(SphincsSha2_128fPublic::from_cose_key(&okp), Err(CoseError::WrongKeyType(1)));
+        let other = [&hex!("a3 01 07 03 3a00010040 20 5820")[..], &public_key.to_bytes()[2..]].concat();
+        assert_eq!(SphincsSha2_128fPublic::from_cose_key(&other), Err(CoseError::WrongAlgorithm(-65602, -65601)));
+        assert!(SphincsSha2_128sPublic::from_cose_key(&other).is_ok());
+        let no_alg = [&hex!("a2 01 07 20 5820")[..], &public_key.to_bytes()[2..]].concat();
+        assert_eq!(SphincsSha2_128fPublic::from_cose_key(&no_alg), Err(CoseError::MissingLabel(HEADER_ALG)));
+    }
+
+    #[test]
+    fn test_sign1_fixture() {
+        let signer = signer();
+        // Protected header {1: -65602, 4: h'6b6964'}, empty external_aad, payload "hello"
+        let protected = hex!("a2 01 3a00010041 04 43 6b6964");
+        let to_be_signed = [&hex!("84 6a 5369676e617475726531 4c")[..], &protected, &hex!("40 45 68656c6c6f")].concat();
+        let signature = signer.sign(&to_be_signed).to_bytes();
+        assert_eq!(signature.len(), 13022);
+        let fixture = [&hex!("d2 84 4c")[..], &protected, &hex!("a0 45 68656c6c6f 59 32de"), &signature].concat();
+
+        let message = CoseSign1Builder::new(b"hello").kid(b"kid").sign(&signer).unwrap();
+        assert_eq!(message, fixture);
+        // and an independent CBOR decoder agrees on the structure
+        match ciborium::from_reader::<ciborium::Value, _>(message.as_slice()).unwrap() {
+            ciborium::Value::Tag(18, inner) => assert_eq!(inner.as_array().map(Vec::len), Some(4)),
+            other => panic!("Not a tagged COSE_Sign1: {other:?}"),
+        }
+
+        let decoded = CoseSign1::from_bytes(&fixture).unwrap();
+        assert_eq!(decoded.alg, Some(-65602));
+        assert_eq!(decoded.kid.as_deref(), Some(&b"kid"[..]));
+        assert_eq!(decoded.payload, b"hello");
+        assert_eq!(decoded.verify(&signer.public_key(), b""), Ok(()));
+        assert_eq!(decoded.verify(&signer.public_key(), b"other aad"), Err(CoseError::InvalidSignature));
+    }
+
+    #[test]
+    fn test_sign1_rejects_tampering() {
+        let signer = signer();
+        let public_key = signer.public_key();
+        let message = CoseSign1Builder::new(b"firmware v2").external_aad(b"device-7").sign(&signer).unwrap();
+        assert_eq!(CoseSign1::from_bytes(&message).unwrap().verify(&public_key, b"device-7"), Ok(()));
+
+        // An untagged message with the kid in the unprotected header
+        let protected = hex!("a1 01 3a00010041");
+        let to_be_signed = [&hex!("84 6a 5369676e617475726531 47")[..], &protected, &hex!("40 43 6d7367")].concat();
+        let signature = signer.sign(&to_be_signed).to_bytes();
+        let untagged = [&hex!("84 47")[..], &protected, &hex!("a1 04 41 01 43 6d7367 59 32de"), &signature].concat();
+        let decoded = CoseSign1::from_bytes(&untagged).unwrap();
+        assert_eq!(decoded.kid, Some(vec![1]));
+        assert_eq!(decoded.verify(&public_key, b""), Ok(()));
+
+        // The payload is covered by the signature
+        let mut tampered = message.clone();
+        let at = tampered.windows(11).position(|window| window == b"firmware v2").unwrap();
+        tampered[at + 10] = b'3';
+        assert_eq!(CoseSign1::from_bytes(&tampered).unwrap().verify(&public_key, b"device-7"), Err(CoseError::InvalidSignature));
+
+        // Another tag, a detached payload
+        assert_eq!(CoseSign1::from_bytes(&[&hex!("d1")[..], &message[1..]].concat()), Err(CoseError::WrongTag(17)));
+        let detached = [&hex!("84 47")[..], &protected, &hex!("a0 f6 40")].concat();
+        assert_eq!(CoseSign1::from_bytes(&detached), Err(CoseError::DetachedPayload));
+        // An array of 3, without the signature
+        let short = [&hex!("83 47")[..], &protected, &hex!("a0 43 6d7367")].concat();
+        assert_eq!(CoseSign1::from_bytes(&short), Err(CoseError::Cbor(CborError::UnexpectedLength(4, 3))));
+    }
+}
@@ -1,11 +1,22 @@
-pub fn message_to_indices<const K: usize, const A: usize>(message: &[u8]) -> [u32; K] {
-    core::array::from_fn(|i| {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndicesError {
+    TooShort(usize, usize), // bytes needed, got
+}
+
+// Splits the first K*A bits of the message into K indices of A bits each.
+// The message must hold at least K*A bits, use a digest expanded to that length.
+pub fn message_to_indices<const K: usize, const A: usize>(message: &[u8]) -> Result<[u32; K], IndicesError> {
+    if message.len() * 8 < K * A {
+        return Err(IndicesError::TooShort((K * A).div_ceil(8), message.len()));
+    }
+    Ok(core::array::from_fn(|i| {
 
         let offset_bits = i * A;
         let bit_idx = offset_bits % 8;
         let byte_idx = offset_bits / 8;
         
-        let b0 = *(message.get(byte_idx)).unwrap_or(&0) as u32; 
+        // The last index may end before the 24 bit window does
+        let b0 = message[byte_idx] as u32; 
         let b1 = *(message.get(byte_idx + 1)).unwrap_or(&0) as u32; 
         let b2 = *(message.get(byte_idx + 2)).unwrap_or(&0) as u32; 
         
@@ -14,6 +25,5 @@ pub fn message_to_indices<const K: usize, const A: usize>(message: &[u8]) -> [u3
         let shift = 24 - (bit_idx + A);
         let mask = (1 << A) - 1;
         (super_byte >> shift) & mask
-    })
-    
-}
\ No newline at end of file
+    }))
+}
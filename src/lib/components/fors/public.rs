@@ -1,45 +1,101 @@
-use crate::lib::{components::{fors::indices::message_to_indices}, helpers::{hasher::{HashContext, hash_array, hash_message}, random_generator::HashData}};
+use std::marker::PhantomData;
+
+use crate::lib::{components::{fors::indices::{IndicesError, message_to_indices}, merkle_tree::secret::climb}, helpers::{encoding::{DecodeError, Reader}, hasher::{HashContext, HashSuite, Sha2Suite}, random_generator::{AddressType, HashData}}};
+
+// Every one of the K trees gets its own key pair address
+pub(super) fn fors_tree_context<const N: usize>(context: &HashContext<N>, tree_idx: usize) -> HashContext<N> {
+    let mut address = context.address.with_type(AddressType::ForsTree);
+    address.key_pair = tree_idx as u32;
+    HashContext { public_seed: context.public_seed, address }
+}
+
+pub(super) fn fors_leaf<H: HashSuite, const N: usize>(secret_key: &HashData<N>, tree: &HashContext<N>, leaf_idx: u32) -> HashData<N> {
+    let mut context = tree.clone();
+    context.address.hash = leaf_idx;
+    H::hash_with_context(&[*secret_key], &context)
+}
+
+pub(super) fn fors_roots_context<const N: usize>(context: &HashContext<N>) -> HashContext<N> {
+    HashContext { public_seed: context.public_seed, address: context.address.with_type(AddressType::ForsRoots) }
+}
+
+// Climbs every tree from its revealed leaf and compresses the K roots into the public key
+pub(crate) fn public_key_from_elements<H: HashSuite, const K: usize, const N: usize>(elements: impl Iterator<Item = (HashData<N>, impl IntoIterator<Item = HashData<N>>)>, indices: [u32; K], context: &HashContext<N>) -> HashData<N> {
+    let roots = elements.zip(indices).enumerate().map(|(i, ((secret_key, auth_path), idx))| {
+        let tree = fors_tree_context(context, i);
+        climb::<H, N>(fors_leaf::<H, N>(&secret_key, &tree, idx), idx as usize, auth_path, &tree)
+    });
+    H::hash_iter_with_context(roots, &fors_roots_context(context))
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct ForsSignatureElement<const A: usize> {
-    pub secret_key: HashData,
-    pub auth_path: [HashData; A],
+pub struct ForsSignatureElement<const A: usize, const N: usize = 32> {
+    pub secret_key: HashData<N>,
+    pub auth_path: [HashData<N>; A],
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct ForsSignature<const K: usize, const A: usize> {
-    pub signatures: [ForsSignatureElement<A>; K],
-    pub context: HashContext,
-    pub public_key: HashData
+pub struct ForsSignature<const K: usize, const A: usize, const N: usize = 32, H: HashSuite = Sha2Suite> {
+    pub signatures: [ForsSignatureElement<A, N>; K],
+    pub context: HashContext<N>,
+    pub public_key: HashData<N>,
+    pub(crate) suite: PhantomData<H>,
 }
-impl<const K: usize, const A: usize> ForsSignature<K, A> {
-    pub fn get_expected_public_from_hash(self, indices: [u32; K]) -> HashData {
-        let hashed_collection:[HashData; K] = std::array::from_fn(|i|{
-            let signature = &self.signatures[i];
-            let mut idx = indices[i];
-            let mut hashed_level = hash_message(&signature.secret_key);
-            for j in 0..A {
-                let pair = if idx % 2 == 1{
-                    [signature.auth_path[j], hashed_level, self.context.public_seed]
-                } else{
-                    [hashed_level, signature.auth_path[j], self.context.public_seed]
-                };
-                hashed_level = hash_array(&pair);
-                idx /= 2;
-            };
-            hashed_level
+impl<const A: usize, const N: usize> ForsSignatureElement<A, N> {
+    pub const fn size() -> usize {
+        (1 + A) * N
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [self.secret_key.as_slice(), self.auth_path.as_flattened()].concat()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes, Self::size())?;
+        let secret_key = reader.array();
+        let auth_path = core::array::from_fn(|_| reader.array());
+        Ok(Self { secret_key, auth_path })
+    }
+}
+
+impl<const K: usize, const A: usize, const N: usize, H: HashSuite> ForsSignature<K, A, N, H> {
+    pub const fn size() -> usize {
+        K * ForsSignatureElement::<A, N>::size() + HashContext::<N>::size() + N
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::size());
+        self.signatures.iter().for_each(|element| out.extend(element.to_bytes()));
+        out.extend(self.context.to_bytes());
+        out.extend_from_slice(&self.public_key);
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes, Self::size())?;
+        let signatures = core::array::from_fn(|_| {
+            ForsSignatureElement::from_bytes(reader.take(ForsSignatureElement::<A, N>::size())).expect("The element length is fixed")
         });
-        hash_array(&hashed_collection)
-    } 
+        let context = HashContext::from_bytes(reader.take(HashContext::<N>::size()))?;
+        let public_key = reader.array();
+        Ok(Self { signatures, context, public_key, suite: PhantomData })
+    }
 
-    pub fn validate(self, message: &[u8], public_key: HashData) -> bool {
-        let indices = message_to_indices::<K, A>(message);
+    pub fn get_expected_public_from_hash(self, indices: [u32; K]) -> HashData<N> {
+        let elements = self.signatures.iter().map(|element| (element.secret_key, element.auth_path));
+        public_key_from_elements::<H, K, N>(elements, indices, &self.context)
+    }
+
+    // Whether the signature of `message` leads to `public_key`. A message shorter
+    // than K*A bits is an error, not an invalid signature.
+    pub fn validate(self, message: &[u8], public_key: HashData<N>) -> Result<bool, IndicesError> {
+        let indices = message_to_indices::<K, A>(message)?;
 
-        self.get_expected_public_from_hash(indices) == public_key
+        Ok(self.get_expected_public_from_hash(indices) == public_key)
     }
 
     
-    pub fn validate_self(self, message: &[u8]) -> bool {
+    pub fn validate_self(self, message: &[u8]) -> Result<bool, IndicesError> {
         let public_key = self.public_key;
         self.validate(message, public_key)
     }
@@ -49,28 +105,42 @@ impl<const K: usize, const A: usize> ForsSignature<K, A> {
 
 #[cfg(test)]
 mod tests {
-    use crate::lib::{components::fors::secret::Fors, helpers::{hasher::{HashContext, hash_message}, random_generator::{Address, HASH_DATA_0, HashData}}};
+    use crate::lib::{components::fors::{indices::IndicesError, public::ForsSignature, secret::Fors}, helpers::{encoding::DecodeError, hasher::{HashContext, hash_message}, random_generator::{Address, HASH_DATA_0, HashData}}};
 
     #[test]
     fn test_signature_validation() {
         // Set 2 messages, one will be signed, and one will be checked against the signature, and fail
-        const MESSAGE:&[u8] = "Hello from rust".as_bytes();
-        const OTHER_MESSAGE:&[u8] = "HEllo from rust".as_bytes();
+        // FORS signs digests of at least K*A bits
+        let message = hash_message("Hello from rust".as_bytes());
+        let other_message = hash_message("HEllo from rust".as_bytes());
         let seed:HashData = hash_message("Secret rust stuff".as_bytes());
 
         // Sign the message using Fors.sign()
         let fors: Fors<14, 10> = Fors::new(seed, HashContext { public_seed: HASH_DATA_0, address: Address::default() });
         let public_key = fors.generate_public_key();
-        let signature = fors.sign(MESSAGE);
+        let signature = fors.sign(&message).unwrap();
         // Validate the signatue for the right message using ForsSignature.validate()
-        assert!(signature.clone().validate(MESSAGE, public_key));
+        assert_eq!(signature.clone().validate(&message, public_key), Ok(true));
         
         // Validate the signatue fails for the wrong message using ForsSignature.validate()
-        assert!(!signature.clone().validate(OTHER_MESSAGE, public_key));
+        assert_eq!(signature.clone().validate(&other_message, public_key), Ok(false));
 
         // Validate the signatue fails for the right message using ForsSignature.validate() with garbeled key 
-        let mut garbeled_key = public_key.clone();
+        let mut garbeled_key = public_key;
         garbeled_key[2] += 1;
-        assert!(!signature.clone().validate(MESSAGE, garbeled_key));
+        assert_eq!(signature.clone().validate(&message, garbeled_key), Ok(false));
+        assert_eq!(signature.clone().validate(&message[..16], public_key), Err(IndicesError::TooShort(18, 16)));
+    }
+
+    #[test]
+    fn test_signature_to_from_bytes() {
+        let fors: Fors<4, 6, 16> = Fors::new([1u8; 16], HashContext { public_seed: [2u8; 16], address: Address { level: 0, position: 5, ..Address::default() } });
+        let signature = fors.sign(&[0xA5u8; 3]).unwrap();
+
+        let bytes = signature.to_bytes();
+        assert_eq!(bytes.len(), ForsSignature::<4, 6, 16>::size());
+        assert_eq!(bytes.len(), 4 * 7 * 16 + 50 + 16);
+        assert_eq!(ForsSignature::<4, 6, 16>::from_bytes(&bytes), Ok(signature));
+        assert_eq!(ForsSignature::<4, 6, 16>::from_bytes(&[0u8; 10]), Err(DecodeError::WrongLength(bytes.len(), 10)));
     }
 }
\ No newline at end of file
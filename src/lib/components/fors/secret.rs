@@ -1,70 +1,75 @@
 
-use sha2::{Digest, Sha256};
-use sha2::digest::Update;
+use std::marker::PhantomData;
 
-use crate::lib::components::fors::indices::message_to_indices;
-use crate::lib::components::fors::public::{ForsSignature, ForsSignatureElement};
+use crate::lib::components::fors::indices::{IndicesError, message_to_indices};
+use crate::lib::components::fors::public::{ForsSignature, ForsSignatureElement, fors_leaf, fors_roots_context, fors_tree_context};
 use crate::lib::components::merkle_tree::secret::pair_keys;
-use crate::lib::helpers::hasher::{HashContext, hash_message};
-use crate::lib::helpers::random_generator::{HashData, InnerKeyRole, get_key};
-
-pub struct Fors<const K: usize, const A: usize> {
-    seed: HashData,
-    context: HashContext,
-    keys_per_tree: usize
+use crate::lib::helpers::hasher::{HashContext, HashSuite, Sha2Suite};
+use crate::lib::helpers::random_generator::{AddressType, HashData, InnerKeyRole, get_key};
+
+pub struct Fors<const K: usize, const A: usize, const N: usize = 32, H: HashSuite = Sha2Suite> {
+    seed: HashData<N>,
+    context: HashContext<N>,
+    keys_per_tree: usize,
+    suite: PhantomData<H>,
 }
 
-impl<const K: usize, const A: usize> Fors<K, A> {
+impl<const K: usize, const A: usize, const N: usize, H: HashSuite> Fors<K, A, N, H> {
     // A means the HEIGHT of the tree, as suggested in FIPS 205
-    pub fn new(seed: HashData, context: HashContext) -> Self {
+    pub fn new(seed: HashData<N>, context: HashContext<N>) -> Self {
         let keys_per_tree = (2_usize).pow(A as u32);
-        Self { seed, context, keys_per_tree }
+        Self { seed, context, keys_per_tree, suite: PhantomData }
     }
 
-    fn generate_pseoudo_random_keys(&self, tree_idx: u64) -> Vec<HashData> {
-            let mut keys: Vec<HashData> = vec!();
+    fn generate_pseoudo_random_keys(&self, tree_idx: u64) -> Vec<HashData<N>> {
+            let mut keys: Vec<HashData<N>> = vec!();
             
             for j in 0..self.keys_per_tree {
                 let combined_idx = (tree_idx as usize) * self.keys_per_tree + j;
-                let key = get_key(self.seed, &self.context.address, &InnerKeyRole::Fors, combined_idx);
+                let key = get_key::<H, N>(self.seed, &self.context.address.with_type(AddressType::ForsPrf), &InnerKeyRole::Fors, combined_idx);
                 keys.push(key);
             };
             keys
     }
-    pub fn generate_public_key(&self) -> HashData {
-        let mut roots = Sha256::new();
-
-        for i in 0..K {
-            let mut keys = self.generate_pseoudo_random_keys(i as u64);
-            keys = keys.into_iter().map(|key| hash_message(&key)).collect();
-            for _ in 0..A {
-                keys = pair_keys(&keys, self.context.public_seed);
+    pub fn generate_public_key(&self) -> HashData<N> {
+        let roots: Vec<HashData<N>> = (0..K).map(|i| {
+            let tree = fors_tree_context(&self.context, i);
+            let mut keys = self.generate_leaves(i);
+            for height in 1..=A {
+                keys = pair_keys::<H, N>(&keys, &tree, height);
             }
-
-            Update::update(&mut roots, &keys[0])
-        };
-        roots.finalize().into()
+            keys[0]
+        }).collect();
+        H::hash_with_context(&roots, &fors_roots_context(&self.context))
         
     }
-    pub fn sign(&self, message: &[u8]) -> ForsSignature<K, A>{
-        let indices = message_to_indices::<K, A>(message);
+    pub fn sign(&self, message: &[u8]) -> Result<ForsSignature<K, A, N, H>, IndicesError> {
+        let indices = message_to_indices::<K, A>(message)?;
         let signatures = std::array::from_fn(|tree_idx| {
             let index = indices[tree_idx];
             let secret_keys = self.generate_pseoudo_random_keys(tree_idx as u64);
             let secret_key = secret_keys[index as usize];
-            let auth_path = self.get_auth_path(&secret_keys, index);
+            let auth_path = self.get_auth_path(tree_idx, index);
 
             ForsSignatureElement{secret_key, auth_path}
         });
-        ForsSignature {signatures, context: self.context.clone(), public_key: self.generate_public_key()}
+        Ok(ForsSignature {signatures, context: self.context.clone(), public_key: self.generate_public_key(), suite: PhantomData})
+    }
+    fn generate_leaves(&self, tree_idx: usize) -> Vec<HashData<N>> {
+        let tree = fors_tree_context(&self.context, tree_idx);
+        self.generate_pseoudo_random_keys(tree_idx as u64).iter().enumerate()
+            .map(|(leaf_idx, key)| fors_leaf::<H, N>(key, &tree, leaf_idx as u32))
+            .collect()
     }
-    pub(super)fn get_auth_path(&self, secret_keys: &[HashData], mut leaf_idx: u32) -> [HashData; A] {
-        let mut keys: Vec<HashData> = secret_keys.iter().map(|key| hash_message(key)).collect();
+
+    pub(super)fn get_auth_path(&self, tree_idx: usize, mut leaf_idx: u32) -> [HashData<N>; A] {
+        let tree = fors_tree_context(&self.context, tree_idx);
+        let mut keys = self.generate_leaves(tree_idx);
         
-        core::array::from_fn(|_| {
+        core::array::from_fn(|height| {
             let neighbor_idx = leaf_idx ^ 1;
             let ret_val = keys[neighbor_idx as usize];
-            keys = pair_keys(&keys, self.context.public_seed);
+            keys = pair_keys::<H, N>(&keys, &tree, height + 1);
             leaf_idx /= 2;
             ret_val
         })
@@ -74,7 +79,10 @@ impl<const K: usize, const A: usize> Fors<K, A> {
 #[cfg(test)]
 mod tests {
 
-    use crate::lib::helpers::{hasher::{hash_array, hash_message}, random_generator::{Address, HASH_DATA_0}};
+    use crate::lib::helpers::{hasher::hash_message, random_generator::{Address, HASH_DATA_0}};
+    use crate::lib::helpers::hasher::Sha2Suite;
+
+    use crate::lib::components::merkle_tree::secret::node_hash;
 
     use super::*;
     use super::super::indices::message_to_indices;
@@ -83,33 +91,41 @@ mod tests {
     fn test_message_to_indices_all_0() {
         let message = [0u8; 202];
 
-        let indices = message_to_indices::<10, 14>(&message);
+        let indices = message_to_indices::<10, 14>(&message).unwrap();
         assert_eq!(indices, [0u32; 10]);
+    }
 
-        let message = [0u8; 1];
+    #[test]
+    fn test_message_to_indices_too_short() {
+        // 10 * 14 bits need 18 bytes
+        assert_eq!(message_to_indices::<10, 14>(&[0xFFu8; 17]), Err(IndicesError::TooShort(18, 17)));
 
-        let indices = message_to_indices::<10, 14>(&message);
-        assert_eq!(indices, [0u32; 10]);
+        let fors: Fors<10, 14> = Fors::new(HASH_DATA_0, HashContext::default());
+        assert_eq!(fors.sign(&[0xFFu8; 17]).err(), Some(IndicesError::TooShort(18, 17)));
+    }
+
+    #[test]
+    fn test_message_to_indices_exact_length() {
+        let indices = message_to_indices::<10, 14>(&[0xFFu8; 18]);
+        assert_eq!(indices, Ok([0x3FFF; 10]));
+
+        // 22 * 14 bits are more than a 32 byte hash holds
+        let indices = message_to_indices::<22, 14>(&[0xAAu8; 39]);
+        assert_eq!(indices, Ok([0x2AAAu32; 22]));
     }
 
     #[test]
     fn test_message_to_indices_all_1() {
         let message = [0xFFu8; 202020];
         // אנחנו מגדירים K=1 ו-A=14 באופן מפורש
-        let indices = message_to_indices::<10, 14>(&message);
+        let indices = message_to_indices::<10, 14>(&message).unwrap();
         assert_eq!(indices, [0x3FFF; 10]);
-        
-        let message = [0xFFu8; 1];
-        // אנחנו מגדירים K=1 ו-A=14 באופן מפורש
-        let indices = message_to_indices::<10, 14>(&message);
-        let expected = [0x3FC0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
-        assert_eq!(indices, expected);
     }
     
     #[test]
     fn test_message_to_indices_real_hash() {
         let message = hash_message("This is a real world message to sign".as_bytes());
-        let indices = message_to_indices::<10, 14>(&message);
+        let indices = message_to_indices::<10, 14>(&message).unwrap();
         let expected = [7426, 10818, 5414, 14732, 10730, 10731, 11712, 1719, 11223, 6407];
         assert_eq!(indices, expected);
     }
@@ -117,36 +133,31 @@ mod tests {
     fn test_message_to_indices_mixed() {
         let message = [0xAAu8; 202];
         // אנחנו מגדירים K=1 ו-A=14 באופן מפורש
-        let indices = message_to_indices::<10, 14>(&message);
+        let indices = message_to_indices::<10, 14>(&message).unwrap();
         assert_eq!(indices, [0x2AAAu32; 10]);
 
-        let message = [0xAAu8; 1];
-        // אנחנו מגדירים K=1 ו-A=14 באופן מפורש
-        let indices = message_to_indices::<10, 14>(&message);
-        assert_eq!(indices, [0x2A80, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
-
         let message = [0xAAu8, 0xFFu8, 0xBBu8, 0x00u8];
         // אנחנו מגדירים K=1 ו-A=14 באופן מפורש
-        let indices = message_to_indices::<10, 14>(&message);
-        assert_eq!(indices, [10943, 15280, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let indices = message_to_indices::<2, 14>(&message).unwrap();
+        assert_eq!(indices, [10943, 15280]);
     }
 
     #[test]
     fn test_get_public_key() {
         let fors1:Fors<4, 8> = Fors::new(hash_message("Hello".as_bytes()), HashContext { public_seed: hash_message("Bye".as_bytes()), 
-            address: Address { level: 0, position: 0 }});
+            address: Address { level: 0, position: 0, ..Address::default() }});
         let pubk1 = fors1.generate_public_key();
 
         let fors2:Fors<4, 8> = Fors::new(hash_message("Hello".as_bytes()), HashContext { public_seed: hash_message("Bye".as_bytes()), 
-            address: Address { level: 0, position: 0 }});
+            address: Address { level: 0, position: 0, ..Address::default() }});
         let pubk2 = fors2.generate_public_key();
 
         let fors3:Fors<4, 8> = Fors::new(hash_message("HellO".as_bytes()), HashContext { public_seed: hash_message("Bye".as_bytes()), 
-            address: Address { level: 0, position: 0 }});
+            address: Address { level: 0, position: 0, ..Address::default() }});
         let pubk3 = fors3.generate_public_key();
 
         let fors4:Fors<4, 8> = Fors::new(hash_message("HellO".as_bytes()), HashContext { public_seed: hash_message("ByE".as_bytes()), 
-            address: Address { level: 0, position: 0 }});
+            address: Address { level: 0, position: 0, ..Address::default() }});
         let pubk4 = fors4.generate_public_key();
 
         assert_eq!(pubk1, pubk2);
@@ -155,7 +166,7 @@ mod tests {
 
         // בדיקה שהוספנו: שינוי ב-Address בלבד חייב לשנות את המפתח הציבורי
         let fors5:Fors<4, 8> = Fors::new(hash_message("Hello".as_bytes()), HashContext { public_seed: hash_message("Bye".as_bytes()),
-            address: Address { level: 0, position: 1 }}); // מיקום שונה
+            address: Address { level: 0, position: 1, ..Address::default() }}); // מיקום שונה
         let pubk5 = fors5.generate_public_key();
         
         // לפני התיקון, זה היה נכשל (הם היו שווים)
@@ -166,10 +177,12 @@ mod tests {
     fn test_sign_basic_integrity() {
         let fors: Fors<4, 4> = Fors::new(HASH_DATA_0, HashContext { public_seed: HASH_DATA_0, address: Address::default() });
         let expected_keys_tree_0 = fors.generate_pseoudo_random_keys(0);
-        let signature = fors.sign(&HASH_DATA_0);
+        let signature = fors.sign(&HASH_DATA_0).unwrap();
+
+        let tree_0 = fors_tree_context(&HashContext { public_seed: HASH_DATA_0, address: Address::default() }, 0);
 
         assert_eq!(signature.signatures[0].secret_key, expected_keys_tree_0[0]);
-        assert_eq!(signature.signatures[0].auth_path[0], hash_message(&expected_keys_tree_0[1]));
+        assert_eq!(signature.signatures[0].auth_path[0], fors_leaf::<Sha2Suite, 32>(&expected_keys_tree_0[1], &tree_0, 1));
     }
 
     #[test]
@@ -177,28 +190,18 @@ mod tests {
         let ctx = HashContext { public_seed: HASH_DATA_0, address: Address::default() };
         let fors: Fors<4, 4> = Fors::new(HASH_DATA_0, ctx.clone());
         let secret_keys:Vec<HashData> = fors.generate_pseoudo_random_keys(0);
+        let tree = fors_tree_context(&ctx, 0);
         let idx = 1;
-        let auth_path = fors.get_auth_path(&secret_keys, idx);
+        let auth_path = fors.get_auth_path(0, idx);
 
-        let mut current_hash = hash_message(&secret_keys[idx as usize]);
-
-        for i in 0..4 {
-            let sibling = auth_path[i];
-
-            let pair = if idx % 2 == 0 {
-                [current_hash, sibling]
-            } else {
-                [sibling, current_hash]
-            };
-            current_hash = hash_array(&[pair[0], pair[1], ctx.public_seed]);
-        }
-
-        assert_eq!(auth_path[0], hash_message(&secret_keys[0]));
+        assert_eq!(auth_path[0], fors_leaf::<Sha2Suite, 32>(&secret_keys[0], &tree, 0));
         
-        let leaf2 = hash_message(&secret_keys[2]);
-        let leaf3 = hash_message(&secret_keys[3]);
+        let leaf2 = fors_leaf::<Sha2Suite, 32>(&secret_keys[2], &tree, 2);
+        let leaf3 = fors_leaf::<Sha2Suite, 32>(&secret_keys[3], &tree, 3);
+
+        assert_eq!(auth_path[1], node_hash::<Sha2Suite, 32>(leaf2, leaf3, &tree, 1, 1));
 
-        let pair = vec![leaf2, leaf3];
-        assert_eq!(auth_path[1], pair_keys(&pair, ctx.public_seed)[0]);
+        // The same key in another tree hashes to another leaf
+        assert_ne!(fors_leaf::<Sha2Suite, 32>(&secret_keys[0], &fors_tree_context(&ctx, 1), 0), auth_path[0]);
     }
 }
\ No newline at end of file
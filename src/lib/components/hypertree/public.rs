@@ -1,18 +1,38 @@
-use crate::lib::{components::merkle_tree::proof::MerkleProof, helpers::random_generator::HashData};
+use crate::lib::{components::merkle_tree::proof::MerkleProof, helpers::{encoding::{DecodeError, Reader}, hasher::{HashSuite, Sha2Suite}, random_generator::HashData}};
 
-pub enum FailedValidation {
-    Proof(usize, HashData, HashData),
-    PublicKey(HashData, HashData)
+pub enum FailedValidation<const N: usize = 32> {
+    Proof(usize, HashData<N>, HashData<N>),
+    PublicKey(HashData<N>, HashData<N>)
 }
 
 #[derive(Clone, PartialEq, Debug)]
-pub struct HyperTreeSignature<const LAYERS: usize, const TREE_HEIGHT: usize> {
-    pub proofs: [MerkleProof<TREE_HEIGHT>; LAYERS],
-    pub public_key: HashData
+pub struct HyperTreeSignature<const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize = 32, const W: usize = 256, H: HashSuite = Sha2Suite> {
+    pub proofs: [MerkleProof<TREE_HEIGHT, N, W, H>; LAYERS],
+    pub public_key: HashData<N>
 }
 
-impl<const LAYERS: usize, const TREE_HEIGHT: usize> HyperTreeSignature<LAYERS, TREE_HEIGHT> {
-    pub fn get_expected_public_key(self, fors_public_key: HashData) -> Result<HashData, FailedValidation> {
+impl<const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite> HyperTreeSignature<LAYERS, TREE_HEIGHT, N, W, H> {
+    pub const fn size() -> usize {
+        LAYERS * MerkleProof::<TREE_HEIGHT, N, W, H>::size() + N
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::size());
+        self.proofs.iter().for_each(|proof| out.extend(proof.to_bytes()));
+        out.extend_from_slice(&self.public_key);
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes, Self::size())?;
+        let proofs: Vec<MerkleProof<TREE_HEIGHT, N, W, H>> = (0..LAYERS)
+            .map(|_| MerkleProof::from_bytes(reader.take(MerkleProof::<TREE_HEIGHT, N, W, H>::size())))
+            .collect::<Result<_, _>>()?;
+        let public_key = reader.array();
+        Ok(Self { proofs: proofs.try_into().expect("There is a proof for every layer"), public_key })
+    }
+
+    pub fn get_expected_public_key(self, fors_public_key: HashData<N>) -> Result<HashData<N>, FailedValidation<N>> {
         let mut testing_key = fors_public_key;
         for i in 0..LAYERS {
             if !self.proofs[i].clone().validate_self(&testing_key) {
@@ -22,7 +42,7 @@ impl<const LAYERS: usize, const TREE_HEIGHT: usize> HyperTreeSignature<LAYERS, T
         };
         Ok(testing_key)
     }
-    pub fn validate(self, fors_public_key: HashData, public_key: HashData) -> Result<HashData, FailedValidation> {
+    pub fn validate(self, fors_public_key: HashData<N>, public_key: HashData<N>) -> Result<HashData<N>, FailedValidation<N>> {
         let testing_key = self.get_expected_public_key(fors_public_key)?;
         match public_key == testing_key {
             true => Ok(public_key),
@@ -33,7 +53,7 @@ impl<const LAYERS: usize, const TREE_HEIGHT: usize> HyperTreeSignature<LAYERS, T
 
 #[cfg(test)]
 mod tests {
-    use crate::lib::{components::hypertree::secret::HyperTreeSigner, helpers::hasher::hash_message};
+    use crate::lib::{components::hypertree::{public::HyperTreeSignature, secret::HyperTreeSigner}, helpers::{encoding::DecodeError, hasher::hash_message}};
     
     #[test]
     fn test_validation_success() {
@@ -53,13 +73,13 @@ mod tests {
         let seed = hash_message("The secret_of_nim".as_bytes());
         let public_seed = hash_message("Never gonna tell you".as_bytes());
         let fors_public_key = hash_message("Drink my juice".as_bytes());
-        let mut fake_fors_public_key = fors_public_key.clone();
-        fake_fors_public_key[2] = fake_fors_public_key[2]>>1;
+        let mut fake_fors_public_key = fors_public_key;
+        fake_fors_public_key[2] >>= 1;
 
         let htree = HyperTreeSigner::<2, 3>::new(seed, public_seed);
         let public_key = htree.generate_master_public_key();
         let mut bad_public_key = htree.generate_master_public_key();
-        bad_public_key[3] = bad_public_key[3] + 1;
+        bad_public_key[3] += 1;
 
         let signature1 = htree.clone().sign(fors_public_key, 10);
 
@@ -67,4 +87,19 @@ mod tests {
         assert!(signature1.clone().validate(fake_fors_public_key, public_key).is_err());
 
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_signature_to_from_bytes() {
+        let htree = HyperTreeSigner::<2, 3, 16, 16>::new([1u8; 16], [2u8; 16]);
+        let signature = htree.clone().sign([3u8; 16], 10);
+
+        let mut bytes = signature.to_bytes();
+        assert_eq!(bytes.len(), HyperTreeSignature::<2, 3, 16, 16>::size());
+        assert_eq!(HyperTreeSignature::<2, 3, 16, 16>::from_bytes(&bytes), Ok(signature.clone()));
+        assert!(HyperTreeSignature::<2, 3, 16, 16>::from_bytes(&bytes).unwrap().validate([3u8; 16], htree.generate_master_public_key()).is_ok());
+
+        // The address type of the first WOTS+ context, after the proof's public key and the public seed
+        bytes[16 + 16 + 18] = 9;
+        assert_eq!(HyperTreeSignature::<2, 3, 16, 16>::from_bytes(&bytes), Err(DecodeError::UnknownAddressType(9)));
+    }
+}
@@ -1,19 +1,22 @@
-use crate::lib::{components::{hypertree::public::HyperTreeSignature, merkle_tree::secret::MerkleSigner}, helpers::{hasher::HashContext, random_generator::{Address, HashData}}};
+use std::marker::PhantomData;
+
+use crate::lib::{components::{hypertree::public::HyperTreeSignature, merkle_tree::secret::MerkleSigner}, helpers::{hasher::{HashContext, HashSuite, Sha2Suite}, random_generator::{Address, HashData}}};
 
 #[derive(Clone, PartialEq)]
-pub struct HyperTreeSigner<const LAYERS: usize, const TREE_HEIGHT: usize> {
-    seed: HashData,
-    pub public_seed: HashData,
+pub struct HyperTreeSigner<const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize = 32, const W: usize = 256, H: HashSuite = Sha2Suite> {
+    seed: HashData<N>,
+    pub public_seed: HashData<N>,
+    suite: PhantomData<H>,
 }
 
-impl<const LAYERS: usize, const TREE_HEIGHT: usize> HyperTreeSigner<LAYERS, TREE_HEIGHT>{
-    pub fn new(seed: HashData, public_seed: HashData) -> Self {
-        Self {seed, public_seed}
+impl<const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite> HyperTreeSigner<LAYERS, TREE_HEIGHT, N, W, H>{
+    pub fn new(seed: HashData<N>, public_seed: HashData<N>) -> Self {
+        Self {seed, public_seed, suite: PhantomData}
     }
     
-    pub fn generate_master_public_key(&self) -> HashData {
-        let context = HashContext{ public_seed: self.public_seed, address: Address {level: (LAYERS - 1) as u16, position: 0} };
-        let signer = MerkleSigner::<TREE_HEIGHT>::new(self.seed, context);
+    pub fn generate_master_public_key(&self) -> HashData<N> {
+        let context = HashContext{ public_seed: self.public_seed, address: Address { level: (LAYERS - 1) as u16, position: 0, ..Address::default() } };
+        let signer = MerkleSigner::<TREE_HEIGHT, N, W, H>::new(self.seed, context);
         
         let (public_key, _) = signer.get_public_key_and_proof();
         public_key
@@ -23,16 +26,16 @@ impl<const LAYERS: usize, const TREE_HEIGHT: usize> HyperTreeSigner<LAYERS, TREE
         index >> (i * TREE_HEIGHT)
     }
 
-    pub fn sign(self, fors_public_key: HashData, index: u128) -> HyperTreeSignature<LAYERS, TREE_HEIGHT> {
+    pub fn sign(self, fors_public_key: HashData<N>, index: u128) -> HyperTreeSignature<LAYERS, TREE_HEIGHT, N, W, H> {
         let mut current_message = fors_public_key;
         
         let proofs = core::array::from_fn(|i| {
             let pos = self.clone().get_tree_pos(index, i);
             let context = HashContext { 
                 public_seed: self.public_seed, 
-                address: Address { level: i as u16, position: pos } 
+                address: Address { level: i as u16, position: pos, ..Address::default() } 
             };
-            let signer = MerkleSigner::<TREE_HEIGHT>::new(self.seed, context);
+            let signer = MerkleSigner::<TREE_HEIGHT, N, W, H>::new(self.seed, context);
             let proof = signer.sign(&current_message);
             current_message = proof.public_key;
             proof
@@ -95,10 +98,10 @@ mod tests {
         assert_eq!(signature1.proofs[0].clone().get_height(), 3);
         assert_ne!(&signature1, &signature2);
         assert_ne!(&signature1, &signature3);
-        assert_eq!(pub_key, [221, 80, 43, 100, 45, 35, 
-            185, 131, 120, 166, 163, 29, 
-            157, 110, 71, 255, 12, 157, 
-            212, 185, 29, 220, 144, 0, 
-            42, 199, 230, 105, 177, 246, 219, 59])
+        assert_eq!(pub_key, [71, 42, 248, 232, 218, 119, 
+            30, 12, 158, 77, 194, 115, 
+            191, 0, 22, 54, 1, 23, 
+            1, 36, 211, 70, 220, 253, 
+            134, 204, 196, 3, 141, 174, 221, 215])
     }
 }
\ No newline at end of file
@@ -0,0 +1,214 @@
+// JOSE for the custom scheme: AKP ("Algorithm Key Pair") JWKs as in the JOSE
+// post-quantum drafts, and JWS compact serialization.
+//
+// The `alg` of a key or token is the name of its parameter set, e.g.
+// SPHINCS-SHA2-128f. The registered SLH-DSA names are not used, since signatures
+// of the custom scheme do not verify as SLH-DSA signatures.
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde_json::{Map, Value, json};
+
+use crate::lib::{
+    components::{
+        parameters::{ParameterSet, Parameters},
+        sphincs::{public::{KEY_VERSION, SphincsPublic}, secret::SphincsSigner, signature::SphincsSignature}
+    },
+    helpers::{encoding::DecodeError, hasher::HashSuite}
+};
+
+pub const KEY_TYPE: &str = "AKP";
+
+#[derive(Debug, PartialEq)]
+pub enum JoseError {
+    Json,
+    Base64(&'static str), // the member that is not base64url
+    MissingMember(&'static str),
+    WrongKeyType(String),
+    WrongAlgorithm(&'static str, String), // expected, got
+    NoAlgorithm, // the const generic parameters are not a named set
+    Key(DecodeError),
+    KeyMismatch, // "pub" is not the public key of "priv"
+    MalformedToken,
+    UnsupportedCritical,
+    InvalidSignature,
+}
+
+impl From<DecodeError> for JoseError {
+    fn from(err: DecodeError) -> Self {
+        JoseError::Key(err)
+    }
+}
+
+fn algorithm(params: Parameters) -> Result<ParameterSet, JoseError> {
+    params.parameter_set().ok_or(JoseError::NoAlgorithm)
+}
+
+fn parse_object(json: &[u8]) -> Result<Map<String, Value>, JoseError> {
+    match serde_json::from_slice(json) {
+        Ok(Value::Object(object)) => Ok(object),
+        _ => Err(JoseError::Json),
+    }
+}
+
+fn member<'a>(object: &'a Map<String, Value>, name: &'static str) -> Result<&'a str, JoseError> {
+    object.get(name).ok_or(JoseError::MissingMember(name))?.as_str().ok_or(JoseError::Json)
+}
+
+fn decode_member(object: &Map<String, Value>, name: &'static str) -> Result<Vec<u8>, JoseError> {
+    URL_SAFE_NO_PAD.decode(member(object, name)?).map_err(|_| JoseError::Base64(name))
+}
+
+fn check_algorithm(object: &Map<String, Value>, set: ParameterSet) -> Result<(), JoseError> {
+    match member(object, "alg")? {
+        alg if alg == set.name() => Ok(()),
+        alg => Err(JoseError::WrongAlgorithm(set.name(), alg.to_string())),
+    }
+}
+
+// Checks kty and alg, and returns the raw PK.seed || PK.root
+fn read_jwk(jwk: &str, set: ParameterSet) -> Result<(Map<String, Value>, Vec<u8>), JoseError> {
+    let object = parse_object(jwk.as_bytes())?;
+    match member(&object, "kty")? {
+        KEY_TYPE => {}
+        kty => return Err(JoseError::WrongKeyType(kty.to_string())),
+    }
+    check_algorithm(&object, set)?;
+    let public = decode_member(&object, "pub")?;
+    Ok((object, public))
+}
+
+impl<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite> SphincsPublic<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+    // {"kty":"AKP","alg":...,"pub":base64url(PK.seed || PK.root)}
+    pub fn to_jwk(&self) -> Result<String, JoseError> {
+        let set = algorithm(self.get_params())?;
+        Ok(json!({"kty": KEY_TYPE, "alg": set.name(), "pub": URL_SAFE_NO_PAD.encode(&self.to_bytes()[2..])}).to_string())
+    }
+
+    pub fn from_jwk(jwk: &str) -> Result<Self, JoseError> {
+        let set = algorithm(Parameters::sphincs::<K, A, LAYERS, TREE_HEIGHT, N, W, H>())?;
+        let (_, public) = read_jwk(jwk, set)?;
+        Ok(Self::from_bytes(&[&[KEY_VERSION, set.id()][..], &public].concat())?)
+    }
+
+    // Checks the token's alg against this key and returns the payload
+    pub fn verify_jws(&self, token: &str) -> Result<Vec<u8>, JoseError> {
+        let set = algorithm(self.get_params())?;
+        let (signing_input, signature) = token.rsplit_once('.').ok_or(JoseError::MalformedToken)?;
+        let (header, payload) = signing_input.split_once('.').ok_or(JoseError::MalformedToken)?;
+
+        let header = parse_object(&URL_SAFE_NO_PAD.decode(header).map_err(|_| JoseError::Base64("header"))?)?;
+        check_algorithm(&header, set)?;
+        // RFC 7515 4.1.11, none of the extensions are understood here
+        if header.contains_key("crit") {
+            return Err(JoseError::UnsupportedCritical);
+        }
+
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| JoseError::Base64("signature"))?;
+        let signature = SphincsSignature::<K, A, LAYERS, TREE_HEIGHT, N, W, H>::from_bytes(&signature)?;
+        signature.validate(signing_input.as_bytes(), self).map_err(|_| JoseError::InvalidSignature)?;
+
+        URL_SAFE_NO_PAD.decode(payload).map_err(|_| JoseError::Base64("payload"))
+    }
+}
+
+impl<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite> SphincsSigner<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+    // The public JWK plus "priv": base64url(SK.seed || SK.prf || PK.seed || PK.root)
+    pub fn to_jwk(&self) -> Result<String, JoseError> {
+        let set = algorithm(Parameters::sphincs::<K, A, LAYERS, TREE_HEIGHT, N, W, H>())?;
+        let public = self.public_key().to_bytes();
+        Ok(json!({
+            "kty": KEY_TYPE,
+            "alg": set.name(),
+            "pub": URL_SAFE_NO_PAD.encode(&public[2..]),
+            "priv": URL_SAFE_NO_PAD.encode(&self.to_bytes()[2..])
+        }).to_string())
+    }
+
+    pub fn from_jwk(jwk: &str) -> Result<Self, JoseError> {
+        let set = algorithm(Parameters::sphincs::<K, A, LAYERS, TREE_HEIGHT, N, W, H>())?;
+        let (object, public) = read_jwk(jwk, set)?;
+        let secret = decode_member(&object, "priv")?;
+
+        let signer = Self::from_bytes(&[&[KEY_VERSION, set.id()][..], &secret].concat())?;
+        match signer.public_key().to_bytes()[2..] == public[..] {
+            true => Ok(signer),
+            false => Err(JoseError::KeyMismatch),
+        }
+    }
+
+    // header.payload.signature, with a {"alg":...} header and the canonical
+    // signature encoding
+    pub fn sign_jws(&self, payload: &[u8]) -> Result<String, JoseError> {
+        let set = algorithm(Parameters::sphincs::<K, A, LAYERS, TREE_HEIGHT, N, W, H>())?;
+        let header = json!({"alg": set.name()}).to_string();
+        let signing_input = format!("{}.{}", URL_SAFE_NO_PAD.encode(header), URL_SAFE_NO_PAD.encode(payload));
+        let signature = self.sign(signing_input.as_bytes());
+        Ok(format!("{signing_input}.{}", URL_SAFE_NO_PAD.encode(signature.to_bytes())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::components::parameters::{SphincsSha2_128fPublic, SphincsSha2_128fSigner, SphincsSha2_128sPublic};
+
+    fn signer() -> SphincsSha2_128fSigner {
+        SphincsSha2_128fSigner::new([1u8; 16], [2u8; 16])
+    }
+
+    #[test]
+    fn test_jwk_round_trip() {
+        let signer = signer();
+        let public_jwk = signer.public_key().to_jwk().unwrap();
+        let object = parse_object(public_jwk.as_bytes()).unwrap();
+        assert_eq!(member(&object, "kty"), Ok("AKP"));
+        assert_eq!(member(&object, "alg"), Ok("SPHINCS-SHA2-128f"));
+        assert!(!object.contains_key("priv"));
+
+        assert_eq!(SphincsSha2_128fPublic::from_jwk(&public_jwk), Ok(signer.public_key()));
+        assert_eq!(SphincsSha2_128fSigner::from_jwk(&signer.to_jwk().unwrap()), Ok(signer.clone()));
+
+        // A public JWK is not a private one, and the alg has to match the type
+        assert_eq!(SphincsSha2_128fSigner::from_jwk(&public_jwk), Err(JoseError::MissingMember("priv")));
+        assert_eq!(SphincsSha2_128sPublic::from_jwk(&public_jwk), Err(JoseError::WrongAlgorithm("SPHINCS-SHA2-128s", "SPHINCS-SHA2-128f".to_string())));
+        assert_eq!(SphincsSha2_128fPublic::from_jwk(&public_jwk.replace("AKP", "OKP")), Err(JoseError::WrongKeyType("OKP".to_string())));
+        assert_eq!(SphincsSha2_128fPublic::from_jwk("[1, 2]"), Err(JoseError::Json));
+    }
+
+    #[test]
+    fn test_jwk_pub_must_match_priv() {
+        let mut jwk = parse_object(signer().to_jwk().unwrap().as_bytes()).unwrap();
+        let other = SphincsSha2_128fSigner::new([3u8; 16], [2u8; 16]).public_key().to_bytes();
+        jwk.insert("pub".to_string(), Value::String(URL_SAFE_NO_PAD.encode(&other[2..])));
+        assert_eq!(SphincsSha2_128fSigner::from_jwk(&Value::Object(jwk).to_string()), Err(JoseError::KeyMismatch));
+    }
+
+    #[test]
+    fn test_jws() {
+        let signer = signer();
+        let public_key = signer.public_key();
+        let token = signer.sign_jws(br#"{"sub":"gateway"}"#).unwrap();
+        assert_eq!(token.split('.').count(), 3);
+        assert_eq!(public_key.verify_jws(&token).unwrap(), br#"{"sub":"gateway"}"#);
+
+        // Another payload under the same signature
+        let parts: Vec<&str> = token.split('.').collect();
+        let forged = format!("{}.{}.{}", parts[0], URL_SAFE_NO_PAD.encode(br#"{"sub":"admin"}"#), parts[2]);
+        assert_eq!(public_key.verify_jws(&forged), Err(JoseError::InvalidSignature));
+
+        // Another alg in the header
+        let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"SPHINCS-SHA2-128s"}"#);
+        let relabelled = format!("{header}.{}.{}", parts[1], parts[2]);
+        assert_eq!(public_key.verify_jws(&relabelled), Err(JoseError::WrongAlgorithm("SPHINCS-SHA2-128f", "SPHINCS-SHA2-128s".to_string())));
+
+        let critical = URL_SAFE_NO_PAD.encode(r#"{"alg":"SPHINCS-SHA2-128f","crit":["exp"]}"#);
+        assert_eq!(public_key.verify_jws(&format!("{critical}.{}.{}", parts[1], parts[2])), Err(JoseError::UnsupportedCritical));
+        assert_eq!(public_key.verify_jws("no dots"), Err(JoseError::MalformedToken));
+    }
+
+    #[test]
+    fn test_unnamed_sets_have_no_alg() {
+        let signer = SphincsSigner::<4, 4, 2, 3>::new([1u8; 32], [2u8; 32]);
+        assert_eq!(signer.to_jwk(), Err(JoseError::NoAlgorithm));
+        assert_eq!(signer.sign_jws(b"payload"), Err(JoseError::NoAlgorithm));
+    }
+}
@@ -1,34 +1,43 @@
-use crate::lib::{components::{wots_plus::signature::WotsPlusSignature}, helpers::{hasher::hash_array, random_generator::HashData}};
+use crate::lib::{components::{merkle_tree::secret::{climb, tree_context}, wots_plus::signature::WotsPlusSignature}, helpers::{encoding::{DecodeError, Reader}, hasher::{HashSuite, Sha2Suite}, random_generator::HashData}};
 
 #[derive(Clone, Debug, PartialEq)]
-pub struct MerkleProof<const HEIGHT:usize> { // STEM_HEIGHT does not include the root level
-    pub public_key: HashData,
-    pub signature: WotsPlusSignature,
-    pub merkle_leaves: [HashData;HEIGHT]
+pub struct MerkleProof<const HEIGHT:usize, const N: usize = 32, const W: usize = 256, H: HashSuite = Sha2Suite> { // STEM_HEIGHT does not include the root level
+    pub public_key: HashData<N>,
+    pub signature: WotsPlusSignature<N, W, H>,
+    pub merkle_leaves: [HashData<N>;HEIGHT]
 }
 
-impl<const HEIGHT:usize> MerkleProof<HEIGHT> {
+impl<const HEIGHT:usize, const N: usize, const W: usize, H: HashSuite> MerkleProof<HEIGHT, N, W, H> {
+    pub const fn size() -> usize {
+        N + WotsPlusSignature::<N, W, H>::size() + HEIGHT * N
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::size());
+        out.extend_from_slice(&self.public_key);
+        out.extend(self.signature.to_bytes());
+        self.merkle_leaves.iter().for_each(|leaf| out.extend_from_slice(leaf));
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes, Self::size())?;
+        let public_key = reader.array();
+        let signature = WotsPlusSignature::from_bytes(reader.take(WotsPlusSignature::<N, W, H>::size()))?;
+        let merkle_leaves = core::array::from_fn(|_| reader.array());
+        Ok(Self { public_key, signature, merkle_leaves })
+    }
+
     pub fn get_height(self) -> usize {
         HEIGHT
     }
-    pub fn get_expected_public_key(self, message: &[u8]) -> HashData{
-        let this_signature = self.signature.clone();
+    pub fn get_expected_public_key(self, message: &[u8]) -> HashData<N>{
         let num_keys = (2_usize).pow(HEIGHT as u32);
-        
-        let mut key = this_signature.get_expected_public_from_message(message);
-        let mut key_idx = this_signature.context.address.position as usize % num_keys;
-        
-        for other_key in self.merkle_leaves {
-            if key_idx % 2 == 1{
-                key = hash_array(&[other_key, key, self.signature.context.public_seed])
-            }else{
-                key = hash_array(&[key, other_key, self.signature.context.public_seed])
-            }
-            key_idx /= 2
-        };
-        key
+        let key = self.signature.get_expected_public_from_message(message);
+        let key_idx = self.signature.context.address.position as usize % num_keys;
+        climb::<H, N>(key, key_idx, self.merkle_leaves, &tree_context(&self.signature.context, HEIGHT))
     }
-    pub fn validate(self, message: &[u8], public_key: HashData)-> bool {
+    pub fn validate(self, message: &[u8], public_key: HashData<N>)-> bool {
         let key = self.get_expected_public_key(message);
         public_key == key
     }
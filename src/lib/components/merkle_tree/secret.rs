@@ -1,33 +1,63 @@
+use std::marker::PhantomData;
+
 use crate::lib::{
-    components::wots_plus::{secret::WotsPlus}, helpers::{hasher::{HashContext, hash_array}, random_generator::{Address, HashData}}
+    components::wots_plus::{secret::WotsPlus}, helpers::{hasher::{HashContext, HashSuite, Sha2Suite}, random_generator::{Address, AddressType, HashData}}
 };
 use super::proof::MerkleProof;
 
-pub(crate) fn pair_keys(keys: &[HashData], public_seed: HashData) -> Vec<HashData> {
+// All the nodes of a tree share the address of its first leaf
+pub(crate) fn tree_context<const N: usize>(leaf_context: &HashContext<N>, height: usize) -> HashContext<N> {
+    let num_leaves = 1u128 << height;
+    let mut address = leaf_context.address.with_type(AddressType::Tree);
+    address.position = leaf_context.address.position / num_leaves * num_leaves;
+    HashContext { public_seed: leaf_context.public_seed, address }
+}
+
+// Hashes two children into the node at `height` and `index` of the tree
+pub(crate) fn node_hash<H: HashSuite, const N: usize>(left: HashData<N>, right: HashData<N>, tree: &HashContext<N>, height: usize, index: usize) -> HashData<N> {
+    let mut context = tree.clone();
+    context.address.chain = height as u32;
+    context.address.hash = index as u32;
+    H::hash_with_context(&[left, right], &context)
+}
+
+// Walks from the leaf at `leaf_idx` up to the root, one sibling of the authentication path per level
+pub(crate) fn climb<H: HashSuite, const N: usize>(leaf: HashData<N>, leaf_idx: usize, siblings: impl IntoIterator<Item = HashData<N>>, tree: &HashContext<N>) -> HashData<N> {
+    siblings.into_iter().enumerate().fold((leaf, leaf_idx), |(key, idx), (height, sibling)| {
+        let key = match idx % 2 {
+            1 => node_hash::<H, N>(sibling, key, tree, height + 1, idx / 2),
+            _ => node_hash::<H, N>(key, sibling, tree, height + 1, idx / 2),
+        };
+        (key, idx / 2)
+    }).0
+}
+
+// Builds the level at `height` from the level below it
+pub(crate) fn pair_keys<H: HashSuite, const N: usize>(keys: &[HashData<N>], tree: &HashContext<N>, height: usize) -> Vec<HashData<N>> {
     assert!(keys.len().is_multiple_of(2), "Number of keys provided to pair_keys must be devisible by 2");
     (0..keys.len()).step_by(2).map(|idx| {
-        hash_array(&[keys[idx], keys[idx+1], public_seed])
+        node_hash::<H, N>(keys[idx], keys[idx+1], tree, height, idx / 2)
     }).collect()
 }
 
 #[derive(Debug)]
-pub struct MerkleSigner<const HEIGHT:usize> {
-    seed: HashData,
+pub struct MerkleSigner<const HEIGHT:usize, const N: usize = 32, const W: usize = 256, H: HashSuite = Sha2Suite> {
+    seed: HashData<N>,
     num_trees: u64,
-    pub context: HashContext,
-    
+    pub context: HashContext<N>,
+    suite: PhantomData<H>,
 }
 
-impl<const HEIGHT:usize> MerkleSigner<HEIGHT> {
-    pub fn new(seed: HashData, context:HashContext) -> Self {
+impl<const HEIGHT:usize, const N: usize, const W: usize, H: HashSuite> MerkleSigner<HEIGHT, N, W, H> {
+    pub fn new(seed: HashData<N>, context:HashContext<N>) -> Self {
         let num_trees: u64 = (2_u64).pow((HEIGHT) as u32);
-        Self{seed, context, num_trees}
+        Self{seed, context, num_trees, suite: PhantomData}
     }
     pub fn get_height(&self) -> usize{
         // returns the full size of the tree, not the stem
         HEIGHT
     }
-    pub(super) fn generate_lowest_layer(&self) -> Vec<WotsPlus> {
+    pub(super) fn generate_lowest_layer(&self) -> Vec<WotsPlus<N, W, H>> {
         
         let position = self.context.address.position;
         let level = self.context.address.level;
@@ -35,36 +65,37 @@ impl<const HEIGHT:usize> MerkleSigner<HEIGHT> {
         let first_postion = (position / (self.num_trees as u128)) * (self.num_trees as u128);
         let next_tree_position = first_postion + self.num_trees as u128;
         (first_postion..next_tree_position).map(|pos| {
-            WotsPlus::new(self.seed, HashContext { public_seed, address: Address{level, position: pos} })
+            WotsPlus::<N, W, H>::new(self.seed, HashContext { public_seed, address: Address { level, position: pos, ..Address::default() } })
         }).collect()
     }
 
-    pub fn get_signing_wots(&self, lowest_layer: &[WotsPlus]) -> WotsPlus {
+    pub fn get_signing_wots(&self, lowest_layer: &[WotsPlus<N, W, H>]) -> WotsPlus<N, W, H> {
         let wots_idx:usize = self.context.address.position as usize % self.num_trees as usize;
         lowest_layer[wots_idx].clone()
     }
 
-    pub fn get_public_key_and_proof(self)  -> (HashData, [HashData;HEIGHT]){
+    pub fn get_public_key_and_proof(self)  -> (HashData<N>, [HashData<N>;HEIGHT]){
         let lowest_layer = self.generate_lowest_layer();
         self._get_public_key_and_proof(lowest_layer)
     }
-    fn _get_public_key_and_proof(&self, lowest_layer: Vec<WotsPlus>) -> (HashData, [HashData;HEIGHT]){
-        let mut public_keys: Vec<HashData> = lowest_layer.iter().map(|wots| wots.generate_public_key().public_key).collect();
+    fn _get_public_key_and_proof(&self, lowest_layer: Vec<WotsPlus<N, W, H>>) -> (HashData<N>, [HashData<N>;HEIGHT]){
+        let mut public_keys: Vec<HashData<N>> = lowest_layer.iter().map(|wots| wots.generate_public_key().public_key).collect();
         let mut hashed_idx = self.context.address.position as usize % self.num_trees as usize;
-        let merkle_proof  = core::array::from_fn(|_| {
+        let tree = tree_context(&self.context, HEIGHT);
+        let merkle_proof  = core::array::from_fn(|height| {
             let other_key = if hashed_idx % 2 == 1 {
                 public_keys[hashed_idx - 1]
             } else {
                 public_keys[hashed_idx + 1]
             };
             hashed_idx /= 2;
-            public_keys = pair_keys(&public_keys, self.context.public_seed);
+            public_keys = pair_keys::<H, N>(&public_keys, &tree, height + 1);
             other_key
         });
         (public_keys[0], merkle_proof)
     }
 
-    pub fn sign(&self, message: &[u8]) -> MerkleProof<HEIGHT> {
+    pub fn sign(&self, message: &[u8]) -> MerkleProof<HEIGHT, N, W, H> {
         let lowest_layer = self.generate_lowest_layer();
         let signing_wots = self.get_signing_wots(&lowest_layer);
         let (public_key, merkle_leaves) = self._get_public_key_and_proof(lowest_layer);
@@ -84,8 +115,8 @@ macro_rules! merkle_signer {
 
 #[cfg(test)]
 mod tests {
-    use crate::lib::components::merkle_tree::secret::pair_keys;
-    use crate::lib::helpers::hasher::hash_message;
+    use crate::lib::components::merkle_tree::secret::{node_hash, pair_keys};
+    use crate::lib::helpers::hasher::{Sha2Suite, hash_message};
     use crate::lib::helpers::random_generator::Address;
     use crate::lib::helpers::{hasher::HashContext, random_generator::HASH_DATA_0};
 
@@ -126,25 +157,27 @@ mod tests {
 
     #[test]
     fn test_pair_keys (){
-        let public_seed = HASH_DATA_0;
+        let tree = HashContext::default();
         let to_join = vec!(
             hash_message("a".as_bytes()), hash_message("b".as_bytes()), 
             hash_message("a".as_bytes()), hash_message("b".as_bytes()), // Note: the first 2 are the same
             hash_message("a".as_bytes()), hash_message("c".as_bytes()),
             hash_message("a".as_bytes()), hash_message("d".as_bytes()));
-        let keys = pair_keys(&to_join, public_seed);
+        let keys = pair_keys::<Sha2Suite, 32>(&to_join, &tree, 1);
         assert_eq!(keys.len(), 4);
-        assert_eq!(keys[0], keys[1]);
+        // Same children, but every node has its own index in the address
+        assert_ne!(keys[0], keys[1]);
+        assert_eq!(keys[1], node_hash::<Sha2Suite, 32>(to_join[0], to_join[1], &tree, 1, 1));
         assert_ne!(keys[0], keys[2]);
         assert_ne!(keys[0], keys[3]);
         assert_ne!(keys[2], keys[3]);
         
-        let more_keys = pair_keys(&keys, public_seed);
+        let more_keys = pair_keys::<Sha2Suite, 32>(&keys, &tree, 2);
         
         assert_eq!(more_keys.len(), 2);
         assert_ne!(more_keys[0], more_keys[1]);
         
-        let one_key = pair_keys(&more_keys, public_seed);
+        let one_key = pair_keys::<Sha2Suite, 32>(&more_keys, &tree, 3);
         
         assert_eq!(one_key.len(), 1);
     }
@@ -154,16 +187,19 @@ mod tests {
         let public_seed2 = HASH_DATA_0;
         let mut public_seed3 = HASH_DATA_0;
         public_seed3[0] = 1;
+        let tree = |public_seed| HashContext { public_seed, address: Address::default() };
 
         let to_join = vec!(
             hash_message("a".as_bytes()), hash_message("b".as_bytes()), 
         );
-        let result1 = pair_keys(&to_join.clone(), public_seed1);
-        let result2 = pair_keys(&to_join.clone(), public_seed2);
-        let result3 = pair_keys(&to_join.clone(), public_seed3);
+        let result1 = pair_keys::<Sha2Suite, 32>(&to_join.clone(), &tree(public_seed1), 1);
+        let result2 = pair_keys::<Sha2Suite, 32>(&to_join.clone(), &tree(public_seed2), 1);
+        let result3 = pair_keys::<Sha2Suite, 32>(&to_join.clone(), &tree(public_seed3), 1);
+        let result4 = pair_keys::<Sha2Suite, 32>(&to_join.clone(), &tree(public_seed1), 2);
 
         assert_eq!(result1, result2);
         assert_ne!(result1, result3);
+        assert_ne!(result1, result4);
 
     }
     #[test]
@@ -175,7 +211,7 @@ mod tests {
             hash_message("a".as_bytes()), hash_message("b".as_bytes()), // Note: the first 2 are the same
             hash_message("a".as_bytes()), hash_message("c".as_bytes()),
             hash_message("a".as_bytes()) );
-        let _ = pair_keys(&to_join, public_seed);
+        let _ = pair_keys::<Sha2Suite, 32>(&to_join, &HashContext { public_seed, address: Address::default() }, 1);
     }
     #[test]
     fn test_get_signing_wots() {
@@ -197,9 +233,9 @@ mod tests {
         let (public_key, merkle_leaves) = signer.get_public_key_and_proof();
         assert_eq!(merkle_leaves.len(), 4);
         // This checks if the key is stable over tests
-        assert_eq!( public_key, [204, 69, 137, 115, 70, 125, 219, 78, 237, 
-            239, 133, 114, 169, 95, 104, 171, 2, 29, 144, 58, 193, 173, 140, 
-            205, 252, 155, 196, 182, 175, 190, 159, 181] );
+        assert_eq!( public_key, [212, 209, 91, 76, 173, 227, 200, 255, 218, 
+            227, 22, 243, 167, 11, 209, 229, 155, 56, 146, 51, 230, 214, 59, 
+            69, 105, 99, 235, 158, 229, 141, 228, 254] );
         
         let mut other_context = HashContext::default();
         other_context.address.position = 11;
@@ -216,7 +252,7 @@ mod tests {
         const MESSAGE:&[u8] = "Hello from Rust".as_bytes();
         const OTHER_MESSAGE:&[u8] = "Hello from Rusty".as_bytes();
 
-        let context = HashContext{ public_seed: hash_message(SEED_CREATOR), address: Address{level: 12, position:123} };
+        let context = HashContext{ public_seed: hash_message(SEED_CREATOR), address: Address { level: 12, position:123, ..Address::default() } };
         
         let signer = merkle_signer!(4, HASH_DATA_0, context);
 
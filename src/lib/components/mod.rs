@@ -2,4 +2,13 @@ pub mod wots_plus;
 pub mod merkle_tree;
 pub mod fors;
 pub mod hypertree;
-pub mod sphincs;
\ No newline at end of file
+pub mod sphincs;
+pub mod slh_dsa;
+pub mod parameters;
+pub mod pkix;
+#[cfg(feature = "jose")]
+pub mod jose;
+pub mod cose;
+pub mod container;
+#[cfg(feature = "armor")]
+pub mod armor;
\ No newline at end of file
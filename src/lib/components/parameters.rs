@@ -0,0 +1,331 @@
+// Named parameter sets: type aliases for the const generics, and a runtime
+// ParameterSet for code that only learns the set when it parses keys or signatures.
+use std::fmt;
+
+use crate::lib::{
+    components::{
+        slh_dsa::{hash::SlhDsaHash, public::SlhDsaPublic, secret::SlhDsaSigner, signature::SlhDsaSignature, wots},
+        sphincs::{public::SphincsPublic, secret::SphincsSigner, signature::SphincsSignature}
+    },
+    helpers::hasher::{HashSuite, Sha2Suite, Shake256Suite}
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    // FIPS 205, components::slh_dsa
+    SlhDsa,
+    // The custom scheme of this crate, components::sphincs
+    Sphincs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parameters {
+    pub scheme: Scheme,
+    pub hash: &'static str,
+    pub n: usize,
+    pub h: usize, // the total hypertree height, d * h'
+    pub d: usize,
+    pub k: usize,
+    pub a: usize,
+    pub w: usize,
+    pub public_key_size: usize,
+    pub signature_size: usize,
+    pub category: u8, // NIST security category, 0 for n below 16
+}
+
+const fn category(n: usize) -> u8 {
+    match n {
+        32.. => 5,
+        24.. => 3,
+        16.. => 1,
+        _ => 0
+    }
+}
+
+impl Parameters {
+    pub const fn slh_dsa<const N: usize, const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, H: SlhDsaHash>() -> Self {
+        Self {
+            scheme: Scheme::SlhDsa,
+            hash: H::NAME,
+            n: N,
+            h: LAYERS * TREE_HEIGHT,
+            d: LAYERS,
+            k: K,
+            a: A,
+            w: wots::W as usize,
+            public_key_size: SlhDsaPublic::<N, K, A, LAYERS, TREE_HEIGHT, H>::size(),
+            signature_size: SlhDsaSignature::<N, K, A, LAYERS, TREE_HEIGHT, H>::size(),
+            category: category(N),
+        }
+    }
+
+    pub const fn sphincs<const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite>() -> Self {
+        Self {
+            scheme: Scheme::Sphincs,
+            hash: H::NAME,
+            n: N,
+            h: LAYERS * TREE_HEIGHT,
+            d: LAYERS,
+            k: K,
+            a: A,
+            w: W,
+            public_key_size: SphincsPublic::<K, A, LAYERS, TREE_HEIGHT, N, W, H>::size(),
+            signature_size: SphincsSignature::<K, A, LAYERS, TREE_HEIGHT, N, W, H>::size(),
+            category: category(N),
+        }
+    }
+
+    // h', the height of every hypertree layer
+    pub const fn tree_height(&self) -> usize {
+        self.h / self.d
+    }
+
+    // The named set with exactly these parameters, if there is one
+    pub fn parameter_set(&self) -> Option<ParameterSet> {
+        ParameterSet::ALL.into_iter().find(|set| set.params() == *self)
+    }
+}
+
+// The ids are stable, they are written into serialized keys and signatures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum ParameterSet {
+    SlhDsaSha2_128s = 1,
+    SlhDsaSha2_128f = 2,
+    SlhDsaSha2_192s = 3,
+    SlhDsaSha2_192f = 4,
+    SlhDsaSha2_256s = 5,
+    SlhDsaSha2_256f = 6,
+    SlhDsaShake128s = 7,
+    SlhDsaShake128f = 8,
+    SlhDsaShake192s = 9,
+    SlhDsaShake192f = 10,
+    SlhDsaShake256s = 11,
+    SlhDsaShake256f = 12,
+    // The FIPS 205 shapes in the custom scheme, with its default w=256
+    SphincsSha2_128s = 65,
+    SphincsSha2_128f = 66,
+    SphincsSha2_192s = 67,
+    SphincsSha2_192f = 68,
+    SphincsSha2_256s = 69,
+    SphincsSha2_256f = 70,
+}
+
+impl ParameterSet {
+    pub const ALL: [ParameterSet; 18] = [
+        Self::SlhDsaSha2_128s, Self::SlhDsaSha2_128f, Self::SlhDsaSha2_192s, Self::SlhDsaSha2_192f, Self::SlhDsaSha2_256s, Self::SlhDsaSha2_256f,
+        Self::SlhDsaShake128s, Self::SlhDsaShake128f, Self::SlhDsaShake192s, Self::SlhDsaShake192f, Self::SlhDsaShake256s, Self::SlhDsaShake256f,
+        Self::SphincsSha2_128s, Self::SphincsSha2_128f, Self::SphincsSha2_192s, Self::SphincsSha2_192f, Self::SphincsSha2_256s, Self::SphincsSha2_256f,
+    ];
+
+    pub const fn params(self) -> Parameters {
+        match self {
+            Self::SlhDsaSha2_128s => Parameters::slh_dsa::<16, 14, 12, 7, 9, Sha2Suite>(),
+            Self::SlhDsaSha2_128f => Parameters::slh_dsa::<16, 33, 6, 22, 3, Sha2Suite>(),
+            Self::SlhDsaSha2_192s => Parameters::slh_dsa::<24, 17, 14, 7, 9, Sha2Suite>(),
+            Self::SlhDsaSha2_192f => Parameters::slh_dsa::<24, 33, 8, 22, 3, Sha2Suite>(),
+            Self::SlhDsaSha2_256s => Parameters::slh_dsa::<32, 22, 14, 8, 8, Sha2Suite>(),
+            Self::SlhDsaSha2_256f => Parameters::slh_dsa::<32, 35, 9, 17, 4, Sha2Suite>(),
+            Self::SlhDsaShake128s => Parameters::slh_dsa::<16, 14, 12, 7, 9, Shake256Suite>(),
+            Self::SlhDsaShake128f => Parameters::slh_dsa::<16, 33, 6, 22, 3, Shake256Suite>(),
+            Self::SlhDsaShake192s => Parameters::slh_dsa::<24, 17, 14, 7, 9, Shake256Suite>(),
+            Self::SlhDsaShake192f => Parameters::slh_dsa::<24, 33, 8, 22, 3, Shake256Suite>(),
+            Self::SlhDsaShake256s => Parameters::slh_dsa::<32, 22, 14, 8, 8, Shake256Suite>(),
+            Self::SlhDsaShake256f => Parameters::slh_dsa::<32, 35, 9, 17, 4, Shake256Suite>(),
+            Self::SphincsSha2_128s => Parameters::sphincs::<14, 12, 7, 9, 16, 256, Sha2Suite>(),
+            Self::SphincsSha2_128f => Parameters::sphincs::<33, 6, 22, 3, 16, 256, Sha2Suite>(),
+            Self::SphincsSha2_192s => Parameters::sphincs::<17, 14, 7, 9, 24, 256, Sha2Suite>(),
+            Self::SphincsSha2_192f => Parameters::sphincs::<33, 8, 22, 3, 24, 256, Sha2Suite>(),
+            Self::SphincsSha2_256s => Parameters::sphincs::<22, 14, 8, 8, 32, 256, Sha2Suite>(),
+            Self::SphincsSha2_256f => Parameters::sphincs::<35, 9, 17, 4, 32, 256, Sha2Suite>(),
+        }
+    }
+
+    pub const fn id(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|set| set.id() == id)
+    }
+
+    // e.g. SLH-DSA-SHA2-128s, or SPHINCS-SHA2-128s for the custom scheme
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::SlhDsaSha2_128s => "SLH-DSA-SHA2-128s",
+            Self::SlhDsaSha2_128f => "SLH-DSA-SHA2-128f",
+            Self::SlhDsaSha2_192s => "SLH-DSA-SHA2-192s",
+            Self::SlhDsaSha2_192f => "SLH-DSA-SHA2-192f",
+            Self::SlhDsaSha2_256s => "SLH-DSA-SHA2-256s",
+            Self::SlhDsaSha2_256f => "SLH-DSA-SHA2-256f",
+            Self::SlhDsaShake128s => "SLH-DSA-SHAKE-128s",
+            Self::SlhDsaShake128f => "SLH-DSA-SHAKE-128f",
+            Self::SlhDsaShake192s => "SLH-DSA-SHAKE-192s",
+            Self::SlhDsaShake192f => "SLH-DSA-SHAKE-192f",
+            Self::SlhDsaShake256s => "SLH-DSA-SHAKE-256s",
+            Self::SlhDsaShake256f => "SLH-DSA-SHAKE-256f",
+            Self::SphincsSha2_128s => "SPHINCS-SHA2-128s",
+            Self::SphincsSha2_128f => "SPHINCS-SHA2-128f",
+            Self::SphincsSha2_192s => "SPHINCS-SHA2-192s",
+            Self::SphincsSha2_192f => "SPHINCS-SHA2-192f",
+            Self::SphincsSha2_256s => "SPHINCS-SHA2-256s",
+            Self::SphincsSha2_256f => "SPHINCS-SHA2-256f",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|set| set.name().eq_ignore_ascii_case(name))
+    }
+
+    // Last arc of the NIST algorithm identifier 2.16.840.1.101.3.4.3.x. The custom
+    // scheme is not SLH-DSA, so its sets have none.
+    pub const fn oid_arc(self) -> Option<u8> {
+        match self {
+            Self::SlhDsaSha2_128s => Some(20),
+            Self::SlhDsaSha2_128f => Some(21),
+            Self::SlhDsaSha2_192s => Some(22),
+            Self::SlhDsaSha2_192f => Some(23),
+            Self::SlhDsaSha2_256s => Some(24),
+            Self::SlhDsaSha2_256f => Some(25),
+            Self::SlhDsaShake128s => Some(26),
+            Self::SlhDsaShake128f => Some(27),
+            Self::SlhDsaShake192s => Some(28),
+            Self::SlhDsaShake192f => Some(29),
+            Self::SlhDsaShake256s => Some(30),
+            Self::SlhDsaShake256f => Some(31),
+            _ => None,
+        }
+    }
+
+    pub fn oid(self) -> Option<String> {
+        self.oid_arc().map(|arc| format!("2.16.840.1.101.3.4.3.{arc}"))
+    }
+
+    pub fn from_oid(oid: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|set| set.oid().as_deref() == Some(oid))
+    }
+}
+
+impl fmt::Display for ParameterSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+macro_rules! slh_dsa_aliases {
+    ($($signer:ident, $public:ident, $signature:ident = <$n:literal, $k:literal, $a:literal, $d:literal, $hp:literal, $hash:ty>;)*) => {
+        $(
+            pub type $signer = SlhDsaSigner<$n, $k, $a, $d, $hp, $hash>;
+            pub type $public = SlhDsaPublic<$n, $k, $a, $d, $hp, $hash>;
+            pub type $signature = SlhDsaSignature<$n, $k, $a, $d, $hp, $hash>;
+        )*
+    };
+}
+
+slh_dsa_aliases! {
+    SlhDsaSha2_128sSigner, SlhDsaSha2_128sPublic, SlhDsaSha2_128sSignature = <16, 14, 12, 7, 9, Sha2Suite>;
+    SlhDsaSha2_128fSigner, SlhDsaSha2_128fPublic, SlhDsaSha2_128fSignature = <16, 33, 6, 22, 3, Sha2Suite>;
+    SlhDsaSha2_192sSigner, SlhDsaSha2_192sPublic, SlhDsaSha2_192sSignature = <24, 17, 14, 7, 9, Sha2Suite>;
+    SlhDsaSha2_192fSigner, SlhDsaSha2_192fPublic, SlhDsaSha2_192fSignature = <24, 33, 8, 22, 3, Sha2Suite>;
+    SlhDsaSha2_256sSigner, SlhDsaSha2_256sPublic, SlhDsaSha2_256sSignature = <32, 22, 14, 8, 8, Sha2Suite>;
+    SlhDsaSha2_256fSigner, SlhDsaSha2_256fPublic, SlhDsaSha2_256fSignature = <32, 35, 9, 17, 4, Sha2Suite>;
+    SlhDsaShake128sSigner, SlhDsaShake128sPublic, SlhDsaShake128sSignature = <16, 14, 12, 7, 9, Shake256Suite>;
+    SlhDsaShake128fSigner, SlhDsaShake128fPublic, SlhDsaShake128fSignature = <16, 33, 6, 22, 3, Shake256Suite>;
+    SlhDsaShake192sSigner, SlhDsaShake192sPublic, SlhDsaShake192sSignature = <24, 17, 14, 7, 9, Shake256Suite>;
+    SlhDsaShake192fSigner, SlhDsaShake192fPublic, SlhDsaShake192fSignature = <24, 33, 8, 22, 3, Shake256Suite>;
+    SlhDsaShake256sSigner, SlhDsaShake256sPublic, SlhDsaShake256sSignature = <32, 22, 14, 8, 8, Shake256Suite>;
+    SlhDsaShake256fSigner, SlhDsaShake256fPublic, SlhDsaShake256fSignature = <32, 35, 9, 17, 4, Shake256Suite>;
+}
+
+macro_rules! sphincs_aliases {
+    ($($signer:ident, $public:ident, $signature:ident = <$k:literal, $a:literal, $d:literal, $hp:literal, $n:literal>;)*) => {
+        $(
+            pub type $signer = SphincsSigner<$k, $a, $d, $hp, $n>;
+            pub type $public = SphincsPublic<$k, $a, $d, $hp, $n>;
+            pub type $signature = SphincsSignature<$k, $a, $d, $hp, $n>;
+        )*
+    };
+}
+
+sphincs_aliases! {
+    SphincsSha2_128sSigner, SphincsSha2_128sPublic, SphincsSha2_128sSignature = <14, 12, 7, 9, 16>;
+    SphincsSha2_128fSigner, SphincsSha2_128fPublic, SphincsSha2_128fSignature = <33, 6, 22, 3, 16>;
+    SphincsSha2_192sSigner, SphincsSha2_192sPublic, SphincsSha2_192sSignature = <17, 14, 7, 9, 24>;
+    SphincsSha2_192fSigner, SphincsSha2_192fPublic, SphincsSha2_192fSignature = <33, 8, 22, 3, 24>;
+    SphincsSha2_256sSigner, SphincsSha2_256sPublic, SphincsSha2_256sSignature = <22, 14, 8, 8, 32>;
+    SphincsSha2_256fSigner, SphincsSha2_256fPublic, SphincsSha2_256fSignature = <35, 9, 17, 4, 32>;
+}
+
+// The same shapes with hypertrees just tall enough for IndexSpace::Bits32. They are
+// meant for stateful signing only, and are not named sets.
+sphincs_aliases! {
+    Sphincs32Sha2_128sSigner, Sphincs32Sha2_128sPublic, Sphincs32Sha2_128sSignature = <14, 12, 4, 9, 16>;
+    Sphincs32Sha2_128fSigner, Sphincs32Sha2_128fPublic, Sphincs32Sha2_128fSignature = <33, 6, 11, 3, 16>;
+    Sphincs32Sha2_192sSigner, Sphincs32Sha2_192sPublic, Sphincs32Sha2_192sSignature = <17, 14, 4, 9, 24>;
+    Sphincs32Sha2_192fSigner, Sphincs32Sha2_192fPublic, Sphincs32Sha2_192fSignature = <33, 8, 11, 3, 24>;
+    Sphincs32Sha2_256sSigner, Sphincs32Sha2_256sPublic, Sphincs32Sha2_256sSignature = <22, 14, 4, 8, 32>;
+    Sphincs32Sha2_256fSigner, Sphincs32Sha2_256fPublic, Sphincs32Sha2_256fSignature = <35, 9, 8, 4, 32>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fips_sizes() {
+        // FIPS 205 table 2
+        let expected = [
+            (ParameterSet::SlhDsaSha2_128s, 32, 7856), (ParameterSet::SlhDsaSha2_128f, 32, 17088),
+            (ParameterSet::SlhDsaSha2_192s, 48, 16224), (ParameterSet::SlhDsaSha2_192f, 48, 35664),
+            (ParameterSet::SlhDsaSha2_256s, 64, 29792), (ParameterSet::SlhDsaSha2_256f, 64, 49856),
+        ];
+        for (set, public_key_size, signature_size) in expected {
+            assert_eq!(set.params().public_key_size, public_key_size, "{set}");
+            assert_eq!(set.params().signature_size, signature_size, "{set}");
+        }
+
+        let params = ParameterSet::SlhDsaShake192s.params();
+        assert_eq!((params.n, params.h, params.d, params.tree_height(), params.k, params.a, params.w), (24, 63, 7, 9, 17, 14, 16));
+        assert_eq!(params.category, 3);
+        assert_eq!(params.hash, "SHAKE");
+        assert_eq!(params.signature_size, ParameterSet::SlhDsaSha2_192s.params().signature_size);
+    }
+
+    #[test]
+    fn test_ids_and_names() {
+        for set in ParameterSet::ALL {
+            assert_eq!(ParameterSet::from_id(set.id()), Some(set));
+            assert_eq!(ParameterSet::from_name(set.name()), Some(set));
+            assert_eq!(set.params().parameter_set(), Some(set));
+        }
+        assert_eq!(ParameterSet::from_id(0), None);
+        assert_eq!(ParameterSet::from_name("slh-dsa-shake-256f"), Some(ParameterSet::SlhDsaShake256f));
+        assert_eq!(ParameterSet::from_name("SLH-DSA-SHA3-256f"), None);
+        assert_eq!(ParameterSet::SphincsSha2_128f.to_string(), "SPHINCS-SHA2-128f");
+    }
+
+    #[test]
+    fn test_oids() {
+        assert_eq!(ParameterSet::SlhDsaSha2_128s.oid().as_deref(), Some("2.16.840.1.101.3.4.3.20"));
+        assert_eq!(ParameterSet::SlhDsaShake256f.oid().as_deref(), Some("2.16.840.1.101.3.4.3.31"));
+        assert_eq!(ParameterSet::SphincsSha2_128s.oid(), None);
+        for set in ParameterSet::ALL.into_iter().filter(|set| set.params().scheme == Scheme::SlhDsa) {
+            assert_eq!(ParameterSet::from_oid(&set.oid().unwrap()), Some(set));
+        }
+        assert_eq!(ParameterSet::from_oid("2.16.840.1.101.3.4.3.17"), None);
+    }
+
+    #[test]
+    fn test_aliases_report_their_set() {
+        let signer = SlhDsaSha2_128fSigner::new([1u8; 16], [2u8; 16], [3u8; 16]);
+        assert_eq!(signer.public_key().parameter_set(), Some(ParameterSet::SlhDsaSha2_128f));
+        assert_eq!(SlhDsaSha2_128fSignature::size(), ParameterSet::SlhDsaSha2_128f.params().signature_size);
+
+        let params = ParameterSet::SphincsSha2_128f.params();
+        assert_eq!((params.scheme, params.n, params.w, params.category), (Scheme::Sphincs, 16, 256, 1));
+        assert_eq!(params.public_key_size, 32);
+        assert_eq!(SphincsSha2_128fSignature::size(), params.signature_size);
+        // Other shapes of the custom scheme have no name
+        assert_eq!(Parameters::sphincs::<4, 4, 2, 3, 32, 256, Sha2Suite>().parameter_set(), None);
+    }
+}
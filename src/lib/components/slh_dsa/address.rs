@@ -0,0 +1,93 @@
+// The 32 byte ADRS structure from FIPS 205 section 4.2
+pub use crate::lib::helpers::random_generator::AddressType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Adrs {
+    pub layer: u32,
+    pub tree: u64,
+    pub kind: AddressType,
+    pub key_pair: u32,
+    pub tree_height: u32, // also used as the chain address
+    pub tree_index: u32,  // also used as the hash address
+}
+
+impl Default for Adrs {
+    fn default() -> Self {
+        Self { layer: 0, tree: 0, kind: AddressType::WotsHash, key_pair: 0, tree_height: 0, tree_index: 0 }
+    }
+}
+
+impl Adrs {
+    pub fn set_type_and_clear(&mut self, kind: AddressType) {
+        self.kind = kind;
+        self.key_pair = 0;
+        self.tree_height = 0;
+        self.tree_index = 0;
+    }
+
+    pub fn set_chain_address(&mut self, chain: u32) {
+        self.tree_height = chain;
+    }
+
+    pub fn set_hash_address(&mut self, hash: u32) {
+        self.tree_index = hash;
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        out[..4].copy_from_slice(&self.layer.to_be_bytes());
+        // The tree address is 12 bytes long, but no parameter set uses more than 64 bits of it
+        out[8..16].copy_from_slice(&self.tree.to_be_bytes());
+        out[16..20].copy_from_slice(&(self.kind as u32).to_be_bytes());
+        out[20..24].copy_from_slice(&self.key_pair.to_be_bytes());
+        out[24..28].copy_from_slice(&self.tree_height.to_be_bytes());
+        out[28..].copy_from_slice(&self.tree_index.to_be_bytes());
+        out
+    }
+
+    // ADRSc, used by the SHA2 parameter sets (FIPS 205 section 11.2)
+    pub fn to_compressed_bytes(&self) -> [u8; 22] {
+        let full = self.to_bytes();
+        let mut out = [0u8; 22];
+        out[0] = full[3];
+        out[1..9].copy_from_slice(&full[8..16]);
+        out[9] = full[19];
+        out[10..].copy_from_slice(&full[20..]);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Adrs, AddressType};
+
+    #[test]
+    fn test_to_bytes_layout() {
+        let adrs = Adrs { layer: 3, tree: 0x0102030405060708, kind: AddressType::ForsTree, key_pair: 9, tree_height: 10, tree_index: 11 };
+        let bytes = adrs.to_bytes();
+
+        assert_eq!(bytes[..4], [0, 0, 0, 3]);
+        assert_eq!(bytes[4..8], [0, 0, 0, 0]);
+        assert_eq!(bytes[8..16], [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(bytes[16..20], [0, 0, 0, 3]);
+        assert_eq!(bytes[20..24], [0, 0, 0, 9]);
+        assert_eq!(bytes[24..28], [0, 0, 0, 10]);
+        assert_eq!(bytes[28..], [0, 0, 0, 11]);
+    }
+
+    #[test]
+    fn test_compressed_bytes() {
+        let adrs = Adrs { layer: 3, tree: 0x0102030405060708, kind: AddressType::ForsTree, key_pair: 9, tree_height: 10, tree_index: 11 };
+        let compressed = adrs.to_compressed_bytes();
+
+        assert_eq!(compressed, [3, 1, 2, 3, 4, 5, 6, 7, 8, 3, 0, 0, 0, 9, 0, 0, 0, 10, 0, 0, 0, 11]);
+    }
+
+    #[test]
+    fn test_type_and_clear() {
+        let mut adrs = Adrs { layer: 3, tree: 7, kind: AddressType::ForsTree, key_pair: 9, tree_height: 10, tree_index: 11 };
+        adrs.set_type_and_clear(AddressType::ForsRoots);
+
+        assert_eq!(adrs, Adrs { layer: 3, tree: 7, kind: AddressType::ForsRoots, key_pair: 0, tree_height: 0, tree_index: 0 });
+    }
+}
@@ -0,0 +1,93 @@
+// FORS from FIPS 205 section 8. The message digest is split with the same
+// message_to_indices used by the custom scheme, as it is FIPS 205 base_2b.
+use crate::lib::components::{fors::indices::{IndicesError, message_to_indices}, slh_dsa::{address::{AddressType, Adrs}, hash::SlhDsaHash}};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlhForsElement<const N: usize, const A: usize> {
+    pub secret_key: [u8; N],
+    pub auth_path: [[u8; N]; A],
+}
+
+fn secret_key<H: SlhDsaHash, const N: usize>(secret_seed: &[u8; N], public_seed: &[u8; N], adrs: &Adrs, index: u32) -> [u8; N] {
+    let mut sk_adrs = *adrs;
+    sk_adrs.set_type_and_clear(AddressType::ForsPrf);
+    sk_adrs.key_pair = adrs.key_pair;
+    sk_adrs.tree_index = index;
+    H::prf(public_seed, secret_seed, &sk_adrs)
+}
+
+fn node<H: SlhDsaHash, const N: usize>(secret_seed: &[u8; N], index: u32, height: u32, public_seed: &[u8; N], adrs: &Adrs) -> [u8; N] {
+    let mut node_adrs = *adrs;
+    node_adrs.tree_height = height;
+    node_adrs.tree_index = index;
+    if height == 0 {
+        let sk = secret_key::<H, N>(secret_seed, public_seed, adrs, index);
+        return H::f(public_seed, &node_adrs, &sk);
+    }
+    let left = node::<H, N>(secret_seed, 2 * index, height - 1, public_seed, adrs);
+    let right = node::<H, N>(secret_seed, 2 * index + 1, height - 1, public_seed, adrs);
+    H::h(public_seed, &node_adrs, &left, &right)
+}
+
+pub fn sign<H: SlhDsaHash, const N: usize, const K: usize, const A: usize>(digest: &[u8], secret_seed: &[u8; N], public_seed: &[u8; N], adrs: &Adrs) -> Result<[SlhForsElement<N, A>; K], IndicesError> {
+    let indices = message_to_indices::<K, A>(digest)?;
+    Ok(core::array::from_fn(|i| {
+        let tree_offset = (i as u32) << A;
+        let secret_key = secret_key::<H, N>(secret_seed, public_seed, adrs, tree_offset + indices[i]);
+        let auth_path = core::array::from_fn(|j| {
+            let sibling = (indices[i] >> j) ^ 1;
+            node::<H, N>(secret_seed, (tree_offset >> j) + sibling, j as u32, public_seed, adrs)
+        });
+        SlhForsElement { secret_key, auth_path }
+    }))
+}
+
+pub fn public_from_signature<H: SlhDsaHash, const N: usize, const K: usize, const A: usize>(signature: &[SlhForsElement<N, A>; K], digest: &[u8], public_seed: &[u8; N], adrs: &Adrs) -> Result<[u8; N], IndicesError> {
+    let indices = message_to_indices::<K, A>(digest)?;
+    let mut node_adrs = *adrs;
+    let roots: [[u8; N]; K] = core::array::from_fn(|i| {
+        let element = &signature[i];
+        node_adrs.tree_height = 0;
+        node_adrs.tree_index = ((i as u32) << A) + indices[i];
+        let mut current = H::f(public_seed, &node_adrs, &element.secret_key);
+        for (j, sibling) in element.auth_path.iter().enumerate() {
+            node_adrs.tree_height = j as u32 + 1;
+            node_adrs.tree_index /= 2;
+            current = match (indices[i] >> j).is_multiple_of(2) {
+                true => H::h(public_seed, &node_adrs, &current, sibling),
+                false => H::h(public_seed, &node_adrs, sibling, &current),
+            };
+        }
+        current
+    });
+
+    let mut roots_adrs = *adrs;
+    roots_adrs.set_type_and_clear(AddressType::ForsRoots);
+    roots_adrs.key_pair = adrs.key_pair;
+    Ok(H::t(public_seed, &roots_adrs, &roots))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lib::{components::slh_dsa::address::{AddressType, Adrs}, helpers::hasher::Sha2Suite};
+    use super::*;
+
+    #[test]
+    fn test_public_key_independent_of_digest() {
+        let secret_seed = [5u8; 16];
+        let public_seed = [6u8; 16];
+        let mut adrs = Adrs { tree: 3, ..Adrs::default() };
+        adrs.set_type_and_clear(AddressType::ForsTree);
+        adrs.key_pair = 2;
+
+        let digest1 = [0x12u8, 0x34, 0x56];
+        let digest2 = [0xfeu8, 0xdc, 0xba];
+        let signature1 = sign::<Sha2Suite, 16, 4, 4>(&digest1, &secret_seed, &public_seed, &adrs).unwrap();
+        let signature2 = sign::<Sha2Suite, 16, 4, 4>(&digest2, &secret_seed, &public_seed, &adrs).unwrap();
+        let public1 = public_from_signature::<Sha2Suite, 16, 4, 4>(&signature1, &digest1, &public_seed, &adrs).unwrap();
+        let public2 = public_from_signature::<Sha2Suite, 16, 4, 4>(&signature2, &digest2, &public_seed, &adrs).unwrap();
+
+        assert_eq!(public1, public2);
+        assert_ne!(public_from_signature::<Sha2Suite, 16, 4, 4>(&signature1, &digest2, &public_seed, &adrs).unwrap(), public1);
+    }
+}
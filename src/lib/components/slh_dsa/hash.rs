@@ -0,0 +1,186 @@
+// The SLH-DSA hash functions (FIPS 205 section 11) for each of the hash suites
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256, Sha512};
+use sha3::{Shake256, digest::{ExtendableOutput, Update, XofReader}};
+
+use crate::lib::{components::slh_dsa::address::Adrs, helpers::hasher::{HashSuite, Sha2Suite, Shake256Suite}};
+
+pub trait SlhDsaHash: HashSuite {
+    fn prf<const N: usize>(public_seed: &[u8; N], secret_seed: &[u8; N], adrs: &Adrs) -> [u8; N];
+    fn f<const N: usize>(public_seed: &[u8; N], adrs: &Adrs, message: &[u8; N]) -> [u8; N];
+    fn h<const N: usize>(public_seed: &[u8; N], adrs: &Adrs, left: &[u8; N], right: &[u8; N]) -> [u8; N];
+    fn t<const N: usize>(public_seed: &[u8; N], adrs: &Adrs, values: &[[u8; N]]) -> [u8; N];
+    fn prf_msg<const N: usize>(secret_prf: &[u8; N], opt_rand: &[u8; N], message: &[u8]) -> [u8; N];
+    fn h_msg<const N: usize>(randomizer: &[u8; N], public_seed: &[u8; N], root: &[u8; N], message: &[u8], out_len: usize) -> Vec<u8>;
+}
+
+// SHA2 (FIPS 205 section 11.2). N = 16 uses SHA-256 everywhere, while N = 24 and
+// N = 32 switch H, T, H_msg and PRF_msg over to SHA-512.
+const SHA256_BLOCK: usize = 64;
+const SHA512_BLOCK: usize = 128;
+
+fn uses_sha512<const N: usize>() -> bool {
+    N > 16
+}
+
+fn truncate<const N: usize>(digest: &[u8]) -> [u8; N] {
+    digest[..N].try_into().expect("Digest is shorter than the security parameter")
+}
+
+fn tweaked<D: Digest, const N: usize>(block_size: usize, public_seed: &[u8; N], adrs: &Adrs, parts: &[&[u8]]) -> [u8; N] {
+    let padding = [0u8; SHA512_BLOCK];
+    let mut hasher = D::new();
+    Digest::update(&mut hasher, public_seed);
+    Digest::update(&mut hasher, &padding[..block_size - N]);
+    Digest::update(&mut hasher, adrs.to_compressed_bytes());
+    parts.iter().for_each(|part| Digest::update(&mut hasher, part));
+    truncate(&hasher.finalize())
+}
+
+fn tweaked_wide<const N: usize>(public_seed: &[u8; N], adrs: &Adrs, parts: &[&[u8]]) -> [u8; N] {
+    match uses_sha512::<N>() {
+        true => tweaked::<Sha512, N>(SHA512_BLOCK, public_seed, adrs, parts),
+        false => tweaked::<Sha256, N>(SHA256_BLOCK, public_seed, adrs, parts),
+    }
+}
+
+fn mgf1<D: Digest>(seed: &[u8], out_len: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(out_len + <D as Digest>::output_size());
+    let mut counter = 0u32;
+    while out.len() < out_len {
+        let mut hasher = D::new();
+        Digest::update(&mut hasher, seed);
+        Digest::update(&mut hasher, counter.to_be_bytes());
+        out.extend_from_slice(&hasher.finalize());
+        counter += 1;
+    }
+    out.truncate(out_len);
+    out
+}
+
+fn h_msg_with<D: Digest, const N: usize>(randomizer: &[u8; N], public_seed: &[u8; N], root: &[u8; N], message: &[u8], out_len: usize) -> Vec<u8> {
+    let mut hasher = D::new();
+    Digest::update(&mut hasher, randomizer);
+    Digest::update(&mut hasher, public_seed);
+    Digest::update(&mut hasher, root);
+    Digest::update(&mut hasher, message);
+    let inner = hasher.finalize();
+
+    let mut seed = Vec::with_capacity(2 * N + inner.len());
+    seed.extend_from_slice(randomizer);
+    seed.extend_from_slice(public_seed);
+    seed.extend_from_slice(&inner);
+    mgf1::<D>(&seed, out_len)
+}
+
+impl SlhDsaHash for Sha2Suite {
+    fn prf<const N: usize>(public_seed: &[u8; N], secret_seed: &[u8; N], adrs: &Adrs) -> [u8; N] {
+        tweaked::<Sha256, N>(SHA256_BLOCK, public_seed, adrs, &[secret_seed])
+    }
+
+    fn f<const N: usize>(public_seed: &[u8; N], adrs: &Adrs, message: &[u8; N]) -> [u8; N] {
+        tweaked::<Sha256, N>(SHA256_BLOCK, public_seed, adrs, &[message])
+    }
+
+    fn h<const N: usize>(public_seed: &[u8; N], adrs: &Adrs, left: &[u8; N], right: &[u8; N]) -> [u8; N] {
+        tweaked_wide(public_seed, adrs, &[left, right])
+    }
+
+    fn t<const N: usize>(public_seed: &[u8; N], adrs: &Adrs, values: &[[u8; N]]) -> [u8; N] {
+        let parts: Vec<&[u8]> = values.iter().map(|value| value.as_slice()).collect();
+        tweaked_wide(public_seed, adrs, &parts)
+    }
+
+    fn prf_msg<const N: usize>(secret_prf: &[u8; N], opt_rand: &[u8; N], message: &[u8]) -> [u8; N] {
+        match uses_sha512::<N>() {
+            true => {
+                let mut mac = Hmac::<Sha512>::new_from_slice(secret_prf).expect("HMAC accepts keys of any size");
+                Mac::update(&mut mac, opt_rand);
+                Mac::update(&mut mac, message);
+                truncate(&mac.finalize().into_bytes())
+            }
+            false => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(secret_prf).expect("HMAC accepts keys of any size");
+                Mac::update(&mut mac, opt_rand);
+                Mac::update(&mut mac, message);
+                truncate(&mac.finalize().into_bytes())
+            }
+        }
+    }
+
+    fn h_msg<const N: usize>(randomizer: &[u8; N], public_seed: &[u8; N], root: &[u8; N], message: &[u8], out_len: usize) -> Vec<u8> {
+        match uses_sha512::<N>() {
+            true => h_msg_with::<Sha512, N>(randomizer, public_seed, root, message, out_len),
+            false => h_msg_with::<Sha256, N>(randomizer, public_seed, root, message, out_len),
+        }
+    }
+}
+
+// SHAKE (FIPS 205 section 11.1), every function is SHAKE256 over the full ADRS
+fn shake(parts: &[&[u8]], out: &mut [u8]) {
+    let mut hasher = Shake256::default();
+    parts.iter().for_each(|part| Update::update(&mut hasher, part));
+    hasher.finalize_xof().read(out);
+}
+
+fn shake_n<const N: usize>(parts: &[&[u8]]) -> [u8; N] {
+    let mut out = [0u8; N];
+    shake(parts, &mut out);
+    out
+}
+
+impl SlhDsaHash for Shake256Suite {
+    fn prf<const N: usize>(public_seed: &[u8; N], secret_seed: &[u8; N], adrs: &Adrs) -> [u8; N] {
+        shake_n(&[public_seed, &adrs.to_bytes(), secret_seed])
+    }
+
+    fn f<const N: usize>(public_seed: &[u8; N], adrs: &Adrs, message: &[u8; N]) -> [u8; N] {
+        shake_n(&[public_seed, &adrs.to_bytes(), message])
+    }
+
+    fn h<const N: usize>(public_seed: &[u8; N], adrs: &Adrs, left: &[u8; N], right: &[u8; N]) -> [u8; N] {
+        shake_n(&[public_seed, &adrs.to_bytes(), left, right])
+    }
+
+    fn t<const N: usize>(public_seed: &[u8; N], adrs: &Adrs, values: &[[u8; N]]) -> [u8; N] {
+        let adrs_bytes = adrs.to_bytes();
+        let mut parts: Vec<&[u8]> = vec![public_seed, &adrs_bytes];
+        parts.extend(values.iter().map(|value| value.as_slice()));
+        shake_n(&parts)
+    }
+
+    fn prf_msg<const N: usize>(secret_prf: &[u8; N], opt_rand: &[u8; N], message: &[u8]) -> [u8; N] {
+        shake_n(&[secret_prf, opt_rand, message])
+    }
+
+    fn h_msg<const N: usize>(randomizer: &[u8; N], public_seed: &[u8; N], root: &[u8; N], message: &[u8], out_len: usize) -> Vec<u8> {
+        let mut out = vec![0u8; out_len];
+        shake(&[randomizer, public_seed, root, message], &mut out);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sha2::{Digest, Sha256};
+
+    use super::mgf1;
+
+    #[test]
+    fn test_mgf1_prefix_stable() {
+        let short = mgf1::<Sha256>(b"seed", 10);
+        let long = mgf1::<Sha256>(b"seed", 70);
+
+        assert_eq!(short.len(), 10);
+        assert_eq!(long.len(), 70);
+        assert_eq!(short[..], long[..10]);
+    }
+
+    #[test]
+    fn test_mgf1_first_block() {
+        let out = mgf1::<Sha256>(b"seed", 32);
+        let expected: [u8; 32] = Sha256::new().chain_update(b"seed").chain_update([0u8; 4]).finalize().into();
+
+        assert_eq!(out, expected);
+    }
+}
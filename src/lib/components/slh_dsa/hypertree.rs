@@ -0,0 +1,59 @@
+// The hypertree from FIPS 205 section 7
+use crate::lib::components::slh_dsa::{address::Adrs, hash::SlhDsaHash, xmss::{self, XmssSignature}};
+
+pub fn sign<H: SlhDsaHash, const N: usize, const LAYERS: usize, const TREE_HEIGHT: usize>(message: &[u8; N], secret_seed: &[u8; N], public_seed: &[u8; N], tree: u64, leaf: u32) -> [XmssSignature<N, TREE_HEIGHT>; LAYERS] {
+    let mut adrs = Adrs::default();
+    let mut tree = tree;
+    let mut leaf = leaf;
+    let mut current = *message;
+
+    core::array::from_fn(|layer| {
+        if layer > 0 {
+            leaf = (tree & ((1 << TREE_HEIGHT) - 1)) as u32;
+            tree >>= TREE_HEIGHT;
+        }
+        adrs.layer = layer as u32;
+        adrs.tree = tree;
+        let signature = xmss::sign::<H, N, TREE_HEIGHT>(&current, secret_seed, leaf, public_seed, &adrs);
+        if layer + 1 < LAYERS {
+            current = xmss::public_from_signature::<H, N, TREE_HEIGHT>(leaf, &signature, &current, public_seed, &adrs);
+        }
+        signature
+    })
+}
+
+pub fn expected_root<H: SlhDsaHash, const N: usize, const LAYERS: usize, const TREE_HEIGHT: usize>(message: &[u8; N], signature: &[XmssSignature<N, TREE_HEIGHT>; LAYERS], public_seed: &[u8; N], tree: u64, leaf: u32) -> [u8; N] {
+    let mut adrs = Adrs::default();
+    let mut tree = tree;
+    let mut leaf = leaf;
+
+    signature.iter().enumerate().fold(*message, |current, (layer, xmss_signature)| {
+        if layer > 0 {
+            leaf = (tree & ((1 << TREE_HEIGHT) - 1)) as u32;
+            tree >>= TREE_HEIGHT;
+        }
+        adrs.layer = layer as u32;
+        adrs.tree = tree;
+        xmss::public_from_signature::<H, N, TREE_HEIGHT>(leaf, xmss_signature, &current, public_seed, &adrs)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lib::{components::slh_dsa::{address::Adrs, xmss}, helpers::hasher::Sha2Suite};
+    use super::*;
+
+    #[test]
+    fn test_root_matches_top_tree() {
+        let secret_seed = [7u8; 16];
+        let public_seed = [8u8; 16];
+        let message = [1u8; 16];
+        let top_adrs = Adrs { layer: 2, ..Adrs::default() };
+        let root = xmss::node::<Sha2Suite, 16>(&secret_seed, 0, 2, &public_seed, &top_adrs);
+
+        let signature = sign::<Sha2Suite, 16, 3, 2>(&message, &secret_seed, &public_seed, 0b1101, 2);
+
+        assert_eq!(expected_root::<Sha2Suite, 16, 3, 2>(&message, &signature, &public_seed, 0b1101, 2), root);
+        assert_ne!(expected_root::<Sha2Suite, 16, 3, 2>(&message, &signature, &public_seed, 0b1100, 2), root);
+    }
+}
@@ -0,0 +1,11 @@
+pub mod address;
+pub mod hash;
+pub mod wots;
+pub mod xmss;
+pub mod fors;
+pub mod hypertree;
+pub mod secret;
+pub mod signature;
+pub mod public;
+#[cfg(test)]
+pub mod tests;
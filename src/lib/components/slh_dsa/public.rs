@@ -0,0 +1,38 @@
+use std::marker::PhantomData;
+
+use crate::lib::{components::{parameters::{ParameterSet, Parameters}, slh_dsa::{hash::SlhDsaHash, signature::SlhDsaError}}, helpers::hasher::Sha2Suite};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlhDsaPublic<const N: usize, const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, H: SlhDsaHash = Sha2Suite> {
+    pub key: [u8; N],
+    pub public_seed: [u8; N],
+    pub(crate) suite: PhantomData<H>,
+}
+
+impl<const N: usize, const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, H: SlhDsaHash> SlhDsaPublic<N, K, A, LAYERS, TREE_HEIGHT, H> {
+    pub const fn size() -> usize {
+        2 * N
+    }
+
+    pub fn get_params(&self) -> Parameters {
+        Parameters::slh_dsa::<N, K, A, LAYERS, TREE_HEIGHT, H>()
+    }
+
+    pub fn parameter_set(&self) -> Option<ParameterSet> {
+        self.get_params().parameter_set()
+    }
+
+    // PK.seed || PK.root
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [self.public_seed, self.key].concat()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SlhDsaError> {
+        if bytes.len() != Self::size() {
+            return Err(SlhDsaError::WrongLength(Self::size(), bytes.len()));
+        }
+        let public_seed = bytes[..N].try_into().expect("Length is checked above");
+        let key = bytes[N..].try_into().expect("Length is checked above");
+        Ok(Self { key, public_seed, suite: PhantomData })
+    }
+}
@@ -0,0 +1,83 @@
+use std::marker::PhantomData;
+
+use crate::lib::components::{parameters::ParameterSet, slh_dsa::{
+    address::Adrs, fors, hash::SlhDsaHash, hypertree,
+    public::SlhDsaPublic,
+    signature::{SlhDsaError, SlhDsaSignature, digest_len, encode_message, fors_address, split_digest},
+    xmss
+}};
+use crate::lib::helpers::hasher::Sha2Suite;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlhDsaSigner<const N: usize, const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, H: SlhDsaHash = Sha2Suite> {
+    seed: [u8; N],
+    prf: [u8; N],
+    public_seed: [u8; N],
+    root: [u8; N],
+    suite: PhantomData<H>,
+}
+
+impl<const N: usize, const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, H: SlhDsaHash> SlhDsaSigner<N, K, A, LAYERS, TREE_HEIGHT, H> {
+    // slh_keygen_internal, FIPS 205 algorithm 18
+    pub fn new(seed: [u8; N], prf: [u8; N], public_seed: [u8; N]) -> Self {
+        let top_layer = Adrs { layer: (LAYERS - 1) as u32, ..Adrs::default() };
+        let root = xmss::node::<H, N>(&seed, 0, TREE_HEIGHT as u32, &public_seed, &top_layer);
+        Self { seed, prf, public_seed, root, suite: PhantomData }
+    }
+
+    pub fn new_random() -> Self {
+        Self::new(rand::random(), rand::random(), rand::random())
+    }
+
+    pub const fn size() -> usize {
+        4 * N
+    }
+
+    pub fn public_key(&self) -> SlhDsaPublic<N, K, A, LAYERS, TREE_HEIGHT, H> {
+        SlhDsaPublic { key: self.root, public_seed: self.public_seed, suite: PhantomData }
+    }
+
+    pub fn parameter_set(&self) -> Option<ParameterSet> {
+        self.public_key().parameter_set()
+    }
+
+    // SK.seed || SK.prf || PK.seed || PK.root
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [self.seed, self.prf, self.public_seed, self.root].concat()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SlhDsaError> {
+        if bytes.len() != Self::size() {
+            return Err(SlhDsaError::WrongLength(Self::size(), bytes.len()));
+        }
+        let part = |i: usize| -> [u8; N] { bytes[i * N..(i + 1) * N].try_into().expect("Length is checked above") };
+        Ok(Self { seed: part(0), prf: part(1), public_seed: part(2), root: part(3), suite: PhantomData })
+    }
+
+    // slh_sign_internal, FIPS 205 algorithm 19. Without additional randomness the
+    // signature is deterministic.
+    pub fn sign_internal(&self, message: &[u8], additional_random: Option<[u8; N]>) -> SlhDsaSignature<N, K, A, LAYERS, TREE_HEIGHT, H> {
+        let opt_rand = additional_random.unwrap_or(self.public_seed);
+        let randomizer = H::prf_msg(&self.prf, &opt_rand, message);
+        let digest = H::h_msg(&randomizer, &self.public_seed, &self.root, message, digest_len(K, A, LAYERS, TREE_HEIGHT));
+        let (md, tree, leaf) = split_digest::<K, A, LAYERS, TREE_HEIGHT>(&digest);
+
+        let adrs = fors_address(tree, leaf);
+        let fors = fors::sign::<H, N, K, A>(md, &self.seed, &self.public_seed, &adrs).expect("md holds K*A bits");
+        let fors_key = fors::public_from_signature::<H, N, K, A>(&fors, md, &self.public_seed, &adrs).expect("md holds K*A bits");
+        let hyper_tree = hypertree::sign::<H, N, LAYERS, TREE_HEIGHT>(&fors_key, &self.seed, &self.public_seed, tree, leaf);
+
+        SlhDsaSignature { randomizer, fors, hyper_tree, suite: PhantomData }
+    }
+
+    // Hedged signing, the default in FIPS 205
+    pub fn sign(&self, message: &[u8], context: &[u8]) -> Result<SlhDsaSignature<N, K, A, LAYERS, TREE_HEIGHT, H>, SlhDsaError> {
+        let encoded = encode_message(message, context)?;
+        Ok(self.sign_internal(&encoded, Some(rand::random())))
+    }
+
+    pub fn sign_deterministic(&self, message: &[u8], context: &[u8]) -> Result<SlhDsaSignature<N, K, A, LAYERS, TREE_HEIGHT, H>, SlhDsaError> {
+        let encoded = encode_message(message, context)?;
+        Ok(self.sign_internal(&encoded, None))
+    }
+}
@@ -0,0 +1,127 @@
+use std::marker::PhantomData;
+
+use crate::lib::components::slh_dsa::{
+    address::{AddressType, Adrs}, fors::{self, SlhForsElement}, hash::SlhDsaHash, hypertree,
+    public::SlhDsaPublic, wots::wots_len, xmss::XmssSignature
+};
+use crate::lib::helpers::hasher::Sha2Suite;
+
+#[derive(Debug, PartialEq)]
+pub enum SlhDsaError {
+    ContextTooLong(usize),
+    WrongLength(usize, usize), // expected, got
+    InvalidSignature,
+}
+
+// M' for the pure (non pre-hashed) variant, FIPS 205 algorithm 22
+pub fn encode_message(message: &[u8], context: &[u8]) -> Result<Vec<u8>, SlhDsaError> {
+    if context.len() > 255 {
+        return Err(SlhDsaError::ContextTooLong(context.len()));
+    }
+    let mut encoded = Vec::with_capacity(2 + context.len() + message.len());
+    encoded.push(0);
+    encoded.push(context.len() as u8);
+    encoded.extend_from_slice(context);
+    encoded.extend_from_slice(message);
+    Ok(encoded)
+}
+
+pub const fn digest_len(k: usize, a: usize, layers: usize, tree_height: usize) -> usize {
+    (k * a).div_ceil(8) + ((layers - 1) * tree_height).div_ceil(8) + tree_height.div_ceil(8)
+}
+
+fn bytes_to_u64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0u64, |acc, byte| (acc << 8) | *byte as u64)
+}
+
+// Splits H_msg output into the FORS digest, the hypertree index and the leaf index
+pub fn split_digest<const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize>(digest: &[u8]) -> (&[u8], u64, u32) {
+    let tree_bits = (LAYERS - 1) * TREE_HEIGHT;
+    let md_len = (K * A).div_ceil(8);
+    let tree_len = tree_bits.div_ceil(8);
+
+    let md = &digest[..md_len];
+    let tree = match tree_bits {
+        64.. => bytes_to_u64(&digest[md_len..md_len + tree_len]),
+        _ => bytes_to_u64(&digest[md_len..md_len + tree_len]) & ((1u64 << tree_bits) - 1),
+    };
+    let leaf = bytes_to_u64(&digest[md_len + tree_len..]) & ((1u64 << TREE_HEIGHT) - 1);
+    (md, tree, leaf as u32)
+}
+
+pub fn fors_address(tree: u64, leaf: u32) -> Adrs {
+    let mut adrs = Adrs { tree, ..Adrs::default() };
+    adrs.set_type_and_clear(AddressType::ForsTree);
+    adrs.key_pair = leaf;
+    adrs
+}
+
+fn take<const N: usize>(bytes: &[u8], offset: &mut usize) -> [u8; N] {
+    let out = bytes[*offset..*offset + N].try_into().expect("Length is checked before reading");
+    *offset += N;
+    out
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlhDsaSignature<const N: usize, const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, H: SlhDsaHash = Sha2Suite> {
+    pub randomizer: [u8; N],
+    pub fors: [SlhForsElement<N, A>; K],
+    pub hyper_tree: [XmssSignature<N, TREE_HEIGHT>; LAYERS],
+    pub(crate) suite: PhantomData<H>,
+}
+
+impl<const N: usize, const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, H: SlhDsaHash> SlhDsaSignature<N, K, A, LAYERS, TREE_HEIGHT, H> {
+    pub const fn size() -> usize {
+        N * (1 + K * (1 + A) + LAYERS * TREE_HEIGHT + LAYERS * wots_len(N))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::size());
+        out.extend_from_slice(&self.randomizer);
+        for element in &self.fors {
+            out.extend_from_slice(&element.secret_key);
+            element.auth_path.iter().for_each(|node| out.extend_from_slice(node));
+        }
+        for layer in &self.hyper_tree {
+            layer.wots.iter().for_each(|chain| out.extend_from_slice(chain));
+            layer.auth_path.iter().for_each(|node| out.extend_from_slice(node));
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SlhDsaError> {
+        if bytes.len() != Self::size() {
+            return Err(SlhDsaError::WrongLength(Self::size(), bytes.len()));
+        }
+        let mut offset = 0;
+        let randomizer = take(bytes, &mut offset);
+        let fors = core::array::from_fn(|_| {
+            let secret_key = take(bytes, &mut offset);
+            let auth_path = core::array::from_fn(|_| take(bytes, &mut offset));
+            SlhForsElement { secret_key, auth_path }
+        });
+        let hyper_tree = core::array::from_fn(|_| {
+            let wots = (0..wots_len(N)).map(|_| take(bytes, &mut offset)).collect();
+            let auth_path = core::array::from_fn(|_| take(bytes, &mut offset));
+            XmssSignature { wots, auth_path }
+        });
+        Ok(Self { randomizer, fors, hyper_tree, suite: PhantomData })
+    }
+
+    pub fn validate_internal(&self, message: &[u8], public_key: &SlhDsaPublic<N, K, A, LAYERS, TREE_HEIGHT, H>) -> Result<(), SlhDsaError> {
+        let digest = H::h_msg(&self.randomizer, &public_key.public_seed, &public_key.key, message, digest_len(K, A, LAYERS, TREE_HEIGHT));
+        let (md, tree, leaf) = split_digest::<K, A, LAYERS, TREE_HEIGHT>(&digest);
+
+        let fors_key = fors::public_from_signature::<H, N, K, A>(&self.fors, md, &public_key.public_seed, &fors_address(tree, leaf)).expect("md holds K*A bits");
+        let root = hypertree::expected_root::<H, N, LAYERS, TREE_HEIGHT>(&fors_key, &self.hyper_tree, &public_key.public_seed, tree, leaf);
+        match root == public_key.key {
+            true => Ok(()),
+            false => Err(SlhDsaError::InvalidSignature)
+        }
+    }
+
+    pub fn validate(&self, message: &[u8], context: &[u8], public_key: &SlhDsaPublic<N, K, A, LAYERS, TREE_HEIGHT, H>) -> Result<(), SlhDsaError> {
+        let encoded = encode_message(message, context)?;
+        self.validate_internal(&encoded, public_key)
+    }
+}
@@ -0,0 +1,147 @@
+use hex_literal::hex;
+
+use crate::lib::{
+    components::slh_dsa::{public::SlhDsaPublic, secret::SlhDsaSigner, signature::{SlhDsaError, SlhDsaSignature}},
+    helpers::hasher::{Shake256Suite, hash_message}
+};
+
+// Reference values below were produced with OpenSSL 3.5 (SLH-DSA-SHA2-* and SLH-DSA-SHAKE-*), using
+// SK.seed || SK.prf || PK.seed = 0, 1, 2, ... and deterministic signing.
+const MESSAGE: &[u8] = b"Hello, SLH-DSA!";
+
+fn counting_seeds<const N: usize>() -> ([u8; N], [u8; N], [u8; N]) {
+    let bytes: Vec<u8> = (0..3 * N as u8).collect();
+    (bytes[..N].try_into().unwrap(), bytes[N..2 * N].try_into().unwrap(), bytes[2 * N..].try_into().unwrap())
+}
+
+#[test]
+fn test_sha2_128f_matches_reference() {
+    let (seed, prf, public_seed) = counting_seeds::<16>();
+    let signer = SlhDsaSigner::<16, 33, 6, 22, 3>::new(seed, prf, public_seed);
+    let public_key = signer.public_key();
+    assert_eq!(public_key.to_bytes(), hex!("202122232425262728292a2b2c2d2e2f3b56e816847f000386aeec2e2bb9e1b5"));
+
+    let signature = signer.sign_deterministic(MESSAGE, b"").unwrap();
+    let bytes = signature.to_bytes();
+    assert_eq!(bytes.len(), 17088);
+    assert_eq!(bytes[..16], hex!("45e9b50ed0bd7409aaa94e7488a0029a"));
+    assert_eq!(hash_message(&bytes), hex!("e5d040a9c589d2494f2b7ba47da0533b42baf937ed398aa571706269e89c3b3b"));
+    assert!(signature.validate(MESSAGE, b"", &public_key).is_ok());
+}
+
+#[test]
+fn test_sha2_128f_context_matches_reference() {
+    let (seed, prf, public_seed) = counting_seeds::<16>();
+    let signer = SlhDsaSigner::<16, 33, 6, 22, 3>::new(seed, prf, public_seed);
+
+    let signature = signer.sign_deterministic(MESSAGE, b"firmware").unwrap();
+    assert_eq!(hash_message(&signature.to_bytes()), hex!("2c924a75804a4b9cc8129cb7a6e121792e55ce4ca028d60d347be6806f0daf0c"));
+    assert!(signature.validate(MESSAGE, b"firmware", &signer.public_key()).is_ok());
+    assert_eq!(signature.validate(MESSAGE, b"firmwarE", &signer.public_key()), Err(SlhDsaError::InvalidSignature));
+}
+
+#[test]
+fn test_sha2_192f_matches_reference() {
+    let (seed, prf, public_seed) = counting_seeds::<24>();
+    let signer = SlhDsaSigner::<24, 33, 8, 22, 3>::new(seed, prf, public_seed);
+    assert_eq!(signer.public_key().to_bytes(), hex!("303132333435363738393a3b3c3d3e3f40414243444546479236ccebbb3a90ac2452dd89de49dab1340ec02419a2870e"));
+
+    let signature = signer.sign_deterministic(MESSAGE, b"").unwrap();
+    assert_eq!(signature.to_bytes().len(), 35664);
+    assert_eq!(hash_message(&signature.to_bytes()), hex!("0b337f84c6d9dd8367b1623176305f250616c4eae49ec25ea035161db0386383"));
+}
+
+#[test]
+fn test_sha2_256f_matches_reference() {
+    let (seed, prf, public_seed) = counting_seeds::<32>();
+    let signer = SlhDsaSigner::<32, 35, 9, 17, 4>::new(seed, prf, public_seed);
+    assert_eq!(signer.public_key().to_bytes(), hex!("404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f42cffe64ddbd6731063752684df77c8b58c225dc6b491208916b654ea1393176"));
+
+    let signature = signer.sign_deterministic(MESSAGE, b"").unwrap();
+    assert_eq!(signature.to_bytes().len(), 49856);
+    assert_eq!(hash_message(&signature.to_bytes()), hex!("fbe03f518baa9cad525155bc3e6e8b22bd229e520cab6747272cea05f3bfa589"));
+}
+
+#[test]
+fn test_sha2_128s_keygen_matches_reference() {
+    let (seed, prf, public_seed) = counting_seeds::<16>();
+    let signer = SlhDsaSigner::<16, 14, 12, 7, 9>::new(seed, prf, public_seed);
+    assert_eq!(signer.public_key().to_bytes(), hex!("202122232425262728292a2b2c2d2e2f990ce6298792b128846a8e4a3a68954c"));
+    assert_eq!(SlhDsaSignature::<16, 14, 12, 7, 9>::size(), 7856);
+}
+
+#[test]
+fn test_shake_128f_matches_reference() {
+    let (seed, prf, public_seed) = counting_seeds::<16>();
+    let signer = SlhDsaSigner::<16, 33, 6, 22, 3, Shake256Suite>::new(seed, prf, public_seed);
+    let public_key = signer.public_key();
+    assert_eq!(public_key.to_bytes(), hex!("202122232425262728292a2b2c2d2e2fa90e4715b9a925c332801767fd786371"));
+
+    let signature = signer.sign_deterministic(MESSAGE, b"").unwrap();
+    assert_eq!(hash_message(&signature.to_bytes()), hex!("583883ad1dedf05b73f69ea49f433041f59e0ee2152b6077cfdfcb0c1c0ef30e"));
+    assert!(signature.validate(MESSAGE, b"", &public_key).is_ok());
+}
+
+#[test]
+fn test_shake_192f_matches_reference() {
+    let (seed, prf, public_seed) = counting_seeds::<24>();
+    let signer = SlhDsaSigner::<24, 33, 8, 22, 3, Shake256Suite>::new(seed, prf, public_seed);
+    assert_eq!(signer.public_key().to_bytes(), hex!("303132333435363738393a3b3c3d3e3f40414243444546473f01b06bebed020a459696868d115fe8507ded8dc08e825d"));
+
+    let signature = signer.sign_deterministic(MESSAGE, b"").unwrap();
+    assert_eq!(hash_message(&signature.to_bytes()), hex!("14f7efe30b411929d47c34ca7d23daa5d419be32f8abf48103ccf6213d9032d3"));
+}
+
+#[test]
+fn test_shake_256f_matches_reference() {
+    let (seed, prf, public_seed) = counting_seeds::<32>();
+    let signer = SlhDsaSigner::<32, 35, 9, 17, 4, Shake256Suite>::new(seed, prf, public_seed);
+    assert_eq!(signer.public_key().to_bytes(), hex!("404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f818d7e76beef979b5bbf9161fdefa21bd0fe0bfe19157a5711a8de8a8f6878e6"));
+
+    let signature = signer.sign_deterministic(MESSAGE, b"").unwrap();
+    assert_eq!(hash_message(&signature.to_bytes()), hex!("8d1b2a5836f755646dc890ef6b405a26c404d30c68655a329650339d360059af"));
+}
+
+#[test]
+fn test_hedged_signatures_differ_and_validate() {
+    let signer = SlhDsaSigner::<16, 33, 6, 22, 3>::new_random();
+    let public_key = signer.public_key();
+
+    let signature1 = signer.sign(MESSAGE, b"").unwrap();
+    let signature2 = signer.sign(MESSAGE, b"").unwrap();
+
+    assert_ne!(signature1.randomizer, signature2.randomizer);
+    assert!(signature1.validate(MESSAGE, b"", &public_key).is_ok());
+    assert!(signature2.validate(MESSAGE, b"", &public_key).is_ok());
+    assert_eq!(signature1.validate(b"Hello, SLH-DSA?", b"", &public_key), Err(SlhDsaError::InvalidSignature));
+}
+
+#[test]
+fn test_context_too_long() {
+    let signer = SlhDsaSigner::<16, 33, 6, 22, 3>::new([1u8; 16], [2u8; 16], [3u8; 16]);
+
+    assert_eq!(signer.sign(MESSAGE, &[0u8; 256]).err(), Some(SlhDsaError::ContextTooLong(256)));
+}
+
+#[test]
+fn test_keys_and_signature_to_from_bytes() {
+    let signer = SlhDsaSigner::<16, 33, 6, 22, 3>::new([1u8; 16], [2u8; 16], [3u8; 16]);
+    let public_key = signer.public_key();
+    let signature = signer.sign_deterministic(MESSAGE, b"").unwrap();
+
+    let loaded_signer = SlhDsaSigner::<16, 33, 6, 22, 3>::from_bytes(&signer.to_bytes()).unwrap();
+    let loaded_public = SlhDsaPublic::<16, 33, 6, 22, 3>::from_bytes(&public_key.to_bytes()).unwrap();
+    let loaded_signature = SlhDsaSignature::<16, 33, 6, 22, 3>::from_bytes(&signature.to_bytes()).unwrap();
+
+    assert_eq!(loaded_signer, signer);
+    assert_eq!(loaded_public, public_key);
+    assert_eq!(loaded_signature, signature);
+
+    let mut tampered = signature.to_bytes();
+    tampered[100] ^= 1;
+    let tampered = SlhDsaSignature::<16, 33, 6, 22, 3>::from_bytes(&tampered).unwrap();
+    assert_eq!(tampered.validate(MESSAGE, b"", &public_key), Err(SlhDsaError::InvalidSignature));
+
+    assert_eq!(SlhDsaSignature::<16, 33, 6, 22, 3>::from_bytes(&[0u8; 10]).err(), Some(SlhDsaError::WrongLength(17088, 10)));
+    assert_eq!(SlhDsaPublic::<16, 33, 6, 22, 3>::from_bytes(&[0u8; 31]).err(), Some(SlhDsaError::WrongLength(32, 31)));
+}
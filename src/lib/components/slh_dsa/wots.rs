@@ -0,0 +1,129 @@
+// WOTS+ as defined in FIPS 205 section 5, with w = 16 (lg_w = 4) for every parameter set
+use crate::lib::components::slh_dsa::{address::{AddressType, Adrs}, hash::SlhDsaHash};
+
+pub const LG_W: usize = 4;
+pub const W: u32 = 1 << LG_W;
+const CHECKSUM_LEN: usize = 3;
+
+pub const fn message_len(n: usize) -> usize {
+    8 * n / LG_W
+}
+
+pub const fn wots_len(n: usize) -> usize {
+    message_len(n) + CHECKSUM_LEN
+}
+
+// base_2b from FIPS 205 algorithm 4, for b not larger than 16 bits
+pub fn base_2b(x: &[u8], b: usize, out_len: usize) -> Vec<u32> {
+    let mut bits = 0usize;
+    let mut total = 0u32;
+    let mut bytes = x.iter();
+    (0..out_len).map(|_| {
+        while bits < b {
+            total = (total << 8) | *bytes.next().expect("Not enough input bytes for base_2b") as u32;
+            bits += 8;
+        }
+        bits -= b;
+        (total >> bits) & ((1 << b) - 1)
+    }).collect()
+}
+
+fn message_digits<const N: usize>(message: &[u8; N]) -> Vec<u32> {
+    let mut digits = base_2b(message, LG_W, message_len(N));
+    let checksum: u32 = digits.iter().map(|digit| W - 1 - digit).sum();
+    // Left align the 12 checksum bits in two bytes
+    let checksum_bytes = ((checksum << 4) as u16).to_be_bytes();
+    digits.extend(base_2b(&checksum_bytes, LG_W, CHECKSUM_LEN));
+    digits
+}
+
+fn chain<H: SlhDsaHash, const N: usize>(x: [u8; N], start: u32, steps: u32, public_seed: &[u8; N], adrs: &mut Adrs) -> [u8; N] {
+    (start..start + steps).fold(x, |acc, j| {
+        adrs.set_hash_address(j);
+        H::f(public_seed, adrs, &acc)
+    })
+}
+
+fn secret_key<H: SlhDsaHash, const N: usize>(secret_seed: &[u8; N], public_seed: &[u8; N], adrs: &Adrs, chain_idx: u32) -> [u8; N] {
+    let mut sk_adrs = *adrs;
+    sk_adrs.set_type_and_clear(AddressType::WotsPrf);
+    sk_adrs.key_pair = adrs.key_pair;
+    sk_adrs.set_chain_address(chain_idx);
+    H::prf(public_seed, secret_seed, &sk_adrs)
+}
+
+fn compress_public<H: SlhDsaHash, const N: usize>(chains: &[[u8; N]], public_seed: &[u8; N], adrs: &Adrs) -> [u8; N] {
+    let mut pk_adrs = *adrs;
+    pk_adrs.set_type_and_clear(AddressType::WotsPk);
+    pk_adrs.key_pair = adrs.key_pair;
+    H::t(public_seed, &pk_adrs, chains)
+}
+
+pub fn generate_public_key<H: SlhDsaHash, const N: usize>(secret_seed: &[u8; N], public_seed: &[u8; N], adrs: &Adrs) -> [u8; N] {
+    let mut chain_adrs = *adrs;
+    let chains: Vec<[u8; N]> = (0..wots_len(N) as u32).map(|i| {
+        let sk = secret_key::<H, N>(secret_seed, public_seed, adrs, i);
+        chain_adrs.set_chain_address(i);
+        chain::<H, N>(sk, 0, W - 1, public_seed, &mut chain_adrs)
+    }).collect();
+    compress_public::<H, N>(&chains, public_seed, adrs)
+}
+
+pub fn sign<H: SlhDsaHash, const N: usize>(message: &[u8; N], secret_seed: &[u8; N], public_seed: &[u8; N], adrs: &Adrs) -> Vec<[u8; N]> {
+    let mut chain_adrs = *adrs;
+    message_digits(message).into_iter().enumerate().map(|(i, digit)| {
+        let sk = secret_key::<H, N>(secret_seed, public_seed, adrs, i as u32);
+        chain_adrs.set_chain_address(i as u32);
+        chain::<H, N>(sk, 0, digit, public_seed, &mut chain_adrs)
+    }).collect()
+}
+
+pub fn public_from_signature<H: SlhDsaHash, const N: usize>(signature: &[[u8; N]], message: &[u8; N], public_seed: &[u8; N], adrs: &Adrs) -> [u8; N] {
+    let mut chain_adrs = *adrs;
+    let chains: Vec<[u8; N]> = message_digits(message).into_iter().enumerate().map(|(i, digit)| {
+        chain_adrs.set_chain_address(i as u32);
+        chain::<H, N>(signature[i], digit, W - 1 - digit, public_seed, &mut chain_adrs)
+    }).collect();
+    compress_public::<H, N>(&chains, public_seed, adrs)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lib::{components::{fors::indices::message_to_indices, slh_dsa::address::Adrs}, helpers::hasher::Sha2Suite};
+    use super::*;
+
+    #[test]
+    fn test_base_2b_matches_message_to_indices() {
+        let message: Vec<u8> = (0..40u8).map(|i| i.wrapping_mul(37)).collect();
+
+        assert_eq!(base_2b(&message, 4, 64), message_to_indices::<64, 4>(&message).unwrap().to_vec());
+        assert_eq!(base_2b(&message, 6, 33), message_to_indices::<33, 6>(&message).unwrap().to_vec());
+        assert_eq!(base_2b(&message, 14, 22), message_to_indices::<22, 14>(&message).unwrap().to_vec());
+    }
+
+    #[test]
+    fn test_checksum_digits() {
+        let zeros = message_digits(&[0u8; 16]);
+        assert_eq!(zeros.len(), wots_len(16));
+        // 32 digits of 0 give a checksum of 32 * 15 = 480 = 0x1e0
+        assert_eq!(zeros[32..], [1, 14, 0]);
+
+        let ones = message_digits(&[0xffu8; 16]);
+        assert_eq!(ones[32..], [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_sign_and_recover() {
+        let secret_seed = [1u8; 16];
+        let public_seed = [2u8; 16];
+        let adrs = Adrs { layer: 1, tree: 5, key_pair: 3, ..Adrs::default() };
+        let message = [0x5au8; 16];
+
+        let public_key = generate_public_key::<Sha2Suite, 16>(&secret_seed, &public_seed, &adrs);
+        let signature = sign::<Sha2Suite, 16>(&message, &secret_seed, &public_seed, &adrs);
+
+        assert_eq!(signature.len(), wots_len(16));
+        assert_eq!(public_from_signature::<Sha2Suite, 16>(&signature, &message, &public_seed, &adrs), public_key);
+        assert_ne!(public_from_signature::<Sha2Suite, 16>(&signature, &[0x5bu8; 16], &public_seed, &adrs), public_key);
+    }
+}
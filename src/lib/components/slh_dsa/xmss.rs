@@ -0,0 +1,75 @@
+// XMSS trees from FIPS 205 section 6, one of these per hypertree layer
+use crate::lib::components::slh_dsa::{address::{AddressType, Adrs}, hash::SlhDsaHash, wots};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmssSignature<const N: usize, const TREE_HEIGHT: usize> {
+    pub wots: Vec<[u8; N]>,
+    pub auth_path: [[u8; N]; TREE_HEIGHT],
+}
+
+pub fn node<H: SlhDsaHash, const N: usize>(secret_seed: &[u8; N], index: u32, height: u32, public_seed: &[u8; N], adrs: &Adrs) -> [u8; N] {
+    let mut node_adrs = *adrs;
+    if height == 0 {
+        node_adrs.set_type_and_clear(AddressType::WotsHash);
+        node_adrs.key_pair = index;
+        return wots::generate_public_key::<H, N>(secret_seed, public_seed, &node_adrs);
+    }
+    let left = node::<H, N>(secret_seed, 2 * index, height - 1, public_seed, adrs);
+    let right = node::<H, N>(secret_seed, 2 * index + 1, height - 1, public_seed, adrs);
+    node_adrs.set_type_and_clear(AddressType::Tree);
+    node_adrs.tree_height = height;
+    node_adrs.tree_index = index;
+    H::h(public_seed, &node_adrs, &left, &right)
+}
+
+pub fn sign<H: SlhDsaHash, const N: usize, const TREE_HEIGHT: usize>(message: &[u8; N], secret_seed: &[u8; N], index: u32, public_seed: &[u8; N], adrs: &Adrs) -> XmssSignature<N, TREE_HEIGHT> {
+    let auth_path = core::array::from_fn(|j| {
+        let sibling = (index >> j) ^ 1;
+        node::<H, N>(secret_seed, sibling, j as u32, public_seed, adrs)
+    });
+    let mut wots_adrs = *adrs;
+    wots_adrs.set_type_and_clear(AddressType::WotsHash);
+    wots_adrs.key_pair = index;
+    let wots = wots::sign::<H, N>(message, secret_seed, public_seed, &wots_adrs);
+
+    XmssSignature { wots, auth_path }
+}
+
+pub fn public_from_signature<H: SlhDsaHash, const N: usize, const TREE_HEIGHT: usize>(index: u32, signature: &XmssSignature<N, TREE_HEIGHT>, message: &[u8; N], public_seed: &[u8; N], adrs: &Adrs) -> [u8; N] {
+    let mut node_adrs = *adrs;
+    node_adrs.set_type_and_clear(AddressType::WotsHash);
+    node_adrs.key_pair = index;
+    let mut current = wots::public_from_signature::<H, N>(&signature.wots, message, public_seed, &node_adrs);
+
+    node_adrs.set_type_and_clear(AddressType::Tree);
+    node_adrs.tree_index = index;
+    for (k, sibling) in signature.auth_path.iter().enumerate() {
+        node_adrs.tree_height = k as u32 + 1;
+        node_adrs.tree_index /= 2;
+        current = match (index >> k).is_multiple_of(2) {
+            true => H::h(public_seed, &node_adrs, &current, sibling),
+            false => H::h(public_seed, &node_adrs, sibling, &current),
+        };
+    }
+    current
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lib::{components::slh_dsa::address::Adrs, helpers::hasher::Sha2Suite};
+    use super::*;
+
+    #[test]
+    fn test_root_from_any_leaf() {
+        let secret_seed = [3u8; 16];
+        let public_seed = [4u8; 16];
+        let adrs = Adrs { layer: 2, tree: 11, ..Adrs::default() };
+        let message = [9u8; 16];
+
+        let root = node::<Sha2Suite, 16>(&secret_seed, 0, 3, &public_seed, &adrs);
+        for index in 0..8 {
+            let signature = sign::<Sha2Suite, 16, 3>(&message, &secret_seed, index, &public_seed, &adrs);
+            assert_eq!(public_from_signature::<Sha2Suite, 16, 3>(index, &signature, &message, &public_seed, &adrs), root);
+        }
+    }
+}
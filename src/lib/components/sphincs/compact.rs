@@ -0,0 +1,136 @@
+use std::marker::PhantomData;
+
+use crate::lib::{
+    components::{
+        fors::{indices::message_to_indices, public::{ForsSignature, ForsSignatureElement}},
+        hypertree::public::HyperTreeSignature, merkle_tree::proof::MerkleProof,
+        sphincs::{public::SphincsPublic, signature::{SignatureValidResult, SigntureError, SphincsSignature, digest_len, index_digest_into, index_mask, signing_context, split_digest}},
+        wots_plus::signature::{WotsPlusSignature, checksum_chains, message_chains}
+    },
+    helpers::{encoding::{DecodeError, Reader}, hasher::{HashSuite, Sha2Suite}, random_generator::HashData}
+};
+
+// The WOTS+ chains and the authentication path of one hypertree layer
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompactLayer<const TREE_HEIGHT: usize, const N: usize = 32> {
+    pub chains: Vec<HashData<N>>,
+    pub auth_path: [HashData<N>; TREE_HEIGHT],
+}
+
+// A SphincsSignature without anything the verifier can recompute: the data hash,
+// the contexts and every embedded public key and root. The verifier rebuilds the
+// addresses from the index, and the keys from the message and the public key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompactSphincsSignature<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize = 32, const W: usize = 256, H: HashSuite = Sha2Suite> {
+    pub randomizer: HashData<N>,
+    pub index: u128,
+    pub timestamp: u128,
+    pub fors: [ForsSignatureElement<A, N>; K],
+    pub layers: [CompactLayer<TREE_HEIGHT, N>; LAYERS],
+    pub(crate) suite: PhantomData<H>,
+}
+
+impl<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite> CompactSphincsSignature<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+    const INDEX_SIZE: usize = (LAYERS * TREE_HEIGHT).div_ceil(8);
+    const CHAINS: usize = message_chains(N, W) + checksum_chains(N, W);
+
+    // The randomizer, the index in as few bytes as it needs, a 16 byte timestamp,
+    // then the FORS elements and the layers
+    pub const fn size() -> usize {
+        N + Self::INDEX_SIZE + 16 + K * ForsSignatureElement::<A, N>::size() + LAYERS * (Self::CHAINS + TREE_HEIGHT) * N
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::size());
+        out.extend_from_slice(&self.randomizer);
+        out.extend_from_slice(&self.index.to_be_bytes()[16 - Self::INDEX_SIZE..]);
+        out.extend_from_slice(&self.timestamp.to_be_bytes());
+        self.fors.iter().for_each(|element| out.extend(element.to_bytes()));
+        for layer in &self.layers {
+            layer.chains.iter().for_each(|chain| out.extend_from_slice(chain));
+            layer.auth_path.iter().for_each(|node| out.extend_from_slice(node));
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes, Self::size())?;
+        let randomizer = reader.array();
+        let index = reader.take(Self::INDEX_SIZE).iter().fold(0u128, |acc, byte| (acc << 8) | *byte as u128);
+        // The unused high bits must be 0, so every signature has one encoding
+        if index & !index_mask(LAYERS * TREE_HEIGHT) != 0 {
+            return Err(DecodeError::IndexOutOfRange(index));
+        }
+        let timestamp = u128::from_be_bytes(reader.array());
+        let fors = core::array::from_fn(|_| {
+            ForsSignatureElement::from_bytes(reader.take(ForsSignatureElement::<A, N>::size())).expect("The element length is fixed")
+        });
+        let layers = core::array::from_fn(|_| {
+            let chains = reader.hashes(Self::CHAINS);
+            let auth_path = core::array::from_fn(|_| reader.array());
+            CompactLayer { chains, auth_path }
+        });
+        Ok(Self { randomizer, index, timestamp, fors, layers, suite: PhantomData })
+    }
+
+    // Rebuilds the full signature, recomputing every context, key and root on the way up
+    pub fn expand(&self, message: &[u8], public_key: &SphincsPublic<K, A, LAYERS, TREE_HEIGHT, N, W, H>) -> SphincsSignature<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+        let mut digest = vec![0u8; digest_len(K, A, LAYERS, TREE_HEIGHT)];
+        index_digest_into::<K, A, LAYERS, TREE_HEIGHT, N, H>(&self.randomizer, &public_key.public_seed, &public_key.key, self.timestamp, message, Some(self.index), &mut digest);
+        let (md, _) = split_digest::<K, A, LAYERS, TREE_HEIGHT>(&digest);
+
+        let context = |level: usize| signing_context(public_key.public_seed, self.index, level, TREE_HEIGHT);
+
+        let mut fors = ForsSignature { signatures: self.fors.clone(), context: context(0), public_key: [0u8; N], suite: PhantomData };
+        fors.public_key = fors.clone().get_expected_public_from_hash(message_to_indices::<K, A>(md).expect("H_msg holds K*A bits"));
+
+        let mut current = fors.public_key;
+        let proofs = core::array::from_fn(|level| {
+            let layer = &self.layers[level];
+            let mut signature = WotsPlusSignature::<N, W, H> {
+                context: context(level),
+                message_hashes: layer.chains[..message_chains(N, W)].to_vec(),
+                checksum_hashes: layer.chains[message_chains(N, W)..].to_vec(),
+                public_key: [0u8; N],
+                suite: PhantomData
+            };
+            signature.public_key = signature.get_expected_public_from_message(&current);
+            let mut proof = MerkleProof { public_key: [0u8; N], signature, merkle_leaves: layer.auth_path };
+            proof.public_key = proof.clone().get_expected_public_key(&current);
+            current = proof.public_key;
+            proof
+        });
+
+        SphincsSignature {
+            data_hash: H::hash_message(message),
+            randomizer: self.randomizer,
+            fors,
+            hyper_tree: HyperTreeSignature { proofs, public_key: current },
+            timestamp: self.timestamp
+        }
+    }
+
+    pub fn validate(&self, message: &[u8], public_key: &SphincsPublic<K, A, LAYERS, TREE_HEIGHT, N, W, H>) -> Result<SignatureValidResult<N>, SigntureError<N>> {
+        self.expand(message, public_key).validate(message, public_key)
+    }
+}
+
+impl<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite> SphincsSignature<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+    pub fn to_compact(&self) -> CompactSphincsSignature<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+        let layers = core::array::from_fn(|level| {
+            let proof = &self.hyper_tree.proofs[level];
+            CompactLayer {
+                chains: proof.signature.message_hashes.iter().chain(&proof.signature.checksum_hashes).copied().collect(),
+                auth_path: proof.merkle_leaves
+            }
+        });
+        CompactSphincsSignature {
+            randomizer: self.randomizer,
+            index: self.fors.context.address.position,
+            timestamp: self.timestamp,
+            fors: self.fors.signatures.clone(),
+            layers,
+            suite: PhantomData
+        }
+    }
+}
@@ -0,0 +1,266 @@
+// Leases of index blocks for signing workers that share one key. A worker takes a
+// lease from the shared store once and then signs from it without coordinating
+// per signature. Every grant and release is appended to a ledger, and the indices
+// a lease did not use, whether it was released early or dropped, are never
+// handed out again.
+use std::{fs::{self, File, OpenOptions}, io::Write, ops::Range, path::{Path, PathBuf}};
+
+use serde_json::{Value, json};
+
+use crate::lib::components::sphincs::state::{IndexState, IndexStore, StateError, aligned_block};
+
+// The leaves under one tree `layers` layers above the bottom of the hypertree. A
+// lease of this size is aligned, so it covers exactly the subtree of one Address.
+pub const fn subtree_leaves(tree_height: usize, layers: usize) -> u64 {
+    assert!(tree_height * layers < 64, "The subtree has more than 2^63 leaves");
+    1 << (tree_height * layers)
+}
+
+// A block of indices for one worker. It is not Clone, so its indices can only be
+// used once; when it is dropped the rest of the block is abandoned.
+#[derive(Debug, PartialEq, Eq)]
+pub struct IndexLease {
+    worker: String,
+    block: Range<u64>,
+    next: u64,
+    created_at: u128,
+}
+
+impl IndexLease {
+    pub fn worker(&self) -> &str {
+        &self.worker
+    }
+
+    pub fn block(&self) -> Range<u64> {
+        self.block.clone()
+    }
+
+    pub fn used(&self) -> u64 {
+        self.next - self.block.start
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.block.end - self.next
+    }
+}
+
+impl IndexStore for IndexLease {
+    // The time floor was applied when the lease was granted
+    fn reserve_block(&mut self, _now: u128, size: u64) -> Result<Range<u64>, StateError> {
+        let block = aligned_block(self.next, size)?;
+        if block.end > self.block.end {
+            return Err(StateError::Exhausted(self.block.end));
+        }
+        self.next = block.end;
+        Ok(block)
+    }
+
+    fn commit(&mut self, index: u64) -> Result<(), StateError> {
+        match (self.block.start..self.next).contains(&index) {
+            true => Ok(()),
+            false => Err(StateError::NotReserved(index)),
+        }
+    }
+
+    fn current(&self) -> IndexState {
+        IndexState { created_at: self.created_at, next_sequence: self.next }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    pub used: u64,
+    pub at: u64,
+}
+
+// One lease as the ledger tells it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaseRecord {
+    pub worker: String,
+    pub block: Range<u64>,
+    pub granted_at: u64,
+    pub released: Option<Release>, // None while the worker holds it, or if it was abandoned
+}
+
+// Grants leases out of a store and logs them to a ledger of JSON lines. With a
+// FileIndexStore, the store's lock also orders the ledger writes of workers in
+// separate processes.
+#[derive(Debug)]
+pub struct LeaseManager<S: IndexStore> {
+    store: S,
+    ledger: File,
+    ledger_path: PathBuf,
+}
+
+impl<S: IndexStore> LeaseManager<S> {
+    pub fn new(store: S, ledger: &Path) -> Result<Self, StateError> {
+        let file = OpenOptions::new().append(true).create(true).open(ledger)?;
+        Ok(Self { store, ledger: file, ledger_path: ledger.to_path_buf() })
+    }
+
+    pub fn ledger_path(&self) -> &Path {
+        &self.ledger_path
+    }
+
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    fn append(&mut self, event: Value) -> Result<(), StateError> {
+        self.ledger.write_all(format!("{event}\n").as_bytes())?;
+        Ok(self.ledger.sync_data()?)
+    }
+
+    // The next `size` indices, aligned to `size`, for `worker`
+    pub fn lease(&mut self, worker: &str, size: u64, now: u128) -> Result<IndexLease, StateError> {
+        let block = self.store.reserve_block(now, size)?;
+        self.append(json!({"event": "granted", "worker": worker, "start": block.start, "end": block.end, "at": millis(now)}))?;
+        self.store.commit(block.start)?;
+        Ok(IndexLease { worker: worker.to_string(), next: block.start, block, created_at: self.store.current().created_at })
+    }
+
+    // Logs how much of the lease was used. The rest is abandoned, not returned.
+    pub fn release(&mut self, lease: IndexLease, now: u128) -> Result<(), StateError> {
+        self.append(json!({"event": "released", "worker": lease.worker, "start": lease.block.start, "used": lease.used(), "at": millis(now)}))
+    }
+}
+
+fn millis(now: u128) -> u64 {
+    u64::try_from(now).unwrap_or(u64::MAX)
+}
+
+// Every lease in the ledger, in the order it was granted. A last line without a
+// newline is an append cut short by a crash, and is skipped.
+pub fn read_ledger(path: &Path) -> Result<Vec<LeaseRecord>, StateError> {
+    let text = fs::read_to_string(path)?;
+    let complete = &text[..text.rfind('\n').map_or(0, |end| end + 1)];
+    let mut records: Vec<LeaseRecord> = Vec::new();
+
+    for (line_number, line) in complete.lines().enumerate() {
+        let malformed = || StateError::Ledger(line_number + 1);
+        let event: Value = serde_json::from_str(line).map_err(|_| malformed())?;
+        let number = |name: &str| event.get(name).and_then(Value::as_u64).ok_or_else(malformed);
+        let start = number("start")?;
+
+        match event.get("event").and_then(Value::as_str) {
+            Some("granted") => records.push(LeaseRecord {
+                worker: event.get("worker").and_then(Value::as_str).ok_or_else(malformed)?.to_string(),
+                block: start..number("end")?,
+                granted_at: number("at")?,
+                released: None,
+            }),
+            Some("released") => {
+                let record = records.iter_mut().find(|record| record.block.start == start).ok_or_else(malformed)?;
+                record.released = Some(Release { used: number("used")?, at: number("at")? });
+            }
+            _ => return Err(malformed()),
+        }
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, thread};
+
+    use super::*;
+    use crate::lib::components::sphincs::{secret::SphincsSigner, state::FileIndexStore, tests::temp_dir};
+
+    #[test]
+    fn test_leases_are_aligned_subtrees() {
+        let dir = temp_dir("lease_subtrees");
+        let store = FileIndexStore::create(&dir.join("key.idx"), 0).unwrap();
+        let mut manager = LeaseManager::new(store, &dir.join("key.leases")).unwrap();
+
+        let mut single = manager.lease("setup", 1, 0).unwrap();
+        assert_eq!(single.block(), 0..1);
+        assert_eq!(single.reserve(0).unwrap(), 0);
+        assert!(matches!(single.reserve(0), Err(StateError::Exhausted(1))));
+
+        // The leaves under one tree of the second layer of a height 3 hypertree
+        let size = subtree_leaves(3, 1);
+        let mut lease = manager.lease("worker-a", size, 0).unwrap();
+        assert_eq!(lease.block(), 8..16);
+        assert_eq!((lease.reserve(0).unwrap(), lease.reserve(0).unwrap()), (8, 9));
+        assert_eq!((lease.used(), lease.remaining()), (2, 6));
+        assert!(lease.commit(9).is_ok());
+        assert!(matches!(lease.commit(10), Err(StateError::NotReserved(10))));
+
+        // Indices 1 to 7 went to alignment and are never handed out
+        assert_eq!(manager.into_store().current().next_sequence, 16);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_workers_sign_disjoint_indices() {
+        let dir = temp_dir("lease_workers");
+        let store_path = dir.join("key.idx");
+        let ledger_path = dir.join("key.leases");
+        drop(FileIndexStore::create(&store_path, 0).unwrap());
+
+        // Four workers, each taking three leases from the shared store in turn
+        let workers: Vec<_> = (0..4).map(|worker| {
+            let (store_path, ledger_path) = (store_path.clone(), ledger_path.clone());
+            thread::spawn(move || {
+                let signer = SphincsSigner::<4, 4, 2, 3>::new([1u8; 32], [2u8; 32]).into_stateful();
+                let mut used = Vec::new();
+                for round in 0..3 {
+                    let mut manager = LeaseManager::new(FileIndexStore::open(&store_path).unwrap(), &ledger_path).unwrap();
+                    let mut lease = manager.lease(&format!("worker-{worker}"), 4, 0).unwrap();
+                    drop(manager);
+
+                    for _ in 0..round + 1 {
+                        let signature = signer.sign_stateful(b"From a worker", &mut lease).unwrap();
+                        assert!(signature.validate(b"From a worker", &signer.public_key()).is_ok());
+                        used.push(signature.fors.context.address.position);
+                    }
+                    // The last lease of every worker is abandoned without a release
+                    if round < 2 {
+                        let mut manager = LeaseManager::new(FileIndexStore::open(&store_path).unwrap(), &ledger_path).unwrap();
+                        manager.release(lease, 0).unwrap();
+                    }
+                }
+                used
+            })
+        }).collect();
+
+        let mut signed = HashSet::new();
+        for worker in workers {
+            for index in worker.join().unwrap() {
+                assert!(signed.insert(index), "index {index} signed twice");
+            }
+        }
+        assert_eq!(signed.len(), 4 * (1 + 2 + 3));
+
+        // The ledger accounts for every lease, and no two overlap
+        let records = read_ledger(&ledger_path).unwrap();
+        assert_eq!(records.len(), 12);
+        let mut covered = HashSet::new();
+        for record in &records {
+            assert!(record.block.clone().all(|index| covered.insert(index)));
+        }
+        assert_eq!(records.iter().filter(|record| record.released.is_none()).count(), 4);
+        assert!(records.iter().filter_map(|record| record.released.as_ref()).all(|release| release.used <= 2));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_ledger() {
+        let dir = temp_dir("lease_ledger");
+        let path = dir.join("key.leases");
+        fs::write(&path, concat!(
+            r#"{"event":"granted","worker":"a","start":0,"end":8,"at":5}"#, "\n",
+            r#"{"event":"released","worker":"a","start":0,"used":3,"at":9}"#, "\n",
+            r#"{"event":"granted","worker":"b","start":8,"end":16,"at":7}"#, "\n",
+            r#"{"event":"granted","wor"#
+        )).unwrap();
+        assert_eq!(read_ledger(&path).unwrap(), [
+            LeaseRecord { worker: "a".to_string(), block: 0..8, granted_at: 5, released: Some(Release { used: 3, at: 9 }) },
+            LeaseRecord { worker: "b".to_string(), block: 8..16, granted_at: 7, released: None },
+        ]);
+
+        fs::write(&path, "{\"event\":\"granted\"}\n").unwrap();
+        assert!(matches!(read_ledger(&path), Err(StateError::Ledger(1))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,5 +1,11 @@
 pub mod secret;
 pub mod signature;
 pub mod public;
+pub mod compact;
+pub mod signature_ref;
+pub mod state;
+#[cfg(feature = "leases")]
+pub mod lease;
+pub mod usage;
 #[cfg(test)]
 pub mod tests;
\ No newline at end of file
@@ -1,20 +1,81 @@
-use crate::lib::{helpers::random_generator::HashData};
-
-#[allow(non_snake_case)]
-pub struct KeyParams {
-    pub K: usize,
-    pub A: usize,
-    pub LAYERS: usize,
-    pub TREE_HEIGHT: usize
+use std::marker::PhantomData;
+
+use crate::lib::{components::{container::hash_suite_id, parameters::{ParameterSet, Parameters}}, helpers::{encoding::{DecodeError, Reader}, hasher::{HashSuite, Sha2Suite}, random_generator::HashData}};
+
+// Leads every encoded key, bumped whenever the layout after the header changes
+pub const KEY_VERSION: u8 = 1;
+
+// The shape of a key that is not a named set: K, A, LAYERS, TREE_HEIGHT, N,
+// log2(W) and the hash suite id
+const SHAPE_SIZE: usize = 7;
+
+fn key_shape(params: Parameters) -> [u8; SHAPE_SIZE] {
+    let [k, a, layers, tree_height, n, log_w] = [params.k, params.a, params.d, params.tree_height(), params.n, params.w.trailing_zeros() as usize].map(|value| value as u8);
+    [k, a, layers, tree_height, n, log_w, hash_suite_id(params)]
+}
+
+// The version and the parameter set id. Shapes that are not a named set have id
+// 0, followed by the shape itself.
+pub(super) fn key_header(params: Parameters) -> Vec<u8> {
+    match params.parameter_set() {
+        Some(set) => vec![KEY_VERSION, set.id()],
+        None => [&[KEY_VERSION, 0][..], &key_shape(params)].concat(),
+    }
 }
-#[derive(Debug)]
-pub struct SphincsPublic<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize> {
-    pub key: HashData,
-    pub public_seed: HashData,
+
+pub(super) fn check_key_header(reader: &mut Reader, params: Parameters) -> Result<(), DecodeError> {
+    let [version, set] = reader.array();
+    let expected = params.parameter_set().map_or(0, ParameterSet::id);
+    if version != KEY_VERSION {
+        return Err(DecodeError::UnsupportedVersion(version));
+    }
+    if set != expected {
+        return Err(DecodeError::WrongParameterSet(expected, set));
+    }
+    if expected == 0 {
+        let shape = reader.array();
+        if shape != key_shape(params) {
+            return Err(DecodeError::WrongShape(key_shape(params), shape));
+        }
+    }
+    Ok(())
 }
 
-impl<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize> SphincsPublic<K, A, LAYERS, TREE_HEIGHT> {
-    pub fn get_params(&self) -> KeyParams {
-        KeyParams { K, A, LAYERS, TREE_HEIGHT }
+#[derive(Debug, Clone, PartialEq)]
+pub struct SphincsPublic<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize = 32, const W: usize = 256, H: HashSuite = Sha2Suite> {
+    pub key: HashData<N>,
+    pub public_seed: HashData<N>,
+    pub(crate) suite: PhantomData<H>,
+}
+
+impl<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite> SphincsPublic<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+    // Size of the bare key, PK.seed || PK.root
+    pub const fn size() -> usize {
+        2 * N
+    }
+
+    pub fn encoded_size() -> usize {
+        key_header(Parameters::sphincs::<K, A, LAYERS, TREE_HEIGHT, N, W, H>()).len() + Self::size()
+    }
+
+    // version || parameter set id (|| shape) || PK.seed || PK.root
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [&key_header(self.get_params())[..], &self.public_seed, &self.key].concat()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes, Self::encoded_size())?;
+        check_key_header(&mut reader, Parameters::sphincs::<K, A, LAYERS, TREE_HEIGHT, N, W, H>())?;
+        let public_seed = reader.array();
+        let key = reader.array();
+        Ok(Self { key, public_seed, suite: PhantomData })
+    }
+
+    pub fn get_params(&self) -> Parameters {
+        Parameters::sphincs::<K, A, LAYERS, TREE_HEIGHT, N, W, H>()
+    }
+
+    pub fn parameter_set(&self) -> Option<ParameterSet> {
+        self.get_params().parameter_set()
     }
 }
@@ -1,11 +1,14 @@
+use std::marker::PhantomData;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::lib::components::fors::public::ForsSignature;
+use crate::lib::components::fors::{indices::IndicesError, public::ForsSignature};
+use crate::lib::components::parameters::{ParameterSet, Parameters};
 use crate::lib::components::{fors::secret::Fors, hypertree::secret::HyperTreeSigner};
-use crate::lib::helpers::hasher::{HashContext, hash_array, hash_message};
-use crate::lib::helpers::random_generator::Address;
+use crate::lib::helpers::encoding::{DecodeError, Reader};
+use crate::lib::helpers::hasher::{HashSuite, Sha2Suite};
+use crate::lib::helpers::random_generator::{Address, InnerKeyRole, get_key};
 use crate::lib::{helpers::random_generator::HashData};
-use crate::lib::components::sphincs::{signature::SphincsSignature,public::{KeyParams, SphincsPublic}};
+use crate::lib::components::sphincs::{signature::{SphincsSignature, digest_len, index_digest_into, index_mask, signing_context, split_digest, timestamped_message},public::{SphincsPublic, check_key_header, key_header}};
 
 pub fn get_ms_timestamp_milliseconds() -> u128{
     let start = SystemTime::now();
@@ -15,69 +18,124 @@ pub fn get_ms_timestamp_milliseconds() -> u128{
     since_the_epoch.as_millis()
 }
 
-fn hash_to_u128(hash: HashData) -> u128 {
-    let mut result = 0u128;
-    for i in 0..2 {
-        let start = i * 16;
-        let mut bytes = [0u8; 16];
-        bytes.copy_from_slice(&hash[start..start+16]);
-        result ^= u128::from_be_bytes(bytes);
-    }
-    result
+// How the randomizer R of a signature is made
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SigningMode {
+    // R from SK.prf, fresh randomness, the current time and the message
+    #[default]
+    Randomized,
+    // R from SK.prf and the message only, with a timestamp of 0. Signing the same
+    // message twice gives byte identical signatures.
+    Deterministic,
 }
 
-#[derive(Debug, Clone)]
-pub struct SphincsSigner<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize> {
-    seed: HashData,
-    public_seed: HashData
+#[derive(Debug, Clone, PartialEq)]
+pub struct SphincsSigner<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize = 32, const W: usize = 256, H: HashSuite = Sha2Suite> {
+    seed: HashData<N>,
+    prf: HashData<N>,
+    public_seed: HashData<N>,
+    root: HashData<N>,
+    mode: SigningMode,
+    suite: PhantomData<H>,
 }
-impl<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize> SphincsSigner<K, A, LAYERS, TREE_HEIGHT> {
-    pub fn new(seed: HashData, public_seed: HashData) -> Self {
-        Self{seed, public_seed}
+impl<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite> SphincsSigner<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+    // SK.prf is derived from the secret seed
+    pub fn new(seed: HashData<N>, public_seed: HashData<N>) -> Self {
+        let prf = get_key::<H, N>(seed, &Address::default(), &InnerKeyRole::MessagePrf, 0);
+        Self::with_prf(seed, prf, public_seed)
     }
 
-    pub fn public_key(&self) -> SphincsPublic<K, A, LAYERS, TREE_HEIGHT> {
-        let hypertree = HyperTreeSigner::<LAYERS, TREE_HEIGHT>::new(self.seed, self.public_seed);
-        SphincsPublic::<K, A, LAYERS, TREE_HEIGHT>{
-            key: hypertree.generate_master_public_key(),
-            public_seed: self.public_seed
-        }
+    pub fn with_prf(seed: HashData<N>, prf: HashData<N>, public_seed: HashData<N>) -> Self {
+        // H_msg is keyed by the root, so it is kept next to the seeds
+        let root = HyperTreeSigner::<LAYERS, TREE_HEIGHT, N, W, H>::new(seed, public_seed).generate_master_public_key();
+        Self{seed, prf, public_seed, root, mode: SigningMode::default(), suite: PhantomData}
+    }
+
+    pub fn with_mode(self, mode: SigningMode) -> Self {
+        Self{mode, ..self}
     }
-    pub fn sign_position(&self, data_hash: HashData, position: u128) -> (ForsSignature<K, A>, HashData){
-        let context = HashContext{public_seed: self.public_seed, address: Address{level: 0, position}};
 
-        let fors = Fors::<K, A>::new(self.seed, context);
-        
-        (fors.sign(&data_hash), fors.generate_public_key())
+    pub fn mode(&self) -> SigningMode {
+        self.mode
     }
 
-    pub(super) fn sign_with_set_ts(&self, message: &[u8], timestamp: u128, force_index: Option<u64>) -> SphincsSignature<K, A, LAYERS, TREE_HEIGHT> {
-        let hashed_ts = hash_message(&timestamp.to_be_bytes());
-        let message_hash = hash_message(message);
-        let hash_and_ts = hash_array(&[message_hash, hashed_ts]);
-        let max_index = (2_u128).pow(LAYERS as u32 * TREE_HEIGHT as u32);
+    pub fn public_key(&self) -> SphincsPublic<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+        SphincsPublic::<K, A, LAYERS, TREE_HEIGHT, N, W, H>{
+            key: self.root,
+            public_seed: self.public_seed,
+            suite: PhantomData
+        }
+    }
+    pub fn sign_position(&self, digest: &[u8], position: u128) -> Result<(ForsSignature<K, A, N, H>, HashData<N>), IndicesError> {
+        let fors = Fors::<K, A, N, H>::new(self.seed, signing_context(self.public_seed, position, 0, TREE_HEIGHT));
 
-        
-        let index = match force_index {
-            None => hash_to_u128(hash_and_ts) % max_index,
-            Some(idx) => (idx as u128) % max_index
+        Ok((fors.sign(digest)?, fors.generate_public_key()))
+    }
+
+    pub(super) fn sign_with_set_ts(&self, message: &[u8], timestamp: u128, force_index: Option<u64>) -> SphincsSignature<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+        let message_hash = H::hash_message(message);
+        let signed_message = timestamped_message(timestamp, message);
+        // Hedged: fresh randomness, with SK.prf as a fallback if the RNG is weak
+        let opt_rand = match self.mode {
+            SigningMode::Randomized => rand::random(),
+            SigningMode::Deterministic => self.public_seed,
         };
-        
-        let (fors, fors_public_key) = self.sign_position(hash_and_ts, index);
-        let hp_signer = HyperTreeSigner::<LAYERS, TREE_HEIGHT>::new(self.seed, self.public_seed);
-        let hyper_tree = hp_signer.sign(fors_public_key, index as u128);
-        SphincsSignature::<K, A, LAYERS, TREE_HEIGHT>{data_hash: message_hash, fors, hyper_tree, timestamp}
+        let randomizer = H::message_prf(&self.prf, &opt_rand, &signed_message);
+        let mut digest = vec![0u8; digest_len(K, A, LAYERS, TREE_HEIGHT)];
+        let force_index = force_index.map(|idx| (idx as u128) & index_mask(LAYERS * TREE_HEIGHT));
+        let index = index_digest_into::<K, A, LAYERS, TREE_HEIGHT, N, H>(&randomizer, &self.public_seed, &self.root, timestamp, message, force_index, &mut digest);
+        let (md, _) = split_digest::<K, A, LAYERS, TREE_HEIGHT>(&digest);
+
+        let (fors, fors_public_key) = self.sign_position(md, index).expect("H_msg holds K*A bits");
+        let hp_signer = HyperTreeSigner::<LAYERS, TREE_HEIGHT, N, W, H>::new(self.seed, self.public_seed);
+        let hyper_tree = hp_signer.sign(fors_public_key, index);
+        SphincsSignature::<K, A, LAYERS, TREE_HEIGHT, N, W, H>{data_hash: message_hash, randomizer, fors, hyper_tree, timestamp}
     }
 
-    pub fn sign(&self, message: &[u8]) -> SphincsSignature<K, A, LAYERS, TREE_HEIGHT> {
-        let timestamp = get_ms_timestamp_milliseconds();
-        // self.sign_with_set_ts(message, timestamp, None)
+    pub fn sign(&self, message: &[u8]) -> SphincsSignature<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+        let timestamp = match self.mode {
+            SigningMode::Randomized => get_ms_timestamp_milliseconds(),
+            SigningMode::Deterministic => 0,
+        };
         self.sign_with_set_ts(message, timestamp, None)
     }
 }
 
-impl<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize> SphincsSigner<K, A, LAYERS, TREE_HEIGHT> {
-    pub fn get_params(self) -> KeyParams {
-        KeyParams { K, A, LAYERS, TREE_HEIGHT }
+impl<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite> SphincsSigner<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+    pub fn get_params(self) -> Parameters {
+        Parameters::sphincs::<K, A, LAYERS, TREE_HEIGHT, N, W, H>()
+    }
+
+    pub fn parameter_set(&self) -> Option<ParameterSet> {
+        self.public_key().parameter_set()
+    }
+
+    // Whether the public key belongs to this signer
+    pub fn matches(&self, public_key: &SphincsPublic<K, A, LAYERS, TREE_HEIGHT, N, W, H>) -> bool {
+        self.public_seed == public_key.public_seed && self.root == public_key.key
+    }
+
+    pub fn encoded_size() -> usize {
+        key_header(Parameters::sphincs::<K, A, LAYERS, TREE_HEIGHT, N, W, H>()).len() + 4 * N
+    }
+
+    // version || parameter set id (|| shape) || SK.seed || SK.prf || PK.seed || PK.root. The
+    // signing mode is not part of the key.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let header = key_header(Parameters::sphincs::<K, A, LAYERS, TREE_HEIGHT, N, W, H>());
+        [&header[..], &self.seed, &self.prf, &self.public_seed, &self.root].concat()
+    }
+
+    // Rebuilds the root from the seeds and refuses keys whose stored root differs
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes, Self::encoded_size())?;
+        check_key_header(&mut reader, Parameters::sphincs::<K, A, LAYERS, TREE_HEIGHT, N, W, H>())?;
+        let (seed, prf, public_seed, root) = (reader.array(), reader.array(), reader.array(), reader.array());
+
+        let signer = Self::with_prf(seed, prf, public_seed);
+        match signer.root == root {
+            true => Ok(signer),
+            false => Err(DecodeError::KeyMismatch)
+        }
     }
 }
@@ -1,42 +1,130 @@
-use crate::lib::{components::{fors::{indices::message_to_indices, public::{ForsSignature}}, hypertree::public::{FailedValidation, HyperTreeSignature}, sphincs::public::SphincsPublic}, helpers::{hasher::{hash_array, hash_message}, random_generator::HashData}};
+use crate::lib::{components::{fors::{indices::message_to_indices, public::{ForsSignature}}, hypertree::public::{FailedValidation, HyperTreeSignature}, sphincs::public::SphincsPublic}, helpers::{encoding::{DecodeError, Reader}, hasher::{HashContext, HashSuite, Sha2Suite}, random_generator::{Address, HashData}}};
+
+// Bytes of H_msg needed for the FORS indices followed by the hypertree index
+pub(super) const fn digest_len(k: usize, a: usize, layers: usize, tree_height: usize) -> usize {
+    (k * a).div_ceil(8) + (layers * tree_height).div_ceil(8)
+}
+
+// Splits H_msg output into the FORS digest and the hypertree index. The index
+// is masked to LAYERS * TREE_HEIGHT bits, so every index is equally likely.
+pub(super) fn split_digest<const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize>(digest: &[u8]) -> (&[u8], u128) {
+    const { assert!(LAYERS * TREE_HEIGHT <= 128, "The hypertree index must fit in 128 bits") };
+    let md_len = (K * A).div_ceil(8);
+    let index = digest[md_len..].iter().fold(0u128, |acc, byte| (acc << 8) | *byte as u128);
+    (&digest[..md_len], index & index_mask(LAYERS * TREE_HEIGHT))
+}
+
+pub(super) const fn index_mask(bits: usize) -> u128 {
+    match bits {
+        128 => u128::MAX,
+        _ => (1u128 << bits) - 1
+    }
+}
+
+// The timestamp is signed together with the message
+pub(super) fn timestamped_message(timestamp: u128, message: &[u8]) -> Vec<u8> {
+    [timestamp.to_be_bytes().as_slice(), message].concat()
+}
+
+// Fills `out` with H_msg and returns the hypertree index of the signature. H_msg
+// is taken over a 0 byte, the timestamp and the message, and picks the index
+// itself. When the signer picks `index` instead and H_msg picked another one, it
+// is taken again over a 1 byte, the index, the timestamp and the message. Either
+// way the FORS digest is bound to the leaf it is signed at, and the verifier
+// passes the index of the signature to get the same digest.
+pub(super) fn index_digest_into<const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, H: HashSuite>(randomizer: &HashData<N>, public_seed: &HashData<N>, root: &HashData<N>, timestamp: u128, message: &[u8], index: Option<u128>, out: &mut [u8]) -> u128 {
+    let timestamp = timestamp.to_be_bytes();
+    H::digest_message_into(randomizer, public_seed, root, &[&[0], &timestamp, message], out);
+    let (_, digest_index) = split_digest::<K, A, LAYERS, TREE_HEIGHT>(out);
+    match index {
+        Some(index) if index != digest_index => {
+            H::digest_message_into(randomizer, public_seed, root, &[&[1], &index.to_be_bytes(), &timestamp, message], out);
+            index
+        }
+        _ => digest_index,
+    }
+}
+
+// The context of the FORS instance (level 0) or the hypertree layer that signs at `index`
+pub(super) fn signing_context<const N: usize>(public_seed: HashData<N>, index: u128, level: usize, tree_height: usize) -> HashContext<N> {
+    HashContext { public_seed, address: Address { level: level as u16, position: index >> (level * tree_height), ..Address::default() } }
+}
 
 #[derive(Debug)]
-pub struct SignatureValidResult {
-    pub data_hash: HashData,
-    pub public_key: HashData,
+pub struct SignatureValidResult<const N: usize = 32> {
+    pub data_hash: HashData<N>,
+    pub public_key: HashData<N>,
     pub timestamp: u128,
 }
 
 
 
 #[derive(Debug)]
-pub enum SigntureError {
-    WrongMessage(HashData),
-    HTProofError(usize, HashData, HashData),
-    HTPublicKey(HashData, HashData),
-    ForsFailure(HashData, HashData)
+pub enum SigntureError<const N: usize = 32> {
+    WrongMessage(HashData<N>),
+    HTProofError(usize, HashData<N>, HashData<N>),
+    HTPublicKey(HashData<N>, HashData<N>),
+    ForsFailure(HashData<N>, HashData<N>),
+    ForsContext, // the FORS context is not the one of a leaf of this key
+    HTContext(usize), // the context of the layer does not match the FORS index
 }
 
 #[derive(Debug, PartialEq, Clone)]
-pub struct SphincsSignature<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize> {
-    pub data_hash: HashData,
-    pub fors: ForsSignature<K, A>,
-    pub hyper_tree: HyperTreeSignature<LAYERS, TREE_HEIGHT>,
+pub struct SphincsSignature<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize = 32, const W: usize = 256, H: HashSuite = Sha2Suite> {
+    pub data_hash: HashData<N>,
+    pub randomizer: HashData<N>,
+    pub fors: ForsSignature<K, A, N, H>,
+    pub hyper_tree: HyperTreeSignature<LAYERS, TREE_HEIGHT, N, W, H>,
     pub timestamp: u128
 }
 
-impl<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize> SphincsSignature<K, A, LAYERS, TREE_HEIGHT> {
-    pub fn validate(&self, message: &[u8], public_key: &SphincsPublic<K, A, LAYERS, TREE_HEIGHT>) -> Result<SignatureValidResult, SigntureError> {
+impl<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite> SphincsSignature<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+    // The data hash, the randomizer, FORS, the hypertree and a 16 byte timestamp
+    pub const fn size() -> usize {
+        2 * N + ForsSignature::<K, A, N, H>::size() + HyperTreeSignature::<LAYERS, TREE_HEIGHT, N, W, H>::size() + 16
+    }
+
+    // Every part in field order, the timestamp big endian
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::size());
+        out.extend_from_slice(&self.data_hash);
+        out.extend_from_slice(&self.randomizer);
+        out.extend(self.fors.to_bytes());
+        out.extend(self.hyper_tree.to_bytes());
+        out.extend_from_slice(&self.timestamp.to_be_bytes());
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes, Self::size())?;
+        let data_hash = reader.array();
+        let randomizer = reader.array();
+        let fors = ForsSignature::from_bytes(reader.take(ForsSignature::<K, A, N, H>::size()))?;
+        let hyper_tree = HyperTreeSignature::from_bytes(reader.take(HyperTreeSignature::<LAYERS, TREE_HEIGHT, N, W, H>::size()))?;
+        let timestamp = u128::from_be_bytes(reader.array());
+        Ok(Self { data_hash, randomizer, fors, hyper_tree, timestamp })
+    }
+
+    pub fn validate(&self, message: &[u8], public_key: &SphincsPublic<K, A, LAYERS, TREE_HEIGHT, N, W, H>) -> Result<SignatureValidResult<N>, SigntureError<N>> {
         
-        let message_hash = hash_message(message);
+        let message_hash = H::hash_message(message);
         
         if message_hash != self.data_hash {
             return Err(SigntureError::WrongMessage(self.data_hash));
         }
 
-        let hashed_ts = hash_message(&self.timestamp.to_be_bytes());
-        let hash_and_ts = hash_array(&[message_hash, hashed_ts]);
-        let indices = message_to_indices::<K, A>(&hash_and_ts);
+        let index = self.fors.context.address.position;
+        if index > index_mask(LAYERS * TREE_HEIGHT) || self.fors.context != signing_context(public_key.public_seed, index, 0, TREE_HEIGHT) {
+            return Err(SigntureError::ForsContext);
+        }
+        if let Some(layer) = (0..LAYERS).find(|&layer| self.hyper_tree.proofs[layer].signature.context != signing_context(public_key.public_seed, index, layer, TREE_HEIGHT)) {
+            return Err(SigntureError::HTContext(layer));
+        }
+
+        let mut digest = vec![0u8; digest_len(K, A, LAYERS, TREE_HEIGHT)];
+        index_digest_into::<K, A, LAYERS, TREE_HEIGHT, N, H>(&self.randomizer, &public_key.public_seed, &public_key.key, self.timestamp, message, Some(index), &mut digest);
+        let (md, _) = split_digest::<K, A, LAYERS, TREE_HEIGHT>(&digest);
+        let indices = message_to_indices::<K, A>(md).expect("H_msg holds K*A bits");
         
         let fors_key = self.fors.clone().get_expected_public_from_hash(indices);
         if  fors_key != self.fors.public_key {
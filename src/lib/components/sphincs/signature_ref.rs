@@ -0,0 +1,137 @@
+use std::marker::PhantomData;
+
+use crate::lib::{
+    components::{
+        fors::{indices::message_to_indices, public::{ForsSignature, ForsSignatureElement, public_key_from_elements}},
+        merkle_tree::{proof::MerkleProof, secret::{climb, tree_context}},
+        sphincs::{public::SphincsPublic, signature::{SignatureValidResult, SigntureError, SphincsSignature, digest_len, index_digest_into, index_mask, signing_context, split_digest}},
+        wots_plus::signature::{WotsPlusSignature, checksum_chains, message_chains, public_key_from_chains}
+    },
+    helpers::{encoding::DecodeError, hasher::{HashContext, HashSuite, Sha2Suite}, random_generator::HashData}
+};
+
+// H_msg is expanded into a buffer on the stack
+const MAX_DIGEST_LEN: usize = 128;
+
+// A canonical signature encoding, verified where it lies. Every hash is read
+// straight out of the buffer, and nothing is copied to the heap on the way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SphincsSignatureRef<'a, const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize = 32, const W: usize = 256, H: HashSuite = Sha2Suite> {
+    bytes: &'a [u8],
+    suite: PhantomData<H>,
+}
+
+impl<'a, const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite> SphincsSignatureRef<'a, K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+    const FORS: usize = 2 * N;
+    const FORS_CONTEXT: usize = Self::FORS + K * ForsSignatureElement::<A, N>::size();
+    const FORS_PUBLIC_KEY: usize = Self::FORS_CONTEXT + HashContext::<N>::size();
+    const HYPER_TREE: usize = Self::FORS + ForsSignature::<K, A, N, H>::size();
+    const TIMESTAMP: usize = SphincsSignature::<K, A, LAYERS, TREE_HEIGHT, N, W, H>::size() - 16;
+
+    // Checks the length and every address, so validation can not fail to decode
+    pub fn new(bytes: &'a [u8]) -> Result<Self, DecodeError> {
+        let expected = SphincsSignature::<K, A, LAYERS, TREE_HEIGHT, N, W, H>::size();
+        if bytes.len() != expected {
+            return Err(DecodeError::WrongLength(expected, bytes.len()));
+        }
+        let signature = Self { bytes, suite: PhantomData };
+        signature.context_at(Self::FORS_CONTEXT)?;
+        (0..LAYERS).try_for_each(|layer| signature.context_at(Self::proof(layer) + N).map(|_| ()))?;
+        Ok(signature)
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn data_hash(&self) -> HashData<N> {
+        self.hash_at(0)
+    }
+
+    pub fn randomizer(&self) -> HashData<N> {
+        self.hash_at(N)
+    }
+
+    pub fn timestamp(&self) -> u128 {
+        u128::from_be_bytes(self.bytes[Self::TIMESTAMP..].try_into().expect("The length is checked in new"))
+    }
+
+    pub fn to_signature(&self) -> SphincsSignature<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+        SphincsSignature::from_bytes(self.bytes).expect("The encoding is checked in new")
+    }
+
+    // Start of the Merkle proof of `layer`: its root, the WOTS+ signature and the authentication path
+    const fn proof(layer: usize) -> usize {
+        Self::HYPER_TREE + layer * MerkleProof::<TREE_HEIGHT, N, W, H>::size()
+    }
+
+    fn hash_at(&self, offset: usize) -> HashData<N> {
+        self.bytes[offset..offset + N].try_into().expect("The length is checked in new")
+    }
+
+    fn hashes_at(self, offset: usize, count: usize) -> impl Iterator<Item = HashData<N>> + 'a {
+        self.bytes[offset..offset + count * N].chunks_exact(N).map(|hash| hash.try_into().expect("Chunks are N bytes"))
+    }
+
+    fn context_at(&self, offset: usize) -> Result<HashContext<N>, DecodeError> {
+        HashContext::from_bytes(&self.bytes[offset..offset + HashContext::<N>::size()])
+    }
+
+    // The same checks, in the same order and with the same errors, as SphincsSignature::validate
+    pub fn validate(&self, message: &[u8], public_key: &SphincsPublic<K, A, LAYERS, TREE_HEIGHT, N, W, H>) -> Result<SignatureValidResult<N>, SigntureError<N>> {
+        const { assert!(digest_len(K, A, LAYERS, TREE_HEIGHT) <= MAX_DIGEST_LEN, "H_msg does not fit the stack buffer") };
+
+        let message_hash = H::hash_message(message);
+        if message_hash != self.data_hash() {
+            return Err(SigntureError::WrongMessage(self.data_hash()));
+        }
+
+        let fors_context = self.context_at(Self::FORS_CONTEXT).expect("Contexts are checked in new");
+        let index = fors_context.address.position;
+        if index > index_mask(LAYERS * TREE_HEIGHT) || fors_context != signing_context(public_key.public_seed, index, 0, TREE_HEIGHT) {
+            return Err(SigntureError::ForsContext);
+        }
+        let layer_context = |layer| self.context_at(Self::proof(layer) + N).expect("Contexts are checked in new");
+        if let Some(layer) = (0..LAYERS).find(|&layer| layer_context(layer) != signing_context(public_key.public_seed, index, layer, TREE_HEIGHT)) {
+            return Err(SigntureError::HTContext(layer));
+        }
+
+        let timestamp = self.timestamp();
+        let mut digest = [0u8; MAX_DIGEST_LEN];
+        let digest = &mut digest[..digest_len(K, A, LAYERS, TREE_HEIGHT)];
+        index_digest_into::<K, A, LAYERS, TREE_HEIGHT, N, H>(&self.randomizer(), &public_key.public_seed, &public_key.key, timestamp, message, Some(index), digest);
+        let (md, _) = split_digest::<K, A, LAYERS, TREE_HEIGHT>(digest);
+
+        let elements = (0..K).map(|i| {
+            let element = Self::FORS + i * ForsSignatureElement::<A, N>::size();
+            (self.hash_at(element), self.hashes_at(element + N, A))
+        });
+        let fors_key = public_key_from_elements::<H, K, N>(elements, message_to_indices::<K, A>(md).expect("H_msg holds K*A bits"), &fors_context);
+        let stored_fors_key = self.hash_at(Self::FORS_PUBLIC_KEY);
+        if fors_key != stored_fors_key {
+            return Err(SigntureError::ForsFailure(fors_key, stored_fors_key));
+        }
+
+        let mut testing_key = fors_key;
+        for layer in 0..LAYERS {
+            let wots = Self::proof(layer) + N;
+            let context = layer_context(layer);
+            let chains = self.hashes_at(wots + HashContext::<N>::size(), message_chains(N, W) + checksum_chains(N, W));
+            let leaf = public_key_from_chains::<N, W, H>(chains, &H::hash_message(&testing_key), &context);
+
+            let leaf_idx = context.address.position as usize % (1 << TREE_HEIGHT);
+            let auth_path = self.hashes_at(wots + WotsPlusSignature::<N, W, H>::size(), TREE_HEIGHT);
+            let root = climb::<H, N>(leaf, leaf_idx, auth_path, &tree_context(&context, TREE_HEIGHT));
+            let proof_key = self.hash_at(Self::proof(layer));
+            if root != proof_key {
+                return Err(SigntureError::HTProofError(layer, testing_key, proof_key));
+            }
+            testing_key = proof_key;
+        }
+
+        match testing_key == public_key.key {
+            true => Ok(SignatureValidResult { data_hash: message_hash, public_key: public_key.key, timestamp }),
+            false => Err(SigntureError::HTPublicKey(public_key.key, testing_key)),
+        }
+    }
+}
@@ -0,0 +1,494 @@
+// Stateful index selection, as described in the README. The state of a key is its
+// creation time and the next sequence number, and the next index is the larger of
+// the sequence number and 60 for every minute since creation. Should the state be
+// lost, the time floor still moves past every index used so far, as long as the
+// key signed no more than 60 times a minute on average.
+use std::{fs::{self, File, OpenOptions}, io::{self, Read, Write}, ops::Range, path::{Path, PathBuf}};
+
+use crate::lib::{
+    components::sphincs::{public::SphincsPublic, secret::{SigningMode, SphincsSigner, get_ms_timestamp_milliseconds}, signature::{SphincsSignature, index_mask}},
+    helpers::{encoding::{DecodeError, Reader}, hasher::{HashSuite, Sha2Suite}}
+};
+
+pub const STATE_VERSION: u8 = 1;
+pub const INDICES_PER_MINUTE: u64 = 60;
+
+#[derive(Debug)]
+pub enum StateError {
+    Io(io::Error),
+    Decode(DecodeError),
+    Exhausted(u64), // the first index past the end of the hypertree or the index space
+    NotReserved(u64),
+    Ledger(usize), // the line of the lease ledger that is malformed
+}
+
+impl From<io::Error> for StateError {
+    fn from(err: io::Error) -> Self {
+        StateError::Io(err)
+    }
+}
+
+impl From<DecodeError> for StateError {
+    fn from(err: DecodeError) -> Self {
+        StateError::Decode(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexState {
+    pub created_at: u128, // milliseconds since the epoch
+    pub next_sequence: u64,
+}
+
+impl IndexState {
+    pub fn new(created_at: u128) -> Self {
+        Self { created_at, next_sequence: 0 }
+    }
+
+    // A clock behind the creation time gives no floor, the sequence still holds
+    pub fn next_index(&self, now: u128) -> u64 {
+        let minutes = now.saturating_sub(self.created_at) / 60_000;
+        let floor = u64::try_from(minutes).unwrap_or(u64::MAX).saturating_mul(INDICES_PER_MINUTE);
+        self.next_sequence.max(floor)
+    }
+
+    pub const fn size() -> usize {
+        1 + 16 + 8
+    }
+
+    // version || created_at || next_sequence, big endian
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [&[STATE_VERSION][..], &self.created_at.to_be_bytes(), &self.next_sequence.to_be_bytes()].concat()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes, Self::size())?;
+        match reader.array::<1>()[0] {
+            STATE_VERSION => {}
+            version => return Err(DecodeError::UnsupportedVersion(version)),
+        }
+        Ok(Self { created_at: u128::from_be_bytes(reader.array()), next_sequence: u64::from_be_bytes(reader.array()) })
+    }
+}
+
+// Durable storage of an IndexState. An index returned by `reserve` must never be
+// returned again, whether or not it was committed, and even after a crash.
+pub trait IndexStore {
+    // Takes the next `size` indices, starting at a multiple of `size`. Indices
+    // skipped to align the block are never handed out either.
+    fn reserve_block(&mut self, now: u128, size: u64) -> Result<Range<u64>, StateError>;
+
+    // Takes the next index for a signature made at `now`
+    fn reserve(&mut self, now: u128) -> Result<u64, StateError> {
+        Ok(self.reserve_block(now, 1)?.start)
+    }
+
+    // Records that the block starting at `index` was used
+    fn commit(&mut self, index: u64) -> Result<(), StateError>;
+
+    // The state including every reservation so far
+    fn current(&self) -> IndexState;
+}
+
+// The first multiple of `size` at or after `index`, and the end of the block there
+pub(super) fn aligned_block(index: u64, size: u64) -> Result<Range<u64>, StateError> {
+    let exhausted = StateError::Exhausted(index);
+    let start = index.checked_next_multiple_of(size).ok_or(exhausted)?;
+    let end = start.checked_add(size).ok_or(StateError::Exhausted(start))?;
+    Ok(start..end)
+}
+
+// A WAL record: the last reserved index and a checksum that tells a torn append apart
+const WAL_RECORD_SIZE: usize = 16;
+
+fn wal_record(index: u64) -> [u8; WAL_RECORD_SIZE] {
+    let index = index.to_be_bytes();
+    let checksum: [u8; 8] = Sha2Suite::hash(&[b"rust_sphincs index wal", &index]);
+    [index, checksum].concat().try_into().expect("Two 8 byte halves")
+}
+
+// The reserved indices of the complete records. Replay stops at the first torn
+// or corrupt record, since its append never returned and its index was never used.
+fn replay_wal(bytes: &[u8]) -> impl Iterator<Item = u64> + '_ {
+    bytes.chunks_exact(WAL_RECORD_SIZE)
+        .map(|record| (u64::from_be_bytes(record[..8].try_into().expect("Records are 16 bytes")), record))
+        .take_while(|(index, record)| record[..] == wal_record(*index))
+        .map(|(index, _)| index)
+}
+
+fn with_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+// Writes a temporary file, syncs it and renames it over `path`, so `path` holds
+// either the old state or the new one
+fn replace_file(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let temp = with_extension(path, ".tmp");
+    let mut file = File::create(&temp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(&temp, path)?;
+    sync_parent(path)
+}
+
+// The rename itself is only durable once the directory is synced
+fn sync_parent(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+        File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+// An IndexStore in a state file and a write-ahead log next to it (`<path>.wal`).
+// `reserve` appends the index to the log and syncs it before returning it.
+// `commit` replaces the state file atomically and empties the log once nothing
+// is left outstanding. Opening replays the log, so every index that may have
+// been used is skipped.
+#[derive(Debug)]
+pub struct FileIndexStore {
+    path: PathBuf,
+    // An exclusive lock on `<path>.lock`, so one process at a time has the store open
+    _lock: File,
+    wal: File,
+    state: IndexState,
+    pending: Vec<u64>,
+}
+
+impl FileIndexStore {
+    // Starts the state of a new key, an existing state file is never overwritten
+    pub fn create(path: &Path, created_at: u128) -> Result<Self, StateError> {
+        let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+        file.write_all(&IndexState::new(created_at).to_bytes())?;
+        file.sync_all()?;
+        sync_parent(path)?;
+        // A log left behind by a lost state file still counts
+        Self::open(path)
+    }
+
+    // Waits until no other process has the store open
+    pub fn open(path: &Path) -> Result<Self, StateError> {
+        let lock = OpenOptions::new().write(true).create(true).truncate(false).open(with_extension(path, ".lock"))?;
+        lock.lock()?;
+        let mut state = IndexState::from_bytes(&fs::read(path)?)?;
+        let wal_path = with_extension(path, ".wal");
+        let mut wal = OpenOptions::new().read(true).append(true).create(true).open(&wal_path)?;
+        let mut log = Vec::new();
+        wal.read_to_end(&mut log)?;
+
+        if let Some(last) = replay_wal(&log).max() {
+            state.next_sequence = state.next_sequence.max(last.checked_add(1).ok_or(StateError::Exhausted(last))?);
+        }
+        let mut store = Self { path: path.to_path_buf(), _lock: lock, wal, state, pending: Vec::new() };
+        store.checkpoint()?;
+        sync_parent(&wal_path)?;
+        Ok(store)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn checkpoint(&mut self) -> io::Result<()> {
+        replace_file(&self.path, &self.state.to_bytes())?;
+        if self.pending.is_empty() {
+            self.wal.set_len(0)?;
+            self.wal.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+impl IndexStore for FileIndexStore {
+    fn reserve_block(&mut self, now: u128, size: u64) -> Result<Range<u64>, StateError> {
+        let block = aligned_block(self.state.next_index(now), size)?;
+        self.wal.write_all(&wal_record(block.end - 1))?;
+        self.wal.sync_data()?;
+        self.state.next_sequence = block.end;
+        self.pending.push(block.start);
+        Ok(block)
+    }
+
+    fn commit(&mut self, index: u64) -> Result<(), StateError> {
+        let position = self.pending.iter().position(|pending| *pending == index).ok_or(StateError::NotReserved(index))?;
+        self.pending.swap_remove(position);
+        Ok(self.checkpoint()?)
+    }
+
+    fn current(&self) -> IndexState {
+        self.state
+    }
+}
+
+// How many indices stateful signing may use. The hypertree needs at least one leaf
+// per index, and the 32-bit space lets it be about half the usual height, which
+// makes signatures a quarter to a third smaller and signing faster.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexSpace {
+    Bits32,
+    Bits64,
+}
+
+impl IndexSpace {
+    pub const fn bits(self) -> usize {
+        match self {
+            IndexSpace::Bits32 => 32,
+            IndexSpace::Bits64 => 64,
+        }
+    }
+
+    pub const fn last_index(self) -> u64 {
+        index_mask(self.bits()) as u64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexSpaceError {
+    TooFewLeaves(usize, usize), // the bits of the space, and the height of the hypertree
+}
+
+// A SphincsSigner bound to an IndexStore. It has no stateless `sign`, so none of
+// the leaves it signs at can also be picked by a digest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatefulSigner<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize = 32, const W: usize = 256, H: HashSuite = Sha2Suite> {
+    signer: SphincsSigner<K, A, LAYERS, TREE_HEIGHT, N, W, H>,
+    index_space: Option<IndexSpace>, // None for every leaf of the hypertree
+}
+
+impl<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite> SphincsSigner<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+    // Takes the signer, so it can no longer sign statelessly
+    pub fn into_stateful(self) -> StatefulSigner<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+        StatefulSigner { signer: self, index_space: None }
+    }
+}
+
+impl<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite> StatefulSigner<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+    // Refuses a space with more indices than the hypertree has leaves
+    pub fn with_index_space(self, index_space: IndexSpace) -> Result<Self, IndexSpaceError> {
+        match LAYERS * TREE_HEIGHT >= index_space.bits() {
+            true => Ok(Self{index_space: Some(index_space), ..self}),
+            false => Err(IndexSpaceError::TooFewLeaves(index_space.bits(), LAYERS * TREE_HEIGHT)),
+        }
+    }
+
+    pub fn index_space(&self) -> Option<IndexSpace> {
+        self.index_space
+    }
+
+    // The last index stateful signing may use. A store never hands out u64::MAX,
+    // as the sequence number after it does not fit, so 2^64 - 2 is the last of a
+    // 64-bit space.
+    pub fn last_index(&self) -> u64 {
+        let hypertree = u64::try_from(index_mask(LAYERS * TREE_HEIGHT)).unwrap_or(u64::MAX);
+        self.index_space.map_or(hypertree, |space| space.last_index().min(hypertree)).min(u64::MAX - 1)
+    }
+
+    pub fn public_key(&self) -> SphincsPublic<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+        self.signer.public_key()
+    }
+
+    // Signs at the next index of the store, instead of the index the digest picks.
+    // The index is reserved before signing and committed after. An index past
+    // `last_index` is refused and stays reserved without a commit: the store never
+    // returns it again, and as every later index is past the end too, the key is
+    // used up either way.
+    pub fn sign_stateful(&self, message: &[u8], store: &mut impl IndexStore) -> Result<SphincsSignature<K, A, LAYERS, TREE_HEIGHT, N, W, H>, StateError> {
+        let now = get_ms_timestamp_milliseconds();
+        let index = store.reserve(now)?;
+        if index > self.last_index() {
+            return Err(StateError::Exhausted(index));
+        }
+        let timestamp = match self.signer.mode() {
+            SigningMode::Randomized => now,
+            SigningMode::Deterministic => 0,
+        };
+        let signature = self.signer.sign_with_set_ts(message, timestamp, Some(index));
+        store.commit(index)?;
+        Ok(signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::lib::components::sphincs::tests::temp_dir;
+
+    #[test]
+    fn test_next_index() {
+        let minute = 60_000;
+        let state = IndexState { created_at: 10 * minute, next_sequence: 5 };
+        assert_eq!(state.next_index(10 * minute), 5);
+        assert_eq!(state.next_index(10 * minute + 59_999), 5);
+        assert_eq!(state.next_index(20 * minute), 600);
+        // The clock is behind the creation time
+        assert_eq!(state.next_index(0), 5);
+
+        let busy = IndexState { next_sequence: 700, ..state };
+        assert_eq!(busy.next_index(20 * minute), 700);
+
+        // A lost state restarts at the time floor, past everything signed at up to 60 a minute
+        let restarted = IndexState::new(state.created_at);
+        assert_eq!(restarted.next_index(20 * minute), 600);
+        assert_eq!(IndexState::new(0).next_index(u128::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn test_state_to_from_bytes() {
+        let state = IndexState { created_at: 1_700_000_000_000, next_sequence: 42 };
+        let bytes = state.to_bytes();
+        assert_eq!(bytes.len(), IndexState::size());
+        assert_eq!(IndexState::from_bytes(&bytes), Ok(state));
+        assert_eq!(IndexState::from_bytes(&[&[2u8][..], &bytes[1..]].concat()), Err(DecodeError::UnsupportedVersion(2)));
+        assert_eq!(IndexState::from_bytes(&bytes[1..]), Err(DecodeError::WrongLength(25, 24)));
+    }
+
+    #[test]
+    fn test_file_store_persists() {
+        let dir = temp_dir("store_persists");
+        let path = dir.join("key.idx");
+        let mut store = FileIndexStore::create(&path, 0).unwrap();
+        for expected in 0..3 {
+            assert_eq!(store.reserve(0).unwrap(), expected);
+            store.commit(expected).unwrap();
+        }
+        assert!(fs::read(with_extension(&path, ".wal")).unwrap().is_empty());
+        assert!(matches!(store.commit(7), Err(StateError::NotReserved(7))));
+        drop(store);
+
+        let mut reopened = FileIndexStore::open(&path).unwrap();
+        assert_eq!(reopened.current(), IndexState { created_at: 0, next_sequence: 3 });
+        assert_eq!(reopened.reserve(0).unwrap(), 3);
+        assert!(matches!(FileIndexStore::create(&path, 0), Err(StateError::Io(err)) if err.kind() == io::ErrorKind::AlreadyExists));
+        drop(reopened);
+
+        fs::write(&path, [1u8; 3]).unwrap();
+        assert!(matches!(FileIndexStore::open(&path), Err(StateError::Decode(DecodeError::WrongLength(25, 3)))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_crash_between_reserve_and_commit() {
+        let dir = temp_dir("store_crash_reserve");
+        let path = dir.join("key.idx");
+        let mut store = FileIndexStore::create(&path, 0).unwrap();
+        let used = store.reserve(0).unwrap();
+        // The process dies before commit, the state file still says 0
+        drop(store);
+        assert_eq!(IndexState::from_bytes(&fs::read(&path).unwrap()).unwrap().next_sequence, 0);
+
+        let mut store = FileIndexStore::open(&path).unwrap();
+        assert_eq!(store.reserve(0).unwrap(), used + 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_crash_during_commit() {
+        let dir = temp_dir("store_crash_commit");
+        let path = dir.join("key.idx");
+
+        // Killed after writing the temporary file, before the rename
+        let mut store = FileIndexStore::create(&path, 0).unwrap();
+        let first = store.reserve(0).unwrap();
+        fs::write(with_extension(&path, ".tmp"), IndexState::new(0).to_bytes()).unwrap();
+        drop(store);
+        let mut store = FileIndexStore::open(&path).unwrap();
+        let second = store.reserve(0).unwrap();
+        assert!(second > first);
+
+        // Killed after the rename, before the log is emptied
+        replace_file(&path, &store.current().to_bytes()).unwrap();
+        drop(store);
+        let mut store = FileIndexStore::open(&path).unwrap();
+        assert!(store.reserve(0).unwrap() > second);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_torn_log_record() {
+        let dir = temp_dir("store_torn_log");
+        let path = dir.join("key.idx");
+        let mut store = FileIndexStore::create(&path, 0).unwrap();
+        let used = store.reserve(0).unwrap();
+        drop(store);
+
+        // A reservation of 1000 that never finished writing, and so was never returned
+        let mut wal = OpenOptions::new().append(true).open(with_extension(&path, ".wal")).unwrap();
+        wal.write_all(&wal_record(1000)[..11]).unwrap();
+        drop(wal);
+
+        let mut store = FileIndexStore::open(&path).unwrap();
+        assert_eq!(store.reserve(0).unwrap(), used + 1);
+        assert_eq!(replay_wal(&[&wal_record(4)[..], &[0u8; 16], &wal_record(9)].concat()).collect::<Vec<u64>>(), [4]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_no_index_returned_twice() {
+        let dir = temp_dir("store_random_crashes");
+        let path = dir.join("key.idx");
+        let mut store = FileIndexStore::create(&path, 0).unwrap();
+        let mut returned = HashSet::new();
+
+        // A fixed pseudo random schedule of commits and crashes at every step
+        let mut schedule = 0x2545_F491_4F6C_DD1Du64;
+        for _ in 0..200 {
+            schedule ^= schedule << 13;
+            schedule ^= schedule >> 7;
+            schedule ^= schedule << 17;
+
+            let index = store.reserve(0).unwrap();
+            assert!(returned.insert(index), "index {index} returned twice");
+            let crashed = match schedule % 4 {
+                0 => { store.commit(index).unwrap(); false }
+                1 => true,
+                2 => { fs::write(with_extension(&path, ".tmp"), b"half written").unwrap(); true }
+                _ => { replace_file(&path, &store.current().to_bytes()).unwrap(); true }
+            };
+            if crashed {
+                drop(store);
+                store = FileIndexStore::open(&path).unwrap();
+            }
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sign_stateful() {
+        let dir = temp_dir("sign_stateful");
+        let mut store = FileIndexStore::create(&dir.join("key.idx"), get_ms_timestamp_milliseconds()).unwrap();
+        let signer = SphincsSigner::<4, 4, 2, 3>::new([1u8; 32], [2u8; 32]).into_stateful();
+
+        for expected in 0..3 {
+            let signature = signer.sign_stateful(b"Next, please", &mut store).unwrap();
+            assert_eq!(signature.fors.context.address.position, expected);
+            assert!(signature.validate(b"Next, please", &signer.public_key()).is_ok());
+        }
+        assert!(store.pending.is_empty());
+
+        // 2 layers of height 3 have 64 leaves
+        store.state.next_sequence = 64;
+        assert!(matches!(signer.sign_stateful(b"One too many", &mut store), Err(StateError::Exhausted(64))));
+        assert_eq!(store.pending, [64]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sign_stateful_index_space() {
+        let dir = temp_dir("sign_index_space");
+        let mut store = FileIndexStore::create(&dir.join("key.idx"), get_ms_timestamp_milliseconds()).unwrap();
+        // 8 layers of height 4, exactly 2^32 leaves
+        let signer = SphincsSigner::<4, 4, 8, 4, 16>::new([1u8; 16], [2u8; 16]).into_stateful().with_index_space(IndexSpace::Bits32).unwrap();
+
+        store.state.next_sequence = u32::MAX as u64;
+        let signature = signer.sign_stateful(b"The last one", &mut store).unwrap();
+        assert_eq!(signature.fors.context.address.position, u32::MAX as u128);
+        assert!(signature.validate(b"The last one", &signer.public_key()).is_ok());
+        assert!(matches!(signer.sign_stateful(b"One too many", &mut store), Err(StateError::Exhausted(0x1_0000_0000))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
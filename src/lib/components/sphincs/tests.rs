@@ -1,9 +1,18 @@
 use crate::lib::{
-    components::sphincs::secret::{SphincsSigner},
-    helpers::hasher::hash_message,
+    components::{parameters::{ParameterSet, Scheme, Sphincs32Sha2_128fPublic, Sphincs32Sha2_128fSigner, Sphincs32Sha2_128sSigner, Sphincs32Sha2_128sSignature, Sphincs32Sha2_128fSignature, Sphincs32Sha2_192sSignature, Sphincs32Sha2_192fSignature, Sphincs32Sha2_256sSignature, Sphincs32Sha2_256fSignature, SphincsSha2_128fSigner}, sphincs::{compact::CompactSphincsSignature, public::{KEY_VERSION, SphincsPublic}, secret::{SigningMode, SphincsSigner}, signature::{SigntureError, SphincsSignature, digest_len, split_digest}, signature_ref::SphincsSignatureRef, state::{IndexSpace, IndexSpaceError}}},
+    helpers::encoding::DecodeError,
+    helpers::hasher::{HashSuite, Sha2Suite, Shake256Suite, hash_message},
     helpers::random_generator::HashData
 };
 
+// A fresh directory per test, removed again on success
+pub(crate) fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("rust_sphincs_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
 #[test]
 fn test_basic_signing() {
     // פרמטרים סטנדרטיים לספינקס (קטנים לבדיקה)
@@ -91,10 +100,10 @@ fn test_public_key_generation() {
     let public_key_params = &public_key.get_params();
     // בדיקות בסיסיות
     assert_eq!(public_key.public_seed, public_seed);
-    assert_eq!(public_key_params.LAYERS, LAYERS);
-    assert_eq!(public_key_params.TREE_HEIGHT, TREE_HEIGHT);
-    assert_eq!(public_key_params.K, K);
-    assert_eq!(public_key_params.A, A);
+    assert_eq!(public_key_params.d, LAYERS);
+    assert_eq!(public_key_params.tree_height(), TREE_HEIGHT);
+    assert_eq!(public_key_params.k, K);
+    assert_eq!(public_key_params.a, A);
     assert_ne!(public_key.key, [0u8; 32]); // המפתח לא אמור להיות אפס
 }
 
@@ -241,6 +250,74 @@ fn test_validate_tampered_signature() {
     assert!(tampered_signature.validate(message, &public_key).is_err(), "Signature validtion should fail when timestamp is tampered");
 }
 
+#[test]
+fn test_shake_suite_signing() {
+    const K: usize = 4;
+    const A: usize = 4;
+    const LAYERS: usize = 2;
+    const TREE_HEIGHT: usize = 3;
+
+    let seed = hash_message("my secret seed".as_bytes());
+    let public_seed = hash_message("my public seed".as_bytes());
+    let sha_signer = SphincsSigner::<K, A, LAYERS, TREE_HEIGHT>::new(seed, public_seed);
+    let shake_signer = SphincsSigner::<K, A, LAYERS, TREE_HEIGHT, 32, 256, Shake256Suite>::new(seed, public_seed);
+
+    let message = b"Verify me with SHAKE!";
+    let signature = shake_signer.sign(message);
+    let public_key = shake_signer.public_key();
+
+    assert_ne!(public_key.key, sha_signer.public_key().key);
+    assert_eq!(signature.data_hash, Shake256Suite::hash_message::<32>(message));
+    assert!(signature.validate(message, &public_key).is_ok());
+    assert!(signature.validate(b"Verify me with SHA!", &public_key).is_err());
+}
+
+#[test]
+fn test_short_hash_signing() {
+    const K: usize = 4;
+    const A: usize = 4;
+    const LAYERS: usize = 2;
+    const TREE_HEIGHT: usize = 3;
+
+    let signer16 = SphincsSigner::<K, A, LAYERS, TREE_HEIGHT, 16>::new([1u8; 16], [2u8; 16]);
+    let signer24 = SphincsSigner::<K, A, LAYERS, TREE_HEIGHT, 24>::new([1u8; 24], [2u8; 24]);
+
+    let message = b"Verify me with less bytes!";
+    let signature16 = signer16.sign(message);
+    let signature24 = signer24.sign(message);
+
+    assert_eq!(signer16.public_key().get_params().n, 16);
+    assert_eq!(signature16.fors.signatures[0].auth_path.len(), A);
+    assert_eq!(signature16.hyper_tree.proofs[0].signature.message_hashes.len(), 16);
+    assert_eq!(signature24.hyper_tree.proofs[0].signature.message_hashes.len(), 24);
+
+    assert!(signature16.validate(message, &signer16.public_key()).is_ok());
+    assert!(signature24.validate(message, &signer24.public_key()).is_ok());
+    assert!(signature16.validate(b"Don't verify me!", &signer16.public_key()).is_err());
+}
+
+#[test]
+fn test_winternitz_parameter_signing() {
+    const K: usize = 4;
+    const A: usize = 4;
+    const LAYERS: usize = 2;
+    const TREE_HEIGHT: usize = 3;
+
+    let seed = hash_message("my secret seed".as_bytes());
+    let public_seed = hash_message("my public seed".as_bytes());
+    let signer = SphincsSigner::<K, A, LAYERS, TREE_HEIGHT, 32, 16>::new(seed, public_seed);
+
+    let message = b"Verify me faster!";
+    let signature = signer.sign(message);
+    let public_key = signer.public_key();
+
+    assert_eq!(public_key.get_params().w, 16);
+    assert_eq!(signature.hyper_tree.proofs[0].signature.message_hashes.len(), 64);
+    assert_eq!(signature.hyper_tree.proofs[0].signature.checksum_hashes.len(), 3);
+    assert!(signature.validate(message, &public_key).is_ok());
+    assert!(signature.validate(b"Don't verify me!", &public_key).is_err());
+}
+
 #[test]
 fn test_sphincs_128f_parameters() {
     // Test with real SPHINCS+-128f parameters from FIPS 205
@@ -262,4 +339,372 @@ fn test_sphincs_128f_parameters() {
     // Verify that a different message fails validation
     let different_message = b"Different message";
     assert!(signature.validate(different_message, &public_key).is_err(), "SPHINCS+-128f signature should be invalid for different message");
-}
\ No newline at end of file
+}
+#[test]
+fn test_fors_digest_longer_than_hash() {
+    // 40 * 8 bits of FORS indices, more than one 32 byte hash
+    const K: usize = 40;
+    const A: usize = 8;
+    const LAYERS: usize = 2;
+    const TREE_HEIGHT: usize = 3;
+
+    let signer = SphincsSigner::<K, A, LAYERS, TREE_HEIGHT>::new([1u8; 32], [2u8; 32]);
+    let public_key = signer.public_key();
+
+    // The last FORS trees used to always get index 0
+    let last_keys: Vec<HashData> = (0..4u128).map(|timestamp| {
+        let signature = signer.sign_with_set_ts(b"Verify me!", timestamp, None);
+        assert!(signature.validate(b"Verify me!", &public_key).is_ok());
+        signature.fors.signatures[K - 1].secret_key
+    }).collect();
+    assert!(last_keys.iter().any(|key| *key != last_keys[0]));
+}
+
+#[test]
+fn test_split_digest() {
+    let digest = [0xFFu8; digest_len(4, 4, 2, 3)];
+    assert_eq!(digest.len(), 3);
+
+    let (md, index) = split_digest::<4, 4, 2, 3>(&digest);
+    assert_eq!(md, &[0xFF, 0xFF]);
+    assert_eq!(index, 0b111111);
+
+    let digest = [0xAAu8; digest_len(33, 6, 22, 3)];
+    let (md, index) = split_digest::<33, 6, 22, 3>(&digest);
+    assert_eq!(md.len(), 25);
+    assert_eq!(index, 0xAAAAAAAAAAAAAAAAAu128 >> 2);
+}
+
+#[test]
+fn test_same_timestamp_randomized() {
+    const K: usize = 4;
+    const A: usize = 4;
+    const LAYERS: usize = 2;
+    const TREE_HEIGHT: usize = 3;
+
+    let signer = SphincsSigner::<K, A, LAYERS, TREE_HEIGHT>::new([1u8; 32], [2u8; 32]);
+    let public_key = signer.public_key();
+    let message = b"Same message, same time";
+
+    let signature1 = signer.sign_with_set_ts(message, 1000, None);
+    let signature2 = signer.sign_with_set_ts(message, 1000, None);
+
+    assert_ne!(signature1.randomizer, signature2.randomizer);
+    assert!(signature1.validate(message, &public_key).is_ok());
+    assert!(signature2.validate(message, &public_key).is_ok());
+
+    let mut tampered_signature = signature1.clone();
+    tampered_signature.randomizer[0] ^= 1;
+    assert!(tampered_signature.validate(message, &public_key).is_err());
+}
+
+#[test]
+fn test_explicit_prf_key() {
+    const K: usize = 4;
+    const A: usize = 4;
+    const LAYERS: usize = 2;
+    const TREE_HEIGHT: usize = 3;
+
+    let signer = SphincsSigner::<K, A, LAYERS, TREE_HEIGHT>::with_prf([1u8; 32], [3u8; 32], [2u8; 32]);
+    // SK.prf does not change the public key
+    assert_eq!(signer.public_key().key, SphincsSigner::<K, A, LAYERS, TREE_HEIGHT>::new([1u8; 32], [2u8; 32]).public_key().key);
+
+    let signature = signer.sign(b"Verify me!");
+    assert!(signature.validate(b"Verify me!", &signer.public_key()).is_ok());
+}
+
+#[test]
+fn test_deterministic_signing() {
+    const K: usize = 4;
+    const A: usize = 4;
+    const LAYERS: usize = 2;
+    const TREE_HEIGHT: usize = 3;
+
+    let signer = SphincsSigner::<K, A, LAYERS, TREE_HEIGHT>::new([1u8; 32], [2u8; 32]).with_mode(SigningMode::Deterministic);
+    let public_key = signer.public_key();
+    assert_eq!(signer.mode(), SigningMode::Deterministic);
+
+    let signature1 = signer.sign(b"Reproduce me!");
+    let signature2 = signer.sign(b"Reproduce me!");
+    assert_eq!(signature1, signature2);
+    assert_eq!(signature1.timestamp, 0);
+    assert!(signature1.validate(b"Reproduce me!", &public_key).is_ok());
+
+    // A fresh signer with the same key gives the same signature
+    let same_key = SphincsSigner::<K, A, LAYERS, TREE_HEIGHT>::new([1u8; 32], [2u8; 32]).with_mode(SigningMode::Deterministic);
+    assert_eq!(same_key.sign(b"Reproduce me!"), signature1);
+    assert_ne!(signer.sign(b"Reproduce me?").randomizer, signature1.randomizer);
+
+    // Randomized signers of the same key still validate against the same public key
+    let randomized = signer.clone().with_mode(SigningMode::Randomized);
+    let signature3 = randomized.sign(b"Reproduce me!");
+    assert_ne!(signature3.randomizer, signature1.randomizer);
+    assert!(signature3.validate(b"Reproduce me!", &public_key).is_ok());
+}
+
+#[test]
+fn test_signature_to_from_bytes() {
+    const K: usize = 4;
+    const A: usize = 4;
+    const LAYERS: usize = 2;
+    const TREE_HEIGHT: usize = 3;
+    type Signature = SphincsSignature<K, A, LAYERS, TREE_HEIGHT>;
+
+    let signer = SphincsSigner::<K, A, LAYERS, TREE_HEIGHT>::new([1u8; 32], [2u8; 32]);
+    let signature = signer.sign(b"Store me!");
+
+    let bytes = signature.to_bytes();
+    assert_eq!(bytes.len(), Signature::size());
+    assert_eq!(bytes[bytes.len() - 16..], signature.timestamp.to_be_bytes());
+    let loaded = Signature::from_bytes(&bytes).unwrap();
+    assert_eq!(loaded, signature);
+    assert!(loaded.validate(b"Store me!", &signer.public_key()).is_ok());
+
+    assert_eq!(Signature::from_bytes(&bytes[..bytes.len() - 1]).err(), Some(DecodeError::WrongLength(bytes.len(), bytes.len() - 1)));
+    assert_eq!(Signature::from_bytes(&[]).err(), Some(DecodeError::WrongLength(bytes.len(), 0)));
+
+    // A flipped bit decodes, but does not validate
+    let mut tampered = bytes.clone();
+    tampered[100] ^= 1;
+    assert!(Signature::from_bytes(&tampered).unwrap().validate(b"Store me!", &signer.public_key()).is_err());
+}
+
+#[test]
+fn test_parameter_set_signature_sizes() {
+    // The fixed sizes of the canonical encoding, listed in the README
+    let expected = [
+        (ParameterSet::SphincsSha2_128s, 6640), (ParameterSet::SphincsSha2_128f, 13022),
+        (ParameterSet::SphincsSha2_192s, 12912), (ParameterSet::SphincsSha2_192f, 24942),
+        (ParameterSet::SphincsSha2_256s, 22562), (ParameterSet::SphincsSha2_256f, 34292),
+    ];
+    for (set, size) in expected {
+        assert_eq!(set.params().scheme, Scheme::Sphincs);
+        assert_eq!(set.params().signature_size, size, "{set}");
+    }
+    assert_eq!(SphincsSignature::<33, 6, 22, 3, 16>::size(), 13022);
+}
+
+#[test]
+fn test_index_space() {
+    let small = SphincsSigner::<4, 4, 2, 3>::new([1u8; 32], [2u8; 32]).into_stateful();
+    assert_eq!((small.index_space(), small.last_index()), (None, 63));
+    assert_eq!(small.with_index_space(IndexSpace::Bits32).err(), Some(IndexSpaceError::TooFewLeaves(32, 6)));
+
+    // 33 layers for 2^32 indices, the rest of the hypertree is never signed at
+    let signer = Sphincs32Sha2_128fSigner::new([1u8; 16], [2u8; 16]).into_stateful();
+    assert_eq!(signer.last_index(), (1 << 33) - 1);
+    assert_eq!(signer.clone().with_index_space(IndexSpace::Bits64).err(), Some(IndexSpaceError::TooFewLeaves(64, 33)));
+    let signer = signer.with_index_space(IndexSpace::Bits32).unwrap();
+    assert_eq!((signer.index_space(), signer.last_index()), (Some(IndexSpace::Bits32), u32::MAX as u64));
+
+    // 128f has 66 bits of hypertree, 128s only 63
+    let signer = SphincsSha2_128fSigner::new([1u8; 16], [2u8; 16]).into_stateful().with_index_space(IndexSpace::Bits64).unwrap();
+    assert_eq!(signer.last_index(), u64::MAX - 1);
+    let deep = SphincsSigner::<4, 4, 21, 3, 16>::new([1u8; 16], [2u8; 16]).into_stateful();
+    assert_eq!(deep.with_index_space(IndexSpace::Bits64).err(), Some(IndexSpaceError::TooFewLeaves(64, 63)));
+}
+
+#[test]
+fn test_index_space_signature_sizes() {
+    // The half-height sets, listed in the README
+    assert_eq!(Sphincs32Sha2_128sSignature::size(), 5098);
+    assert_eq!(Sphincs32Sha2_128fSignature::size(), 8424);
+    assert_eq!(Sphincs32Sha2_192sSignature::size(), 10074);
+    assert_eq!(Sphincs32Sha2_192fSignature::size(), 16120);
+    assert_eq!(Sphincs32Sha2_256sSignature::size(), 16666);
+    assert_eq!(Sphincs32Sha2_256fSignature::size(), 22178);
+}
+
+#[test]
+fn test_compact_signature() {
+    const K: usize = 4;
+    const A: usize = 4;
+    const LAYERS: usize = 2;
+    const TREE_HEIGHT: usize = 3;
+    type Compact = CompactSphincsSignature<K, A, LAYERS, TREE_HEIGHT>;
+
+    let signer = SphincsSigner::<K, A, LAYERS, TREE_HEIGHT>::new([1u8; 32], [2u8; 32]);
+    let public_key = signer.public_key();
+    let signature = signer.sign(b"Make me smaller!");
+    let compact = signature.to_compact();
+
+    // Only the signer-only values are kept, the verifier rebuilds the rest
+    assert_eq!(compact.expand(b"Make me smaller!", &public_key), signature);
+    assert!(compact.validate(b"Make me smaller!", &public_key).is_ok());
+    assert!(compact.validate(b"Make me smaller?", &public_key).is_err());
+
+    let bytes = compact.to_bytes();
+    assert_eq!(bytes.len(), Compact::size());
+    assert!(Compact::size() < SphincsSignature::<K, A, LAYERS, TREE_HEIGHT>::size());
+    assert_eq!(Compact::from_bytes(&bytes).unwrap(), compact);
+    assert_eq!(Compact::from_bytes(&bytes[1..]).err(), Some(DecodeError::WrongLength(bytes.len(), bytes.len() - 1)));
+    // The index takes one byte after the randomizer, and only its low 6 bits are used
+    let mut high_bit = bytes.clone();
+    high_bit[32] |= 0x40;
+    assert_eq!(Compact::from_bytes(&high_bit).err(), Some(DecodeError::IndexOutOfRange(compact.index | 0x40)));
+
+    // A flipped bit anywhere in the chains or paths changes the reconstructed root
+    let mut tampered = bytes.clone();
+    tampered[bytes.len() - 40] ^= 1;
+    assert!(Compact::from_bytes(&tampered).unwrap().validate(b"Make me smaller!", &public_key).is_err());
+
+    // As does pointing the signature at another leaf
+    let mut moved = compact.clone();
+    moved.index ^= 1;
+    assert!(moved.validate(b"Make me smaller!", &public_key).is_err());
+}
+
+#[test]
+fn test_compact_signature_sizes() {
+    // Listed in the README next to the canonical sizes
+    assert_eq!(CompactSphincsSignature::<14, 12, 7, 9, 16>::size(), 5976);
+    assert_eq!(CompactSphincsSignature::<33, 6, 22, 3, 16>::size(), 11129);
+    assert_eq!(CompactSphincsSignature::<17, 14, 7, 9, 24>::size(), 12048);
+    assert_eq!(CompactSphincsSignature::<33, 8, 22, 3, 24>::size(), 22489);
+    assert_eq!(CompactSphincsSignature::<22, 14, 8, 8, 32>::size(), 21368);
+    assert_eq!(CompactSphincsSignature::<35, 9, 17, 4, 32>::size(), 31929);
+}
+
+#[test]
+fn test_keys_to_from_bytes() {
+    const K: usize = 4;
+    const A: usize = 4;
+    const LAYERS: usize = 2;
+    const TREE_HEIGHT: usize = 3;
+    type Signer = SphincsSigner<K, A, LAYERS, TREE_HEIGHT>;
+    type Public = SphincsPublic<K, A, LAYERS, TREE_HEIGHT>;
+
+    let signer = Signer::new([1u8; 32], [2u8; 32]);
+    let public_key = signer.public_key();
+
+    let secret_bytes = signer.to_bytes();
+    let public_bytes = public_key.to_bytes();
+    assert_eq!(secret_bytes.len(), Signer::encoded_size());
+    assert_eq!(public_bytes.len(), Public::encoded_size());
+    // Not a named set, so the id is 0 and the shape follows, with W as log2(W) and SHA2 as 1
+    assert_eq!(secret_bytes[..9], [KEY_VERSION, 0, 4, 4, 2, 3, 32, 8, 1]);
+
+    let loaded_signer = Signer::from_bytes(&secret_bytes).unwrap();
+    let loaded_public = Public::from_bytes(&public_bytes).unwrap();
+    assert_eq!(loaded_signer, signer);
+    assert_eq!(loaded_public, public_key);
+    assert!(loaded_signer.matches(&loaded_public));
+    assert!(!Signer::new([3u8; 32], [2u8; 32]).matches(&loaded_public));
+
+    let signature = loaded_signer.sign(b"Loaded keys");
+    assert!(signature.validate(b"Loaded keys", &loaded_public).is_ok());
+
+    // A stored root that does not belong to the seeds is refused
+    let mut wrong_root = secret_bytes.clone();
+    *wrong_root.last_mut().unwrap() ^= 1;
+    assert_eq!(Signer::from_bytes(&wrong_root), Err(DecodeError::KeyMismatch));
+
+    let mut wrong_version = public_bytes.clone();
+    wrong_version[0] = 2;
+    assert_eq!(Public::from_bytes(&wrong_version), Err(DecodeError::UnsupportedVersion(2)));
+    assert_eq!(Public::from_bytes(&public_bytes[1..]), Err(DecodeError::WrongLength(73, 72)));
+}
+
+#[test]
+fn test_keys_carry_parameter_set() {
+    let signer = SphincsSha2_128fSigner::new([1u8; 16], [2u8; 16]);
+    let bytes = signer.public_key().to_bytes();
+    assert_eq!(bytes[1], ParameterSet::SphincsSha2_128f.id());
+
+    // Same N, so the lengths agree, but the id does not
+    assert_eq!(
+        SphincsPublic::<14, 12, 7, 9, 16>::from_bytes(&bytes),
+        Err(DecodeError::WrongParameterSet(ParameterSet::SphincsSha2_128s.id(), ParameterSet::SphincsSha2_128f.id()))
+    );
+
+    // Shapes that are not a named set carry the shape instead
+    let bytes = Sphincs32Sha2_128sSigner::new([1u8; 16], [2u8; 16]).public_key().to_bytes();
+    assert_eq!(Sphincs32Sha2_128fPublic::from_bytes(&bytes), Err(DecodeError::WrongShape([33, 6, 11, 3, 16, 8, 1], [14, 12, 4, 9, 16, 8, 1])));
+    let bytes = SphincsSigner::<4, 4, 2, 3>::new([1u8; 32], [2u8; 32]).public_key().to_bytes();
+    assert_eq!(SphincsPublic::<4, 4, 2, 3, 32, 16>::from_bytes(&bytes), Err(DecodeError::WrongShape([4, 4, 2, 3, 32, 4, 1], [4, 4, 2, 3, 32, 8, 1])));
+    assert_eq!(SphincsPublic::<4, 4, 2, 3, 32, 256, Shake256Suite>::from_bytes(&bytes), Err(DecodeError::WrongShape([4, 4, 2, 3, 32, 8, 2], [4, 4, 2, 3, 32, 8, 1])));
+}
+
+fn check_signature_ref<const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, H: HashSuite>() {
+    let signer = SphincsSigner::<K, A, LAYERS, TREE_HEIGHT, 32, 256, H>::new([1u8; 32], [2u8; 32]);
+    let public_key = signer.public_key();
+    let signature = signer.sign(b"Check me in place!");
+    let bytes = signature.to_bytes();
+
+    let view = SphincsSignatureRef::<K, A, LAYERS, TREE_HEIGHT, 32, 256, H>::new(&bytes).unwrap();
+    assert_eq!((view.data_hash(), view.randomizer(), view.timestamp()), (signature.data_hash, signature.randomizer, signature.timestamp));
+    assert_eq!(view.to_signature(), signature);
+    let valid = view.validate(b"Check me in place!", &public_key).unwrap();
+    assert_eq!((valid.public_key, valid.timestamp), (public_key.key, signature.timestamp));
+
+    // Every byte of the FORS secret keys, the chains, the paths and the timestamp
+    // matters, and both verifiers fail the same way
+    let same_failure = |bytes: &[u8], message: &[u8]| {
+        let owned = SphincsSignature::<K, A, LAYERS, TREE_HEIGHT, 32, 256, H>::from_bytes(bytes).unwrap().validate(message, &public_key);
+        let borrowed = SphincsSignatureRef::<K, A, LAYERS, TREE_HEIGHT, 32, 256, H>::new(bytes).unwrap().validate(message, &public_key);
+        assert!(borrowed.is_err());
+        assert_eq!(format!("{:?}", owned.err()), format!("{:?}", borrowed.err()));
+    };
+    same_failure(&bytes, b"Check me elsewhere!");
+    for position in [64, 64 + 32 * (A + 1) - 1, bytes.len() / 2, bytes.len() - 16 - 33, bytes.len() - 1] {
+        let mut tampered = bytes.clone();
+        tampered[position] ^= 1;
+        same_failure(&tampered, b"Check me in place!");
+    }
+}
+
+#[test]
+fn test_signature_ref() {
+    check_signature_ref::<4, 4, 2, 3, Sha2Suite>();
+    check_signature_ref::<6, 5, 3, 2, Shake256Suite>();
+
+    let bytes = SphincsSigner::<4, 4, 2, 3>::new([1u8; 32], [2u8; 32]).sign(b"Short").to_bytes();
+    type View<'a> = SphincsSignatureRef<'a, 4, 4, 2, 3>;
+    assert_eq!(View::new(&bytes[1..]), Err(DecodeError::WrongLength(bytes.len(), bytes.len() - 1)));
+    // The address type of the FORS context, after the randomizer, the FORS elements and the public seed
+    let mut bad_address = bytes.clone();
+    bad_address[64 + 4 * 5 * 32 + 32 + 18] = 9;
+    assert_eq!(View::new(&bad_address), Err(DecodeError::UnknownAddressType(9)));
+}
+
+#[test]
+fn test_tree_index_is_bound_to_the_digest() {
+    let signer = SphincsSigner::<4, 4, 2, 3>::new([1u8; 32], [2u8; 32]);
+    let public_key = signer.public_key();
+    let message = b"Stay where you are";
+    let validate = |signature: &SphincsSignature<4, 4, 2, 3>| {
+        let owned = signature.validate(message, &public_key);
+        let borrowed = SphincsSignatureRef::<4, 4, 2, 3>::new(&signature.to_bytes()).unwrap().validate(message, &public_key);
+        assert_eq!(format!("{:?}", owned.as_ref().err()), format!("{:?}", borrowed.err()));
+        owned
+    };
+
+    // A digest picked index, and every forced one, verify
+    let signature = signer.sign(message);
+    assert!(validate(&signature).is_ok());
+    for index in 0..64 {
+        let forced = signer.sign_with_set_ts(message, 7, Some(index));
+        assert_eq!(forced.fors.context.address.position, index as u128);
+        assert!(validate(&forced).is_ok());
+    }
+
+    // Moving the FORS instance to another leaf breaks the addresses above it
+    let index = signature.fors.context.address.position;
+    // Moving the FORS instance to a leaf of another bottom tree breaks the addresses above it
+    let other = (index + 8) % 64;
+    let mut moved = signature.clone();
+    moved.fors.context.address.position = other;
+    assert!(matches!(validate(&moved), Err(SigntureError::HTContext(0))));
+    moved.hyper_tree.proofs[0].signature.context.address.position = other;
+    assert!(matches!(validate(&moved), Err(SigntureError::HTContext(1))));
+    // With every address moved, the FORS digest is taken with the new index
+    moved.hyper_tree.proofs[1].signature.context.address.position = other >> 3;
+    assert!(matches!(validate(&moved), Err(SigntureError::ForsFailure(..))));
+
+    // An index past the hypertree is refused, even with matching addresses
+    let mut outside = signature.clone();
+    outside.fors.context.address.position = index + 64;
+    outside.hyper_tree.proofs[0].signature.context.address.position = index + 64;
+    outside.hyper_tree.proofs[1].signature.context.address.position = (index + 64) >> 3;
+    assert!(matches!(validate(&outside), Err(SigntureError::ForsContext)));
+}
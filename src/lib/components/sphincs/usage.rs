@@ -0,0 +1,184 @@
+// How much of a stateful key is used, and alerts as it runs out. Every index below
+// the next one the store would return counts as used, including those skipped by
+// the time floor or abandoned with a lease.
+use std::{fmt, ops::Range};
+
+use crate::lib::{
+    components::sphincs::{secret::get_ms_timestamp_milliseconds, signature::SphincsSignature, state::{IndexState, IndexStore, StateError, StatefulSigner}},
+    helpers::hasher::HashSuite
+};
+
+pub const DEFAULT_THRESHOLDS: [u8; 3] = [50, 90, 99];
+
+// Called with the threshold reached, in percent, and the usage that reached it
+pub type UsageHook = Box<dyn FnMut(u8, &Usage) + Send>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Usage {
+    pub used: u128,
+    pub remaining: u128,
+    pub capacity: u128, // last_index + 1, which is 2^64 - 1 for IndexSpace::Bits64
+    pub exhausted_at: Option<u128>, // milliseconds since the epoch, at the rate since creation
+    pub subtrees: Range<u64>, // the trees under the top layer with used indices, signing continues in the last
+}
+
+impl Usage {
+    // Rounded down, so 100 only once every index is used
+    pub fn percent(&self) -> u8 {
+        (self.used * 100 / self.capacity).min(100) as u8
+    }
+
+    fn reached(&self, threshold: u8) -> bool {
+        self.used * 100 >= threshold as u128 * self.capacity
+    }
+}
+
+impl<const K:usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite> StatefulSigner<K, A, LAYERS, TREE_HEIGHT, N, W, H> {
+    pub fn usage(&self, state: &IndexState, now: u128) -> Usage {
+        let capacity = self.last_index() as u128 + 1;
+        let used = (state.next_index(now) as u128).min(capacity);
+        let remaining = capacity - used;
+
+        let elapsed = now.saturating_sub(state.created_at);
+        let exhausted_at = match used {
+            0 => None,
+            _ if elapsed == 0 => None,
+            _ => Some(now + remaining * elapsed / used),
+        };
+
+        let subtree_leaves = 1u128 << ((LAYERS - 1) * TREE_HEIGHT);
+        let subtrees = 0..used.div_ceil(subtree_leaves) as u64;
+        Usage { used, remaining, capacity, exhausted_at, subtrees }
+    }
+
+    // sign_stateful, then the alerts for the usage after the signature. Only this
+    // checks the alerts, signatures made with plain sign_stateful are counted once
+    // the next call here sees the store.
+    pub fn sign_stateful_with_alerts(&self, message: &[u8], store: &mut impl IndexStore, alerts: &mut UsageAlerts) -> Result<SphincsSignature<K, A, LAYERS, TREE_HEIGHT, N, W, H>, StateError> {
+        let signature = self.sign_stateful(message, store)?;
+        alerts.check(&self.usage(&store.current(), get_ms_timestamp_milliseconds()));
+        Ok(signature)
+    }
+}
+
+// Calls the hook once for every threshold the usage reaches, lowest first. The
+// thresholds reached are kept in memory only, so after a restart the hook is
+// called again for those the key is already past. The alerts are not part of the
+// signer: they are checked by sign_stateful_with_alerts, or by calling `check`.
+pub struct UsageAlerts {
+    thresholds: Vec<u8>,
+    reached: usize,
+    hook: UsageHook,
+}
+
+impl UsageAlerts {
+    pub fn new(thresholds: &[u8], hook: impl FnMut(u8, &Usage) + Send + 'static) -> Self {
+        let mut thresholds = thresholds.to_vec();
+        thresholds.sort_unstable();
+        thresholds.dedup();
+        Self { thresholds, reached: 0, hook: Box::new(hook) }
+    }
+
+    pub fn thresholds(&self) -> &[u8] {
+        &self.thresholds
+    }
+
+    pub fn check(&mut self, usage: &Usage) {
+        while let Some(&threshold) = self.thresholds.get(self.reached).filter(|&&threshold| usage.reached(threshold)) {
+            self.reached += 1;
+            (self.hook)(threshold, usage);
+        }
+    }
+}
+
+impl fmt::Debug for UsageAlerts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UsageAlerts").field("thresholds", &self.thresholds).field("reached", &self.reached).finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::{Arc, Mutex}};
+
+    use super::*;
+    use crate::lib::components::{parameters::Sphincs32Sha2_128fSigner, sphincs::{secret::SphincsSigner, state::{FileIndexStore, IndexSpace}, tests::temp_dir}};
+
+    #[test]
+    fn test_usage() {
+        // 2 layers of height 3: 64 leaves, under 8 trees of the top layer
+        let signer = SphincsSigner::<4, 4, 2, 3>::new([1u8; 32], [2u8; 32]).into_stateful();
+        let state = IndexState { created_at: 0, next_sequence: 20 };
+
+        assert_eq!(signer.usage(&state, 0), Usage { used: 20, remaining: 44, capacity: 64, exhausted_at: None, subtrees: 0..3 });
+        // 20 indices in 10 seconds, so the other 44 last 22 more
+        let usage = signer.usage(&state, 10_000);
+        assert_eq!((usage.exhausted_at, usage.percent()), (Some(32_000), 31));
+        // The time floor, 60 indices after a minute
+        assert_eq!(signer.usage(&state, 60_000).used, 60);
+        assert_eq!(signer.usage(&state, 120_000), Usage { used: 64, remaining: 0, capacity: 64, exhausted_at: Some(120_000), subtrees: 0..8 });
+
+        // Only half of the top layer is reachable with 2^32 indices
+        let signer = Sphincs32Sha2_128fSigner::new([1u8; 16], [2u8; 16]).into_stateful().with_index_space(IndexSpace::Bits32).unwrap();
+        let usage = signer.usage(&IndexState { created_at: 0, next_sequence: 3 << 30 }, 0);
+        assert_eq!((usage.percent(), usage.capacity, usage.remaining, usage.subtrees), (75, 1 << 32, 1 << 30, 0..3));
+        // A store hands out every index but u64::MAX
+        assert_eq!(SphincsSigner::<4, 4, 22, 3, 16>::new([1u8; 16], [2u8; 16]).into_stateful().usage(&IndexState::new(0), 0).capacity, u64::MAX as u128);
+    }
+
+    #[test]
+    fn test_alerts_until_exhausted() {
+        let dir = temp_dir("usage_alerts");
+        // A creation time in the future keeps the time floor out of the way
+        let mut store = FileIndexStore::create(&dir.join("key.idx"), u128::MAX / 2).unwrap();
+        let signer = SphincsSigner::<4, 4, 2, 3>::new([1u8; 32], [2u8; 32]).into_stateful();
+
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let log = fired.clone();
+        let mut alerts = UsageAlerts::new(&DEFAULT_THRESHOLDS, move |threshold, usage| log.lock().unwrap().push((threshold, usage.used)));
+
+        for _ in 0..64 {
+            signer.sign_stateful_with_alerts(b"Count me", &mut store, &mut alerts).unwrap();
+        }
+        assert_eq!(*fired.lock().unwrap(), [(50, 32), (90, 58), (99, 64)]);
+        assert!(matches!(signer.sign_stateful_with_alerts(b"Count me", &mut store, &mut alerts), Err(StateError::Exhausted(64))));
+        assert_eq!(fired.lock().unwrap().len(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_alerts_until_bits64_exhausted() {
+        let dir = temp_dir("usage_bits64");
+        let path = dir.join("key.idx");
+        fs::write(&path, IndexState { created_at: u128::MAX / 2, next_sequence: u64::MAX - 1 }.to_bytes()).unwrap();
+        let mut store = FileIndexStore::open(&path).unwrap();
+        // 22 layers of height 3 have more leaves than a 64-bit space has indices
+        let signer = SphincsSigner::<4, 4, 22, 3, 16>::new([1u8; 16], [2u8; 16]).into_stateful().with_index_space(IndexSpace::Bits64).unwrap();
+
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let log = fired.clone();
+        let mut alerts = UsageAlerts::new(&[99, 100], move |threshold, usage| log.lock().unwrap().push((threshold, usage.remaining)));
+
+        // The last index there is
+        signer.sign_stateful_with_alerts(b"Count me", &mut store, &mut alerts).unwrap();
+        assert_eq!(*fired.lock().unwrap(), [(99, 0), (100, 0)]);
+        let usage = signer.usage(&store.current(), 0);
+        assert_eq!((usage.used, usage.percent()), (u64::MAX as u128, 100));
+        assert!(matches!(signer.sign_stateful_with_alerts(b"Count me", &mut store, &mut alerts), Err(StateError::Exhausted(u64::MAX))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_alerts_fire_in_order() {
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let log = fired.clone();
+        let mut alerts = UsageAlerts::new(&[99, 50, 90, 50], move |threshold, _: &Usage| log.lock().unwrap().push(threshold));
+        assert_eq!(alerts.thresholds(), [50, 90, 99]);
+
+        let usage = |used| Usage { used, remaining: 100 - used, capacity: 100, exhausted_at: None, subtrees: 0..1 };
+        alerts.check(&usage(49));
+        alerts.check(&usage(95));
+        alerts.check(&usage(96));
+        assert_eq!(*fired.lock().unwrap(), [50, 90]);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod secret;
 pub mod public;
 pub mod signature;
+#[cfg(test)]
 mod tests;
\ No newline at end of file
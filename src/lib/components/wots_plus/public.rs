@@ -1,20 +1,23 @@
-use crate::lib::{components::wots_plus::signature::WotsPlusSignature, helpers::{hasher::{HashContext, hash_message}, random_generator::HashData}};
+use std::marker::PhantomData;
+
+use crate::lib::{components::wots_plus::signature::WotsPlusSignature, helpers::{hasher::{HashContext, HashSuite, Sha2Suite}, random_generator::HashData}};
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct WotsPlusPublic {
-    pub public_key: HashData,
-    pub context: HashContext,
+pub struct WotsPlusPublic<const N: usize = 32, const W: usize = 256, H: HashSuite = Sha2Suite> {
+    pub public_key: HashData<N>,
+    pub context: HashContext<N>,
+    pub(crate) suite: PhantomData<H>,
 }
 
 
-impl WotsPlusPublic {
+impl<const N: usize, const W: usize, H: HashSuite> WotsPlusPublic<N, W, H> {
     
-    pub fn validate_hash(&self, hash: HashData, sign: &WotsPlusSignature) -> bool {
+    pub fn validate_hash(&self, hash: HashData<N>, sign: &WotsPlusSignature<N, W, H>) -> bool {
         sign.clone().get_expected_public_from_hash(hash) == self.public_key
     }
 
-    pub fn validate_message(&self, message: &[u8], _sign: &WotsPlusSignature) -> bool {
-        let message_hash = hash_message(message);
+    pub fn validate_message(&self, message: &[u8], _sign: &WotsPlusSignature<N, W, H>) -> bool {
+        let message_hash = H::hash_message(message);
         self.validate_hash(message_hash, _sign)
     }
     
@@ -1,35 +1,38 @@
-use crate::lib::{components::wots_plus::signature::{MAX_HASHES_NEEDED, WotsPlusSignature}, 
-    helpers::{hasher::{HashContext, hash_message, hash_array, repeat_hash}, 
-    random_generator::{Address, HASH_DATA_0, HashData, InnerKeyRole, RandomGeneratorSha256}}};
+use std::marker::PhantomData;
+
+use crate::lib::{components::wots_plus::signature::{WotsPlusSignature, chain_context, chain_lengths, checksum_chains, message_chains, public_key_context}, 
+    helpers::{encoding::{DecodeError, Reader}, hasher::{HashContext, HashSuite, Sha2Suite}, 
+    random_generator::{Address, AddressType, HashData, InnerKeyRole, get_key}}};
 use rand;
 use super::public::WotsPlusPublic;
-pub struct SeedPair(pub HashData, pub HashData); // private_seed, public_seed
+pub struct SeedPair<const N: usize = 32>(pub HashData<N>, pub HashData<N>); // private_seed, public_seed
 
 
 #[derive(Debug, PartialEq, Clone)]
-pub struct SecretKeysPair{
-    message: [HashData;32],
-    checksum: [HashData;2]
+pub struct SecretKeysPair<const N: usize = 32>{
+    message: Vec<HashData<N>>,
+    checksum: Vec<HashData<N>>
 }
 
 #[derive(Debug, PartialEq, Clone)]
-pub struct WotsPlus {
-    seed: HashData,
+pub struct WotsPlus<const N: usize = 32, const W: usize = 256, H: HashSuite = Sha2Suite> {
+    seed: HashData<N>,
     
-    secret_keys: SecretKeysPair,
-    pub context: HashContext
+    secret_keys: SecretKeysPair<N>,
+    pub context: HashContext<N>,
+    suite: PhantomData<H>,
 }
 
-impl WotsPlus {
+impl<const N: usize, const W: usize, H: HashSuite> WotsPlus<N, W, H> {
     
-    pub fn gen_true_random_keys() -> SeedPair{
+    pub fn gen_true_random_keys() -> SeedPair<N>{
         SeedPair(rand::random(), rand::random())
     }
 
-    fn generate_secret_keys(seed: HashData, address: &Address) -> SecretKeysPair{
-        let mut rndgen = RandomGeneratorSha256::new(seed);
-        let message_keys = rndgen.get_keys::<32>(address, InnerKeyRole::MessageKey);
-        let checksum_keys = rndgen.get_keys::<2>(address, InnerKeyRole::ChecksumKey);
+    fn generate_secret_keys(seed: HashData<N>, address: &Address) -> SecretKeysPair<N>{
+        let address = &address.with_type(AddressType::WotsPrf);
+        let message_keys = (0..message_chains(N, W)).map(|i| get_key::<H, N>(seed, address, &InnerKeyRole::MessageKey, i)).collect();
+        let checksum_keys = (0..checksum_chains(N, W)).map(|i| get_key::<H, N>(seed, address, &InnerKeyRole::ChecksumKey, i)).collect();
         
         SecretKeysPair {
             message: message_keys,
@@ -38,8 +41,8 @@ impl WotsPlus {
 
     }
 
-    pub fn new(seed: HashData, context: HashContext) -> Self {
-        Self {seed, secret_keys: Self::generate_secret_keys(seed, &context.address), context}
+    pub fn new(seed: HashData<N>, context: HashContext<N>) -> Self {
+        Self {seed, secret_keys: Self::generate_secret_keys(seed, &context.address), context, suite: PhantomData}
     }
 
     pub fn new_random(address: Address) -> Self {
@@ -47,59 +50,45 @@ impl WotsPlus {
         Self::new(seed, HashContext { public_seed, address: address.clone() })
     }
 
-    pub fn generate_public_key(&self) -> WotsPlusPublic {
-        let mut public_keyset = [HASH_DATA_0;34];
-        
-        for (index, sk) in self.secret_keys.message.iter().enumerate(){
-            public_keyset[index] = repeat_hash(*sk, 255, &self.context);
-        };
-        
-        for (index, sk) in self.secret_keys.checksum.iter().enumerate(){
-            public_keyset[32 + index] = repeat_hash(*sk, 255, &self.context);
-        };
+    pub fn generate_public_key(&self) -> WotsPlusPublic<N, W, H> {
+        let public_keyset: Vec<HashData<N>> = self.secret_keys.message.iter().chain(self.secret_keys.checksum.iter()).enumerate()
+            .map(|(chain, sk)| H::repeat_hash(*sk, (W - 1) as u8, &chain_context(&self.context, chain)))
+            .collect();
 
-        let public_key = hash_array(&public_keyset);
-        WotsPlusPublic { public_key, context: self.context.clone()}
+        let public_key = H::hash_with_context(&public_keyset, &public_key_context(&self.context));
+        WotsPlusPublic { public_key, context: self.context.clone(), suite: PhantomData }
     }
     
-    pub fn sign_hash(&self, _hash: HashData) -> WotsPlusSignature {
-        let mut count_hashes_left: u16 = MAX_HASHES_NEEDED;
-        let mut message_hashes = [HASH_DATA_0; 32];
-        let mut checksum_hashes = [HASH_DATA_0; 2];
-        
-        for (index, times_to_repeat) in _hash.into_iter().enumerate() {
-            let key = self.secret_keys.message[index];
-            message_hashes[index] = repeat_hash(key, times_to_repeat, &self.context);
-            count_hashes_left -= times_to_repeat as u16; 
-        };
-        
-        let two_bytes = count_hashes_left.to_le_bytes();
-        for (index, times_to_repeat) in two_bytes.into_iter().enumerate() {
-            let key = self.secret_keys.checksum[index];
-            checksum_hashes[index] = repeat_hash(key, times_to_repeat, &self.context);
-        };
+    pub fn sign_hash(&self, _hash: HashData<N>) -> WotsPlusSignature<N, W, H> {
+        let mut chains: Vec<HashData<N>> = self.secret_keys.message.iter().chain(self.secret_keys.checksum.iter())
+            .zip(chain_lengths::<N, W>(&_hash)).enumerate()
+            .map(|(chain, (key, times_to_repeat))| H::repeat_hash(*key, times_to_repeat, &chain_context(&self.context, chain)))
+            .collect();
+        let checksum_hashes = chains.split_off(message_chains(N, W));
+        let message_hashes = chains;
+
         let public_key = self.generate_public_key().public_key;
-        WotsPlusSignature {checksum_hashes, context: self.context.clone(), message_hashes, public_key}
+        WotsPlusSignature {checksum_hashes, context: self.context.clone(), message_hashes, public_key, suite: PhantomData}
     }
     
-    pub fn sign_message(&self, message: &[u8]) -> WotsPlusSignature {
-        let hashed = hash_message(message);
+    pub fn sign_message(&self, message: &[u8]) -> WotsPlusSignature<N, W, H> {
+        let hashed = H::hash_message(message);
         self.sign_hash(hashed)
     }
     
-    pub fn to_bytes(&self) -> [u8; 82] {
-        let mut out = [0u8;82];
-        out[..32].copy_from_slice(&self.seed);
-        out[32..].copy_from_slice(&self.context.to_bytes());
+    pub const fn size() -> usize {
+        N + HashContext::<N>::size()
+    }
 
-        out
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [self.seed.as_slice(), &self.context.to_bytes()].concat()
     }
     
-    pub fn from_bytes(bytes: [u8; 82]) -> Self{
-        let seed:[u8;32] = bytes[..32].try_into().expect("Got the wrong size bytes");
-        let context_bytes:[u8;50] = bytes[32..].try_into().expect("Got the wrong size bytes");
-        let context = HashContext::from_bytes(context_bytes);
-        Self::new(seed, context)
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes, Self::size())?;
+        let seed = reader.array();
+        let context = HashContext::from_bytes(reader.take(HashContext::<N>::size()))?;
+        Ok(Self::new(seed, context))
     }
 }
 
@@ -111,16 +100,25 @@ mod tests {
 
     #[test]
     fn test_to_from_bytes() {
-        let wots = WotsPlus::new([9u8;32], HashContext { public_seed: [10u8;32], address: Address{level: 1, position: 1} });
-        let other_wots = WotsPlus::new([7u8;32], HashContext { public_seed: [90u8;32], address: Address{level: 2, position: 1} });
+        let wots = WotsPlus::<32>::new([9u8;32], HashContext { public_seed: [10u8;32], address: Address { level: 1, position: 1, ..Address::default() } });
+        let other_wots = WotsPlus::<32>::new([7u8;32], HashContext { public_seed: [90u8;32], address: Address { level: 2, position: 1, ..Address::default() } });
         
         let bytes_wots = wots.to_bytes();
         let bytes_other_wots = other_wots.to_bytes();
 
-        let new_wots = WotsPlus::from_bytes(bytes_wots);
-        let new_other_wots = WotsPlus::from_bytes(bytes_other_wots);
+        let new_wots = WotsPlus::<32>::from_bytes(&bytes_wots).unwrap();
+        let new_other_wots = WotsPlus::<32>::from_bytes(&bytes_other_wots).unwrap();
 
         assert_eq!(wots, new_wots);
         assert_ne!(wots, new_other_wots);
     }
+
+    #[test]
+    fn test_to_from_bytes_short_hashes() {
+        let wots = WotsPlus::<16>::new([9u8;16], HashContext { public_seed: [10u8;16], address: Address { level: 1, position: 1, ..Address::default() } });
+        
+        let bytes_wots = wots.to_bytes();
+        assert_eq!(bytes_wots.len(), 66);
+        assert_eq!(WotsPlus::<16>::from_bytes(&bytes_wots), Ok(wots));
+    }
 }
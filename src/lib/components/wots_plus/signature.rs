@@ -1,47 +1,103 @@
-use sha2::{Digest, Sha256, digest::Update};
-use crate::lib::helpers::{hasher::{HashContext, complement_hash, hash_array}, random_generator::{HASH_DATA_0, HashData}};
+use std::marker::PhantomData;
 
-pub const MAX_HASHES_NEEDED:u16 = 255 * 32;
+use crate::lib::{helpers::{encoding::{DecodeError, Reader}, hasher::{HashContext, HashSuite, Sha2Suite}, random_generator::{AddressType, HashData}}};
 
-pub struct ValidWotsPSignature (pub HashData, pub HashContext); // public key, context
-pub struct InvalidWotsPSignature (pub HashData, pub HashData); // calculated public key, public key, context
+// W is the Winternitz parameter, the length of every chain is W - 1
+pub const fn lg_w(w: usize) -> usize {
+    assert!(w == 4 || w == 16 || w == 256, "Supported Winternitz parameters are 4, 16 and 256");
+    w.trailing_zeros() as usize
+}
+
+pub const fn message_chains(n: usize, w: usize) -> usize {
+    8 * n / lg_w(w)
+}
+
+pub const fn max_hashes_needed(n: usize, w: usize) -> u16 {
+    ((w - 1) * message_chains(n, w)) as u16
+}
+
+// Enough chains to hold any checksum, e.g. 2 for w=256 and 3 for w=16 with n=32
+pub const fn checksum_chains(n: usize, w: usize) -> usize {
+    let checksum_bits = (u16::BITS - max_hashes_needed(n, w).leading_zeros()) as usize;
+    checksum_bits.div_ceil(lg_w(w))
+}
+
+// The number of hashes to apply on every chain, the message digits followed by the checksum digits
+pub(super) fn chain_lengths<const N: usize, const W: usize>(message_hash: &HashData<N>) -> impl Iterator<Item = u8> {
+    let lg_w = const { lg_w(W) };
+    // Digits are read most significant first, lg_w always divides 8
+    let digits = move || message_hash.iter().flat_map(move |byte| (0..8 / lg_w).rev().map(move |i| ((*byte as usize >> (i * lg_w)) & (W - 1)) as u8));
+    // Little endian digits, so w=256 keeps the original 2 byte checksum
+    let checksum = digits().fold(max_hashes_needed(N, W), |left, digit| left - digit as u16);
+    digits().chain((0..checksum_chains(N, W)).map(move |i| ((checksum >> (i * lg_w)) as usize & (W - 1)) as u8))
+}
+
+// Every chain hashes with its own chain address
+pub(super) fn chain_context<const N: usize>(context: &HashContext<N>, chain: usize) -> HashContext<N> {
+    let mut address = context.address.with_type(AddressType::WotsHash);
+    address.chain = chain as u32;
+    HashContext { public_seed: context.public_seed, address }
+}
+
+pub(super) fn public_key_context<const N: usize>(context: &HashContext<N>) -> HashContext<N> {
+    HashContext { public_seed: context.public_seed, address: context.address.with_type(AddressType::WotsPk) }
+}
+
+// Completes every chain of a signature and compresses the ends into the public key
+pub(crate) fn public_key_from_chains<const N: usize, const W: usize, H: HashSuite>(chains: impl Iterator<Item = HashData<N>>, message_hash: &HashData<N>, context: &HashContext<N>) -> HashData<N> {
+    let ends = chains.zip(chain_lengths::<N, W>(message_hash)).enumerate()
+        .map(|(chain, (key, times_repeated))| H::chain(key, times_repeated, (W - 1) as u8 - times_repeated, &chain_context(context, chain)));
+    H::hash_iter_with_context(ends, &public_key_context(context))
+}
+
+pub struct ValidWotsPSignature<const N: usize = 32> (pub HashData<N>, pub HashContext<N>); // public key, context
+pub struct InvalidWotsPSignature<const N: usize = 32> (pub HashData<N>, pub HashData<N>); // calculated public key, public key, context
 
 #[derive(Debug,Clone, PartialEq)]
-pub struct WotsPlusSignature {
-    pub context: HashContext,
-    pub message_hashes: [HashData;32],
-    pub checksum_hashes: [HashData;2],
-    pub public_key: HashData
-}
-
-impl WotsPlusSignature {
-    pub fn get_expected_public_from_hash(self, message_hash: HashData) -> HashData {
-        let mut count_hashes_left: u16 = MAX_HASHES_NEEDED;
-        let mut out = [HASH_DATA_0;34];
-
-        for (index, times_repeated) in message_hash.into_iter().enumerate() {
-            let key = self.message_hashes[index];
-            out[index] = complement_hash(key, times_repeated, &self.context);
-            count_hashes_left -= times_repeated as u16; 
-        };
-        
-        let two_bytes = count_hashes_left.to_le_bytes();
-
-        for (index, times_to_repeat) in two_bytes.into_iter().enumerate() {
-            let key = self.checksum_hashes[index];
-            out[32 + index] = complement_hash(key, times_to_repeat, &self.context);
-        };
-
-        hash_array(&out)
+pub struct WotsPlusSignature<const N: usize = 32, const W: usize = 256, H: HashSuite = Sha2Suite> {
+    pub context: HashContext<N>,
+    pub message_hashes: Vec<HashData<N>>,
+    pub checksum_hashes: Vec<HashData<N>>,
+    pub public_key: HashData<N>,
+    pub(crate) suite: PhantomData<H>,
+}
+
+impl<const N: usize, const W: usize, H: HashSuite> WotsPlusSignature<N, W, H> {
+    // The context, every chain and the public key
+    pub const fn size() -> usize {
+        HashContext::<N>::size() + (message_chains(N, W) + checksum_chains(N, W) + 1) * N
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::size());
+        out.extend(self.context.to_bytes());
+        self.message_hashes.iter().chain(&self.checksum_hashes).for_each(|hash| out.extend_from_slice(hash));
+        out.extend_from_slice(&self.public_key);
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes, Self::size())?;
+        let context = HashContext::from_bytes(reader.take(HashContext::<N>::size()))?;
+        let message_hashes = reader.hashes(message_chains(N, W));
+        let checksum_hashes = reader.hashes(checksum_chains(N, W));
+        let public_key = reader.array();
+        Ok(Self { context, message_hashes, checksum_hashes, public_key, suite: PhantomData })
+    }
+
+    pub fn get_expected_public_from_hash(self, message_hash: HashData<N>) -> HashData<N> {
+        self.expected_public_key(&message_hash)
+    }
+
+    pub fn get_expected_public_from_message(&self, message:&[u8]) -> HashData<N> {
+        self.expected_public_key(&H::hash_message(message))
     }
 
-    pub fn get_expected_public_from_message(&self, message:&[u8]) -> HashData {
-        let mut message_hash = Sha256::default();
-        Update::update(&mut message_hash, message);
-        self.clone().get_expected_public_from_hash(message_hash.finalize().into())
+    fn expected_public_key(&self, message_hash: &HashData<N>) -> HashData<N> {
+        public_key_from_chains::<N, W, H>(self.message_hashes.iter().chain(self.checksum_hashes.iter()).copied(), message_hash, &self.context)
     }
 
-    pub fn validate_self(self, message_hash: HashData) -> Result<ValidWotsPSignature, InvalidWotsPSignature> {
+    pub fn validate_self(self, message_hash: HashData<N>) -> Result<ValidWotsPSignature<N>, InvalidWotsPSignature<N>> {
         let calculated_key = self.clone().get_expected_public_from_hash(message_hash);
         match self.public_key == calculated_key {
             true => Ok(ValidWotsPSignature(self.public_key, self.context.clone())),
@@ -1,92 +1,175 @@
-#[cfg(test)]
-mod tests {
-    use crate::lib::{components::wots_plus::secret::{SeedPair, WotsPlus}, helpers::{hasher::{HashContext, hash_message}, random_generator::{Address, HASH_DATA_0, HashData, InnerKeyRole, RandomGeneratorSha256, byte_array_to_hex}}};
-    use std::collections::HashSet;
+use crate::lib::{components::wots_plus::{secret::{SeedPair, WotsPlus}, signature::{WotsPlusSignature, checksum_chains, message_chains}}, helpers::{encoding::DecodeError, hasher::{HashContext, hash_message}, random_generator::{Address, HASH_DATA_0, HashData, InnerKeyRole, RandomGeneratorSha256, byte_array_to_hex}}};
+use std::collections::HashSet;
+
+fn gen_private_public_from_seed(address: &Address) -> SeedPair {
+    let key:[u8;32] = [31u8;32];
+    let mut generator = RandomGeneratorSha256::new(key);
     
-    fn gen_private_public_from_seed(address: &Address) -> SeedPair {
-        let key:[u8;32] = [31u8;32];
-        let mut generator = RandomGeneratorSha256::new(key);
-        
-        let seeds = generator.get_keys::<2>(&address, InnerKeyRole::MessageKey); // Dummy role for test
-        
-        SeedPair(seeds[0], seeds[1])
-    }
+    let seeds = generator.get_keys::<2>(address, InnerKeyRole::MessageKey); // Dummy role for test
     
-    #[test]
-    fn test_true_random_key_pair() {
-        let mut hashset_of_seeds = HashSet::<HashData>::default();
-        
-        // Adding some past hashes, to see they are not repeated
-        hashset_of_seeds.insert (HASH_DATA_0); // TODO: Acctually add some from debug data
-        let basline_size = hashset_of_seeds.len();
-
-        for i in 0..100 {
-            let SeedPair(seed, public_seed) = WotsPlus::gen_true_random_keys();
-
-            hashset_of_seeds.insert(seed);
-            assert_eq!(i*2 + basline_size + 1, hashset_of_seeds.len(), "On iteration {}, seed {} repeated", i, byte_array_to_hex(&seed));
-            hashset_of_seeds.insert(public_seed);
-            assert_eq!(i*2 + basline_size + 2, hashset_of_seeds.len(), "On iteration {}, seed {} repeated", i, byte_array_to_hex(&public_seed));
-        }
+    SeedPair(seeds[0], seeds[1])
+}
+
+#[test]
+fn test_true_random_key_pair() {
+    let mut hashset_of_seeds = HashSet::<HashData>::default();
+    
+    // Adding some past hashes, to see they are not repeated
+    hashset_of_seeds.insert (HASH_DATA_0); // TODO: Acctually add some from debug data
+    let basline_size = hashset_of_seeds.len();
+
+    for i in 0..100 {
+        let SeedPair(seed, public_seed) = WotsPlus::<32>::gen_true_random_keys();
+
+        hashset_of_seeds.insert(seed);
+        assert_eq!(i*2 + basline_size + 1, hashset_of_seeds.len(), "On iteration {}, seed {} repeated", i, byte_array_to_hex(&seed));
+        hashset_of_seeds.insert(public_seed);
+        assert_eq!(i*2 + basline_size + 2, hashset_of_seeds.len(), "On iteration {}, seed {} repeated", i, byte_array_to_hex(&public_seed));
     }
+}
+
+
+#[test]
+fn test_public_key_stability() {
+    let address = Address { level: 1, position: 9000, ..Address::default() };
+    let SeedPair(seed, public_seed) = gen_private_public_from_seed(&address);
     
+    let secret1 = WotsPlus::<32>::new(seed, HashContext { public_seed, address: address.clone() });
+    let secret2 = WotsPlus::<32>::new(seed, HashContext { public_seed, address: address.clone() });
     
-    #[test]
-    fn test_public_key_stability() {
-        let address = Address {level: 1, position: 9000};
-        let SeedPair(seed, public_seed) = gen_private_public_from_seed(&address);
-        
-        let secret1 = WotsPlus::new(seed, HashContext { public_seed, address: address.clone() });
-        let secret2 = WotsPlus::new(seed, HashContext { public_seed, address: address.clone() });
-        
-        assert_eq!(secret1.generate_public_key().public_key, secret2.generate_public_key().public_key);
-    }
+    assert_eq!(secret1.generate_public_key().public_key, secret2.generate_public_key().public_key);
+}
+
+#[test]
+fn test_public_key_sensativity() {
+    let address = Address { level: 1, position: 9000, ..Address::default() };
+    let SeedPair(seed, public_seed) = gen_private_public_from_seed(&address);
     
-    #[test]
-    fn test_public_key_sensativity() {
-        let address = Address {level: 1, position: 9000};
-        let SeedPair(seed, public_seed) = gen_private_public_from_seed(&address);
-        
-        let mut address2 = address.clone();
-        address2.position = 9001;
-        
-        let secret1 = WotsPlus::new(seed, HashContext { public_seed, address: address.clone() });
-        // Knowingly providing the wrong address, for the test
-        let secret2 = WotsPlus::new(seed, HashContext { public_seed, address: address2.clone() });
-        
-        let pub1 = secret1.generate_public_key().public_key;
-        let pub2 = secret2.generate_public_key().public_key;
+    let mut address2 = address.clone();
+    address2.position = 9001;
+    
+    let secret1 = WotsPlus::<32>::new(seed, HashContext { public_seed, address: address.clone() });
+    // Knowingly providing the wrong address, for the test
+    let secret2 = WotsPlus::<32>::new(seed, HashContext { public_seed, address: address2.clone() });
+    
+    let pub1 = secret1.generate_public_key().public_key;
+    let pub2 = secret2.generate_public_key().public_key;
+    
+    let diff_bit = (0..32).map(|i| pub1[i] == pub2[i]).
+        fold(0, |acc, num| acc + (num as i32));
         
-        let diff_bit = (0..32).map(|i| pub1[i] == pub2[i]).
-            fold(0, |acc, num| acc + (num as i32));
-            
-        assert!(diff_bit <= 2);
-    }
+    assert!(diff_bit <= 2);
+}
 
-    #[test]
-    fn test_signature_on_message() {
-        const MESSAGE:&[u8] = "Hello from SPHINCS+ on rust".as_bytes();
-        const OTHER_MESSAGE:&[u8] = "Bye from SPHINCS+ on rust".as_bytes();
-        
-        let context = HashContext{public_seed: hash_message("This is my public_seed".as_bytes()), address: Address { level: 1, position: 19 }};
-        let wots = WotsPlus::new(hash_message("This is my secret_key".as_bytes()), context);
-        let public = wots.generate_public_key();
-        
-        let signature = wots.sign_message(&MESSAGE);
-        let other_signature = wots.sign_message(&OTHER_MESSAGE);
-        
-        let expected_pubkey1 = signature.get_expected_public_from_message(&MESSAGE);
-        let expected_pubkey2 = other_signature.get_expected_public_from_message(&OTHER_MESSAGE);
-        
-        assert_eq!(expected_pubkey2, expected_pubkey1);
+#[test]
+fn test_signature_on_message() {
+    const MESSAGE:&[u8] = "Hello from SPHINCS+ on rust".as_bytes();
+    const OTHER_MESSAGE:&[u8] = "Bye from SPHINCS+ on rust".as_bytes();
+    
+    let context = HashContext{public_seed: hash_message("This is my public_seed".as_bytes()), address: Address { level: 1, position: 19, ..Address::default() }};
+    let wots = WotsPlus::<32>::new(hash_message("This is my secret_key".as_bytes()), context);
+    let public = wots.generate_public_key();
+    
+    let signature = wots.sign_message(MESSAGE);
+    let other_signature = wots.sign_message(OTHER_MESSAGE);
+    
+    let expected_pubkey1 = signature.get_expected_public_from_message(MESSAGE);
+    let expected_pubkey2 = other_signature.get_expected_public_from_message(OTHER_MESSAGE);
+    
+    assert_eq!(expected_pubkey2, expected_pubkey1);
 
-        assert_eq!(public.public_key, expected_pubkey1);
+    assert_eq!(public.public_key, expected_pubkey1);
 
-        assert!(public.validate_message(MESSAGE, &signature));
-        assert!(public.validate_message(OTHER_MESSAGE, &other_signature));
-        
-        assert!(public.validate_message(OTHER_MESSAGE, &signature) == false);
+    assert!(public.validate_message(MESSAGE, &signature));
+    assert!(public.validate_message(OTHER_MESSAGE, &other_signature));
+    
+    assert!(!public.validate_message(OTHER_MESSAGE, &signature));
+}
+
+#[test]
+fn test_signature_on_message_short_hashes() {
+    const MESSAGE:&[u8] = "Hello from SPHINCS+ on rust".as_bytes();
+    const OTHER_MESSAGE:&[u8] = "Bye from SPHINCS+ on rust".as_bytes();
+
+    let context = HashContext{public_seed: [7u8;16], address: Address { level: 1, position: 19, ..Address::default() }};
+    let wots = WotsPlus::<16>::new([3u8;16], context);
+    let public = wots.generate_public_key();
+
+    let signature = wots.sign_message(MESSAGE);
+    assert_eq!(signature.message_hashes.len(), 16);
+    assert!(public.validate_message(MESSAGE, &signature));
+    assert!(!public.validate_message(OTHER_MESSAGE, &signature));
+}
+
+#[test]
+fn test_chain_counts() {
+    assert_eq!((message_chains(32, 256), checksum_chains(32, 256)), (32, 2));
+    assert_eq!((message_chains(32, 16), checksum_chains(32, 16)), (64, 3));
+    assert_eq!((message_chains(32, 4), checksum_chains(32, 4)), (128, 5));
+    assert_eq!((message_chains(16, 16), checksum_chains(16, 16)), (32, 3));
+}
+
+fn sign_and_validate<const W: usize>() {
+    const MESSAGE:&[u8] = "Hello from SPHINCS+ on rust".as_bytes();
+    const OTHER_MESSAGE:&[u8] = "Bye from SPHINCS+ on rust".as_bytes();
+
+    let context = HashContext{public_seed: [7u8;32], address: Address { level: 1, position: 19, ..Address::default() }};
+    let wots = WotsPlus::<32, W>::new([3u8;32], context);
+    let public = wots.generate_public_key();
+
+    let signature = wots.sign_message(MESSAGE);
+    assert_eq!(signature.message_hashes.len(), message_chains(32, W));
+    assert_eq!(signature.checksum_hashes.len(), checksum_chains(32, W));
+    assert!(public.validate_message(MESSAGE, &signature));
+    assert!(!public.validate_message(OTHER_MESSAGE, &signature));
+}
+
+#[test]
+fn test_signature_on_message_w16() {
+    sign_and_validate::<16>();
+}
+
+#[test]
+fn test_signature_on_message_w4() {
+    sign_and_validate::<4>();
+}
+
+#[test]
+fn test_checksum_extremes() {
+    // All zero and all max digits, the checksum chains must cover both ends
+    let context = HashContext{public_seed: [7u8;32], address: Address { level: 1, position: 19, ..Address::default() }};
+    let wots = WotsPlus::<32, 16>::new([3u8;32], context);
+    let public = wots.generate_public_key();
+
+    for hash in [[0u8; 32], [0xFFu8; 32]] {
+        let signature = wots.sign_hash(hash);
+        assert!(public.validate_hash(hash, &signature));
     }
+}
+
+// TODO: Test from bytes and to bytes
+
+#[test]
+fn test_signature_to_from_bytes() {
+    let context = HashContext{public_seed: [7u8;16], address: Address { level: 1, position: 19, ..Address::default() }};
+    let signature = WotsPlus::<16, 16>::new([3u8;16], context).sign_message(b"Serialize me");
+
+    let bytes = signature.to_bytes();
+    assert_eq!(bytes.len(), WotsPlusSignature::<16, 16>::size());
+    assert_eq!(bytes.len(), 50 + (32 + 3 + 1) * 16);
+    assert_eq!(WotsPlusSignature::<16, 16>::from_bytes(&bytes), Ok(signature));
+
+    assert_eq!(WotsPlusSignature::<16, 16>::from_bytes(&bytes[1..]), Err(DecodeError::WrongLength(bytes.len(), bytes.len() - 1)));
+}
+
+#[test]
+fn test_chain_lengths_are_base_2b_digits() {
+    use crate::lib::components::{slh_dsa::wots::base_2b, wots_plus::signature::chain_lengths};
 
-    // TODO: Test from bytes and to bytes
-}
\ No newline at end of file
+    let hash: HashData = hash_message(b"digits");
+    let digits = |lg_w: usize| base_2b(&hash, lg_w, 256 / lg_w).into_iter().map(|digit| digit as u8).collect::<Vec<u8>>();
+    assert_eq!(chain_lengths::<32, 4>(&hash).take(128).collect::<Vec<u8>>(), digits(2));
+    assert_eq!(chain_lengths::<32, 16>(&hash).take(64).collect::<Vec<u8>>(), digits(4));
+    assert_eq!(chain_lengths::<32, 256>(&hash).take(32).collect::<Vec<u8>>(), digits(8));
+    assert_eq!(chain_lengths::<32, 16>(&hash).count(), 67);
+}
@@ -0,0 +1,221 @@
+// The part of CBOR (RFC 8949) that COSE keys and messages need: integers, byte
+// and text strings, arrays, maps and tags. Encoding is always the shortest form,
+// decoding refuses indefinite lengths.
+
+pub const UNSIGNED: u8 = 0;
+pub const NEGATIVE: u8 = 1;
+pub const BYTES: u8 = 2;
+pub const TEXT: u8 = 3;
+pub const ARRAY: u8 = 4;
+pub const MAP: u8 = 5;
+pub const TAG: u8 = 6;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CborError {
+    UnexpectedType(u8, u8), // expected major type, got
+    Truncated,
+    IndefiniteLength,
+    IntegerOverflow,
+    BadText,
+    TrailingData(usize),
+    UnexpectedLength(u64, u64), // items expected in an array or map, got
+}
+
+pub fn head(major: u8, value: u64) -> Vec<u8> {
+    let major = major << 5;
+    match value {
+        0..=23 => vec![major | value as u8],
+        24..=0xff => vec![major | 24, value as u8],
+        0x100..=0xffff => [&[major | 25][..], &(value as u16).to_be_bytes()].concat(),
+        0x1_0000..=0xffff_ffff => [&[major | 26][..], &(value as u32).to_be_bytes()].concat(),
+        _ => [&[major | 27][..], &value.to_be_bytes()].concat(),
+    }
+}
+
+pub fn int(value: i64) -> Vec<u8> {
+    match value {
+        0.. => head(UNSIGNED, value as u64),
+        _ => head(NEGATIVE, !value as u64),
+    }
+}
+
+pub fn bytes(value: &[u8]) -> Vec<u8> {
+    [head(BYTES, value.len() as u64), value.to_vec()].concat()
+}
+
+pub fn text(value: &str) -> Vec<u8> {
+    [head(TEXT, value.len() as u64), value.as_bytes().to_vec()].concat()
+}
+
+pub fn array(items: &[Vec<u8>]) -> Vec<u8> {
+    [head(ARRAY, items.len() as u64), items.concat()].concat()
+}
+
+// Pairs are written in the given order, callers sort them for deterministic output
+pub fn map(pairs: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+    let mut out = head(MAP, pairs.len() as u64);
+    for (key, value) in pairs {
+        out.extend_from_slice(key);
+        out.extend_from_slice(value);
+    }
+    out
+}
+
+pub struct CborReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> CborReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], CborError> {
+        if self.bytes.len() < len {
+            return Err(CborError::Truncated);
+        }
+        let (out, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(out)
+    }
+
+    pub fn peek_major(&self) -> Option<u8> {
+        self.bytes.first().map(|byte| byte >> 5)
+    }
+
+    // (major type, argument)
+    pub fn head(&mut self) -> Result<(u8, u64), CborError> {
+        let first = self.take(1)?[0];
+        let value = match first & 0x1f {
+            info @ 0..=23 => info as u64,
+            24 => self.take(1)?[0] as u64,
+            25 => u16::from_be_bytes(self.take(2)?.try_into().expect("took 2 bytes")) as u64,
+            26 => u32::from_be_bytes(self.take(4)?.try_into().expect("took 4 bytes")) as u64,
+            27 => u64::from_be_bytes(self.take(8)?.try_into().expect("took 8 bytes")),
+            _ => return Err(CborError::IndefiniteLength),
+        };
+        Ok((first >> 5, value))
+    }
+
+    fn expect(&mut self, major: u8) -> Result<u64, CborError> {
+        match self.head()? {
+            (got, value) if got == major => Ok(value),
+            (got, _) => Err(CborError::UnexpectedType(major, got)),
+        }
+    }
+
+    pub fn int(&mut self) -> Result<i64, CborError> {
+        let (major, value) = self.head()?;
+        let value = i64::try_from(value).map_err(|_| CborError::IntegerOverflow)?;
+        match major {
+            UNSIGNED => Ok(value),
+            NEGATIVE => Ok(!value),
+            _ => Err(CborError::UnexpectedType(UNSIGNED, major)),
+        }
+    }
+
+    pub fn bytes(&mut self) -> Result<&'a [u8], CborError> {
+        let len = self.expect(BYTES)?;
+        self.take(usize::try_from(len).map_err(|_| CborError::Truncated)?)
+    }
+
+    pub fn text(&mut self) -> Result<&'a str, CborError> {
+        let len = self.expect(TEXT)?;
+        let raw = self.take(usize::try_from(len).map_err(|_| CborError::Truncated)?)?;
+        std::str::from_utf8(raw).map_err(|_| CborError::BadText)
+    }
+
+    // These return the number of items, which the caller reads next
+    pub fn array(&mut self) -> Result<u64, CborError> {
+        self.expect(ARRAY)
+    }
+
+    pub fn map(&mut self) -> Result<u64, CborError> {
+        self.expect(MAP)
+    }
+
+    pub fn tag(&mut self) -> Result<u64, CborError> {
+        self.expect(TAG)
+    }
+
+    // Skips one complete data item of any type. Nested items are counted instead
+    // of recursed into, so deep nesting cannot overflow the stack.
+    pub fn skip(&mut self) -> Result<(), CborError> {
+        let mut pending: u64 = 1;
+        while pending > 0 {
+            pending -= 1;
+            let (major, value) = self.head()?;
+            let items = match major {
+                BYTES | TEXT => { self.take(usize::try_from(value).map_err(|_| CborError::Truncated)?)?; 0 }
+                ARRAY => value,
+                MAP => value.checked_mul(2).ok_or(CborError::IntegerOverflow)?,
+                TAG => 1,
+                _ => 0,
+            };
+            pending = pending.checked_add(items).ok_or(CborError::IntegerOverflow)?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(&self) -> Result<(), CborError> {
+        match self.bytes.len() {
+            0 => Ok(()),
+            left => Err(CborError::TrailingData(left)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+
+    use super::*;
+
+    #[test]
+    fn test_rfc_8949_examples() {
+        // Appendix A of RFC 8949
+        assert_eq!(int(0), hex!("00"));
+        assert_eq!(int(23), hex!("17"));
+        assert_eq!(int(24), hex!("1818"));
+        assert_eq!(int(1000), hex!("1903e8"));
+        assert_eq!(int(1000000), hex!("1a000f4240"));
+        assert_eq!(int(-1), hex!("20"));
+        assert_eq!(int(-1000), hex!("3903e7"));
+        assert_eq!(bytes(&hex!("01020304")), hex!("4401020304"));
+        assert_eq!(text("IETF"), hex!("6449455446"));
+        assert_eq!(array(&[int(1), array(&[int(2), int(3)])]), hex!("8201820203"));
+        assert_eq!(map(&[(int(1), int(2)), (int(3), int(4))]), hex!("a201020304"));
+
+        let mut reader = CborReader::new(&hex!("3903e7 4401020304 6449455446"));
+        assert_eq!(reader.int(), Ok(-1000));
+        assert_eq!(reader.bytes(), Ok(&hex!("01020304")[..]));
+        assert_eq!(reader.text(), Ok("IETF"));
+        assert_eq!(reader.finish(), Ok(()));
+    }
+
+    #[test]
+    fn test_reader_errors() {
+        assert_eq!(CborReader::new(&hex!("5f")).bytes(), Err(CborError::IndefiniteLength));
+        assert_eq!(CborReader::new(&hex!("44010203")).bytes(), Err(CborError::Truncated));
+        assert_eq!(CborReader::new(&hex!("6449455446")).bytes(), Err(CborError::UnexpectedType(BYTES, TEXT)));
+        assert_eq!(CborReader::new(&hex!("1bffffffffffffffff")).int(), Err(CborError::IntegerOverflow));
+
+        let mut reader = CborReader::new(&hex!("a2 01 820203 03 6449455446 00"));
+        assert_eq!(reader.skip(), Ok(()));
+        assert_eq!(reader.finish(), Err(CborError::TrailingData(1)));
+    }
+
+    #[test]
+    fn test_skip_hostile_items() {
+        // 2^64 - 1 pairs are more items than a u64 counts
+        assert_eq!(CborReader::new(&hex!("bbffffffffffffffff")).skip(), Err(CborError::IntegerOverflow));
+
+        // A million nested arrays, the innermost missing its item
+        let nested = vec![0x81u8; 1_000_000];
+        assert_eq!(CborReader::new(&nested).skip(), Err(CborError::Truncated));
+        let complete = [&nested[..], &hex!("00 01")[..]].concat();
+        let mut reader = CborReader::new(&complete);
+        assert_eq!(reader.skip(), Ok(()));
+        assert_eq!(reader.finish(), Err(CborError::TrailingData(1)));
+    }
+}
@@ -0,0 +1,154 @@
+// The small part of DER and PEM that PKCS#8 and SubjectPublicKeyInfo keys need:
+// definite, minimal lengths and a handful of universal tags
+#[cfg(feature = "pem")]
+use base64::{Engine, engine::general_purpose::STANDARD};
+
+pub const INTEGER: u8 = 0x02;
+pub const BIT_STRING: u8 = 0x03;
+pub const OCTET_STRING: u8 = 0x04;
+pub const OBJECT_IDENTIFIER: u8 = 0x06;
+pub const SEQUENCE: u8 = 0x30;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DerError {
+    UnexpectedTag(u8, u8), // expected, got
+    BadLength,
+    TrailingData(usize),
+    MissingPemLabel(&'static str),
+    BadBase64,
+}
+
+pub fn encode(tag: u8, content: &[u8]) -> Vec<u8> {
+    let len = content.len();
+    let mut out = vec![tag];
+    match len {
+        0..=0x7f => out.push(len as u8),
+        0x80..=0xff => out.extend_from_slice(&[0x81, len as u8]),
+        _ => {
+            assert!(len <= 0xffff, "DER values are at most 64 KiB here");
+            out.push(0x82);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+// Walks the elements of a DER value one by one
+pub struct DerReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> DerReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.bytes.first().copied()
+    }
+
+    // Any element, as (tag, content)
+    pub fn any(&mut self) -> Result<(u8, &'a [u8]), DerError> {
+        let [tag, first, rest @ ..] = self.bytes else {
+            return Err(DerError::BadLength);
+        };
+        let (len, rest) = match *first {
+            0..=0x7f => (*first as usize, rest),
+            0x81 => match rest {
+                [len, rest @ ..] if *len >= 0x80 => (*len as usize, rest),
+                _ => return Err(DerError::BadLength),
+            },
+            0x82 => match rest {
+                [high, low, rest @ ..] if *high > 0 => (u16::from_be_bytes([*high, *low]) as usize, rest),
+                _ => return Err(DerError::BadLength),
+            },
+            _ => return Err(DerError::BadLength),
+        };
+        if rest.len() < len {
+            return Err(DerError::BadLength);
+        }
+        self.bytes = &rest[len..];
+        Ok((*tag, &rest[..len]))
+    }
+
+    pub fn expect(&mut self, tag: u8) -> Result<&'a [u8], DerError> {
+        match self.any()? {
+            (got, content) if got == tag => Ok(content),
+            (got, _) => Err(DerError::UnexpectedTag(tag, got)),
+        }
+    }
+
+    pub fn finish(&self) -> Result<(), DerError> {
+        match self.bytes.len() {
+            0 => Ok(()),
+            left => Err(DerError::TrailingData(left)),
+        }
+    }
+}
+
+// Reads exactly one element that spans all of the input
+pub fn decode_single(bytes: &[u8], tag: u8) -> Result<&[u8], DerError> {
+    let mut reader = DerReader::new(bytes);
+    let content = reader.expect(tag)?;
+    reader.finish()?;
+    Ok(content)
+}
+
+// RFC 7468 text encoding, 64 characters per line
+#[cfg(feature = "pem")]
+pub fn to_pem(label: &str, der: &[u8]) -> String {
+    let encoded = STANDARD.encode(der);
+    let mut out = format!("-----BEGIN {label}-----\n");
+    for line in encoded.as_bytes().chunks(64) {
+        out.push_str(std::str::from_utf8(line).expect("base64 is ASCII"));
+        out.push('\n');
+    }
+    out.push_str(&format!("-----END {label}-----\n"));
+    out
+}
+
+#[cfg(feature = "pem")]
+pub fn from_pem(label: &'static str, pem: &str) -> Result<Vec<u8>, DerError> {
+    let begin = format!("-----BEGIN {label}-----");
+    let end = format!("-----END {label}-----");
+    let start = pem.find(&begin).ok_or(DerError::MissingPemLabel(label))? + begin.len();
+    let stop = pem[start..].find(&end).ok_or(DerError::MissingPemLabel(label))? + start;
+
+    let body: String = pem[start..stop].chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    STANDARD.decode(body).map_err(|_| DerError::BadBase64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lengths() {
+        for len in [0, 1, 127, 128, 255, 256, 1000] {
+            let content = vec![7u8; len];
+            let encoded = encode(OCTET_STRING, &content);
+            assert_eq!(decode_single(&encoded, OCTET_STRING), Ok(content.as_slice()));
+        }
+        assert_eq!(encode(OCTET_STRING, &[0u8; 128])[..3], [OCTET_STRING, 0x81, 0x80]);
+        assert_eq!(encode(OCTET_STRING, &[0u8; 256])[..4], [OCTET_STRING, 0x82, 0x01, 0x00]);
+
+        // Lengths that are not minimal, indefinite or cut off are refused
+        assert_eq!(decode_single(&[OCTET_STRING, 0x81, 0x05, 0, 0, 0, 0, 0], OCTET_STRING), Err(DerError::BadLength));
+        assert_eq!(decode_single(&[OCTET_STRING, 0x80, 0, 0], OCTET_STRING), Err(DerError::BadLength));
+        assert_eq!(decode_single(&[OCTET_STRING, 0x03, 0], OCTET_STRING), Err(DerError::BadLength));
+        assert_eq!(decode_single(&[OCTET_STRING, 0x01, 0, 0], OCTET_STRING), Err(DerError::TrailingData(1)));
+        assert_eq!(decode_single(&[INTEGER, 0x01, 0], OCTET_STRING), Err(DerError::UnexpectedTag(OCTET_STRING, INTEGER)));
+    }
+
+    #[cfg(feature = "pem")]
+    #[test]
+    fn test_pem() {
+        let der: Vec<u8> = (0..100).collect();
+        let pem = to_pem("PUBLIC KEY", &der);
+        assert!(pem.starts_with("-----BEGIN PUBLIC KEY-----\n"));
+        assert!(pem.lines().all(|line| line.len() <= 64));
+        assert_eq!(from_pem("PUBLIC KEY", &pem), Ok(der));
+        assert_eq!(from_pem("PRIVATE KEY", &pem), Err(DerError::MissingPemLabel("PRIVATE KEY")));
+    }
+}
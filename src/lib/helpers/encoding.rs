@@ -0,0 +1,43 @@
+// Byte decoding shared by the keys and signatures of the custom scheme
+use crate::lib::helpers::random_generator::HashData;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    WrongLength(usize, usize), // expected, got
+    UnknownAddressType(u32),
+    UnsupportedVersion(u8),
+    WrongParameterSet(u8, u8), // expected, got
+    WrongShape([u8; 7], [u8; 7]), // expected, got, for keys that are not a named set
+    KeyMismatch, // the stored PK.root is not the root of the stored seeds
+    IndexOutOfRange(u128), // a hypertree index with bits above LAYERS * TREE_HEIGHT
+}
+
+// Reads consecutive fields out of an input of a known, fixed size. The length is
+// checked once up front, so the reads themselves can not run out of bytes.
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8], expected: usize) -> Result<Self, DecodeError> {
+        match bytes.len() == expected {
+            true => Ok(Self { bytes, offset: 0 }),
+            false => Err(DecodeError::WrongLength(expected, bytes.len()))
+        }
+    }
+
+    pub fn take(&mut self, len: usize) -> &'a [u8] {
+        let out = &self.bytes[self.offset..self.offset + len];
+        self.offset += len;
+        out
+    }
+
+    pub fn array<const LEN: usize>(&mut self) -> [u8; LEN] {
+        self.take(LEN).try_into().expect("take returns exactly LEN bytes")
+    }
+
+    pub fn hashes<const N: usize>(&mut self, count: usize) -> Vec<HashData<N>> {
+        (0..count).map(|_| self.array()).collect()
+    }
+}
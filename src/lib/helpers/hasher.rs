@@ -1,66 +1,218 @@
+use std::fmt::Debug;
+
+use hmac::{Hmac, Mac};
 use sha2::{Sha256, Digest, digest::Update};
+use sha3::{Shake256, digest::{ExtendableOutput, XofReader}};
 
-use crate::lib::helpers::random_generator::{Address, HASH_DATA_0, HashData};
+use crate::lib::helpers::{encoding::{DecodeError, Reader}, random_generator::{Address, ADDRESS_SIZE, HashData}};
 
 #[derive(Debug, PartialEq, Clone)]
-pub struct HashContext {
-    pub public_seed: HashData,
+pub struct HashContext<const N: usize = 32> {
+    pub public_seed: HashData<N>,
     pub address: Address
 }
 
-impl HashContext{
-    pub fn to_bytes(&self) -> [u8;50] {
-        let mut out = [0u8;50];
-        out[..32].copy_from_slice(&self.public_seed);
-        out[32..].copy_from_slice(&self.address.to_bytes());
-        out
+impl<const N: usize> HashContext<N> {
+    pub const fn size() -> usize {
+        N + ADDRESS_SIZE
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [self.public_seed.as_slice(), &self.address.to_bytes()].concat()
     }
 
-    pub fn from_bytes(bytes:[u8;50]) -> Self {
-        let pubkey = bytes[0..32].try_into().expect("Unexpected byte size provided");
-        let address_bytes = bytes[32..].try_into().expect("Unexpected byte size provided");
-        let address = Address::from_bytes(address_bytes);
-        Self { public_seed: pubkey, address }
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes, Self::size())?;
+        let public_seed = reader.array();
+        let address = Address::from_bytes(reader.array())?;
+        Ok(Self { public_seed, address })
     }
 }
 
-impl Default for HashContext {
+impl<const N: usize> Default for HashContext<N> {
     fn default() -> Self {
-        Self{public_seed: HASH_DATA_0, address: Address{level: 0, position: 0}}
+        Self{public_seed: [0u8; N], address: Address::default()}
+    }
+}
+// A hash suite picks the hash function behind every primitive of the scheme.
+// Only `hash` has to be provided, everything else is built on top of it.
+// N is the output length in bytes, and can be 16, 24 or 32.
+pub trait HashSuite: Clone + Copy + Debug + PartialEq + Default {
+    // The hash family as it appears in parameter set names
+    const NAME: &'static str;
+
+    fn hash<const N: usize>(parts: &[&[u8]]) -> HashData<N>;
+
+    // Walks `steps` steps of a hash chain from step `start`, every step with its own hash address
+    fn chain<const N: usize>(to_hash: HashData<N>, start: u8, steps: u8, context: &HashContext<N>) -> HashData<N> {
+        let mut step_context = context.clone();
+        (start as u32..start as u32 + steps as u32).fold(to_hash, |acc, step| {
+            step_context.address.hash = step;
+            Self::hash_with_context(&[acc], &step_context)
+        })
+    }
+
+    fn repeat_hash<const N: usize>(to_hash: HashData<N>, times_to_repeat: u8, context: &HashContext<N>) -> HashData<N> {
+        Self::chain(to_hash, 0, times_to_repeat, context)
+    }
+
+    fn complement_hash<const N: usize>(to_hash: HashData<N>, times_repeated: u8, context: &HashContext<N>) -> HashData<N> {
+        Self::chain(to_hash, times_repeated, 255-times_repeated, context)
+    }
+
+    // Hashes every part handed to `feed`, in order, as `hash` would their concatenation.
+    // The default buffers the parts, suites that can stream override it so that
+    // verification does not allocate.
+    fn hash_streamed<const N: usize>(write: impl FnOnce(&mut dyn FnMut(&[u8]))) -> HashData<N> {
+        let mut buffer = Vec::new();
+        write(&mut |part| buffer.extend_from_slice(part));
+        Self::hash(&[&buffer])
+    }
+
+    // The tweakable hash, the context (public seed and address) is appended to the hashes
+    fn hash_with_context<const N: usize>(hashes: &[HashData<N>], context: &HashContext<N>) -> HashData<N> {
+        Self::hash_iter_with_context(hashes.iter().copied(), context)
+    }
+
+    // The same, for hashes that are computed while they are hashed
+    fn hash_iter_with_context<const N: usize>(hashes: impl IntoIterator<Item = HashData<N>>, context: &HashContext<N>) -> HashData<N> {
+        Self::hash_streamed(|feed| {
+            hashes.into_iter().for_each(|hash| feed(&hash));
+            feed(&context.public_seed);
+            feed(&context.address.to_bytes());
+        })
+    }
+
+    fn hash_array<const N: usize>(hashes: &[HashData<N>]) -> HashData<N> {
+        let parts: Vec<&[u8]> = hashes.iter().map(|h| h.as_slice()).collect();
+        Self::hash(&parts)
+    }
+
+    fn hash_message<const N: usize>(message: &[u8]) -> HashData<N> {
+        Self::hash(&[message])
+    }
+
+    // PRF_msg: the per signature randomizer R, keyed by SK.prf
+    fn message_prf<const N: usize>(prf: &HashData<N>, opt_rand: &HashData<N>, message: &[u8]) -> HashData<N> {
+        Self::hash(&[prf, opt_rand, message])
+    }
+
+    // MGF1 from RFC 8017, over 32 byte blocks
+    fn mgf1(seed: &[u8], out_len: usize) -> Vec<u8> {
+        let mut out = vec![0u8; out_len];
+        Self::mgf1_into(&[seed], &mut out);
+        out
+    }
+
+    // MGF1 of the concatenated seed parts, filling `out`
+    fn mgf1_into(seed: &[&[u8]], out: &mut [u8]) {
+        for (counter, block) in out.chunks_mut(32).enumerate() {
+            let hash = Self::hash_streamed::<32>(|feed| {
+                seed.iter().for_each(|part| feed(part));
+                feed(&(counter as u32).to_be_bytes());
+            });
+            block.copy_from_slice(&hash[..block.len()]);
+        }
+    }
+
+    // H_msg: expands the message to `out_len` bytes, keyed by the randomizer, the public seed and the root
+    fn digest_message<const N: usize>(randomizer: &HashData<N>, public_seed: &HashData<N>, root: &HashData<N>, message: &[u8], out_len: usize) -> Vec<u8> {
+        let mut out = vec![0u8; out_len];
+        Self::digest_message_into(randomizer, public_seed, root, &[message], &mut out);
+        out
+    }
+
+    // H_msg of the concatenated message parts, filling `out`
+    fn digest_message_into<const N: usize>(randomizer: &HashData<N>, public_seed: &HashData<N>, root: &HashData<N>, message: &[&[u8]], out: &mut [u8]) {
+        let inner = Self::hash_streamed::<32>(|feed| {
+            [randomizer.as_slice(), public_seed, root].into_iter().chain(message.iter().copied()).for_each(feed);
+        });
+        Self::mgf1_into(&[randomizer, public_seed, &inner], out);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Sha2Suite;
+
+impl HashSuite for Sha2Suite {
+    const NAME: &'static str = "SHA2";
+
+    // SHA-256, truncated to N bytes
+    fn hash<const N: usize>(parts: &[&[u8]]) -> HashData<N> {
+        const { assert!(N <= 32, "SHA-256 can not produce more than 32 bytes") };
+        let mut hasher = Sha256::default();
+        parts.iter().for_each(|part| Update::update(&mut hasher, part));
+        hasher.finalize()[..N].try_into().expect("N is checked above")
+    }
+
+    fn hash_streamed<const N: usize>(write: impl FnOnce(&mut dyn FnMut(&[u8]))) -> HashData<N> {
+        const { assert!(N <= 32, "SHA-256 can not produce more than 32 bytes") };
+        let mut hasher = Sha256::default();
+        write(&mut |part| Update::update(&mut hasher, part));
+        hasher.finalize()[..N].try_into().expect("N is checked above")
+    }
+
+    // A prefixed SHA-256 is open to length extension, so PRF_msg is HMAC-SHA-256
+    fn message_prf<const N: usize>(prf: &HashData<N>, opt_rand: &HashData<N>, message: &[u8]) -> HashData<N> {
+        const { assert!(N <= 32, "SHA-256 can not produce more than 32 bytes") };
+        let mut mac = Hmac::<Sha256>::new_from_slice(prf).expect("HMAC accepts keys of any size");
+        Mac::update(&mut mac, opt_rand);
+        Mac::update(&mut mac, message);
+        mac.finalize().into_bytes()[..N].try_into().expect("N is checked above")
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Shake256Suite;
+
+fn shake(write: impl FnOnce(&mut dyn FnMut(&[u8])), out: &mut [u8]) {
+    let mut hasher = Shake256::default();
+    write(&mut |part| Update::update(&mut hasher, part));
+    hasher.finalize_xof().read(out);
+}
+
+impl HashSuite for Shake256Suite {
+    const NAME: &'static str = "SHAKE";
+
+    fn hash<const N: usize>(parts: &[&[u8]]) -> HashData<N> {
+        Self::hash_streamed(|feed| parts.iter().for_each(|part| feed(part)))
+    }
+
+    fn hash_streamed<const N: usize>(write: impl FnOnce(&mut dyn FnMut(&[u8]))) -> HashData<N> {
+        let mut out = [0u8; N];
+        shake(write, &mut out);
+        out
+    }
+
+    // SHAKE256 is an XOF, no need for MGF1
+    fn digest_message_into<const N: usize>(randomizer: &HashData<N>, public_seed: &HashData<N>, root: &HashData<N>, message: &[&[u8]], out: &mut [u8]) {
+        shake(|feed| {
+            [randomizer.as_slice(), public_seed, root].into_iter().chain(message.iter().copied()).for_each(feed);
+        }, out);
+    }
+}
+
+// The free functions below use the default SHA2 suite
 pub fn repeat_hash(to_hash: HashData, times_to_repeat: u8, context: &HashContext) -> [u8;32] {
-    (0..times_to_repeat).fold(to_hash, |acc, _| {
-        let mut hashed = Sha256::default();
-        
-        Update::update(&mut hashed, &acc);
-        Update::update(&mut hashed, &context.to_bytes());
-        
-        hashed.finalize().into()
-    })
+    Sha2Suite::repeat_hash(to_hash, times_to_repeat, context)
 }
 
 pub fn complement_hash(to_hash: HashData, times_repeated: u8, context: &HashContext) -> [u8;32] {
-    repeat_hash(to_hash, 255-times_repeated, context)
+    Sha2Suite::complement_hash(to_hash, times_repeated, context)
 }
 
 pub fn hash_array(hashes: &[HashData]) -> HashData{
-    let mut hasher = Sha256::default();
-    hashes.iter().for_each(|h| Update::update(&mut hasher, h));
-    hasher.finalize().into()
+    Sha2Suite::hash_array(hashes)
 }
 
 pub fn hash_message(message: &[u8]) -> HashData {
-    let mut message_hush = Sha256::default();
-    Update::update(&mut message_hush, message);
-
-    message_hush.finalize().into()
+    Sha2Suite::hash_message(message)
 }
 #[cfg(test)]
 mod tests {
     use rand;
     use super::*;
-    use crate::lib::helpers::random_generator::{Address, InnerKeyRole, RandomGeneratorSha256};
+    use crate::lib::helpers::random_generator::{Address, AddressType, InnerKeyRole, RandomGeneratorSha256};
 
     #[test]
     fn test_hash_text() {
@@ -72,10 +224,27 @@ mod tests {
 
         assert_ne!(msg1_hash, msg2_hash);
     }
+    #[test]
+    fn test_shake_suite() {
+        // SHAKE256 of the empty string, first 32 bytes
+        assert_eq!(Shake256Suite::hash_message::<32>(b""), hex_literal::hex!("46b9dd2b0ba88d13233b3feb743eeb243fcd52ea62b81b82b50c27646ed5762f"));
+        assert_eq!(Shake256Suite::hash_array(&[[1u8;32], [2u8;32]]), Shake256Suite::hash::<32>(&[&[1u8;32], &[2u8;32]]));
+        assert_ne!(Shake256Suite::hash_message::<32>(b"abc"), Sha2Suite::hash_message::<32>(b"abc"));
+    }
+
+    #[test]
+    fn test_suite_repeat_hash() {
+        let context = HashContext { public_seed: [8;32], address: Address { level: 10, position: 15, ..Address::default() } };
+
+        let shake_repeat = Shake256Suite::repeat_hash([0;32], 2, &context);
+        assert_eq!(Shake256Suite::complement_hash(shake_repeat, 2, &context), Shake256Suite::repeat_hash([0;32], 255, &context));
+        assert_ne!(shake_repeat, repeat_hash([0;32], 2, &context));
+    }
+
     #[test]
     fn test_repeated_hash_same_when_zero(){
         let initial_random:  [u8;32] = rand::random();
-        let context = HashContext { public_seed: [8;32], address: Address { level: 10, position: 15 } };
+        let context = HashContext { public_seed: [8;32], address: Address { level: 10, position: 15, ..Address::default() } };
         let hashed_random = repeat_hash(initial_random, 0, &context);
         
         assert_eq!(initial_random, hashed_random);
@@ -84,19 +253,66 @@ mod tests {
     #[test]
     fn test_complement(){
         let initial_random:  [u8;32] = [0;32];
-        let context = HashContext { public_seed: [8;32], address: Address { level: 10, position: 15 } };
+        let context = HashContext { public_seed: [8;32], address: Address { level: 10, position: 15, ..Address::default() } };
         
         let hashed_random = repeat_hash(initial_random, 2, &context);
         let simulated_complete = complement_hash(initial_random, 253, &context);
         
         assert_ne!(initial_random, hashed_random);
-        assert_eq!(hashed_random, simulated_complete);
+        // Every step of the chain has its own hash address, so the same number of steps
+        // from another starting point gives another result
+        assert_ne!(hashed_random, simulated_complete);
+        assert_eq!(simulated_complete, Sha2Suite::chain(initial_random, 253, 2, &context));
+    }
+
+    #[test]
+    fn test_digest_message() {
+        let digest = Sha2Suite::digest_message(&[1u8;16], &[2u8;16], &[3u8;16], b"message", 70);
+        assert_eq!(digest.len(), 70);
+        // A prefix of a longer output
+        assert_eq!(digest[..40], Sha2Suite::digest_message(&[1u8;16], &[2u8;16], &[3u8;16], b"message", 40));
+        assert_ne!(digest, Sha2Suite::digest_message(&[1u8;16], &[2u8;16], &[4u8;16], b"message", 70));
+        assert_ne!(digest, Sha2Suite::digest_message(&[0u8;16], &[2u8;16], &[3u8;16], b"message", 70));
+
+        let shake_digest = Shake256Suite::digest_message(&[1u8;16], &[2u8;16], &[3u8;16], b"message", 70);
+        assert_eq!(shake_digest.len(), 70);
+        assert_ne!(shake_digest, digest);
+    }
+
+    #[test]
+    fn test_message_prf() {
+        let randomizer = Sha2Suite::message_prf(&[1u8; 16], &[2u8; 16], b"message");
+        assert_eq!(randomizer, Sha2Suite::message_prf(&[1u8; 16], &[2u8; 16], b"message"));
+        assert_ne!(randomizer, Sha2Suite::message_prf(&[3u8; 16], &[2u8; 16], b"message"));
+        assert_ne!(randomizer, Sha2Suite::message_prf(&[1u8; 16], &[3u8; 16], b"message"));
+        assert_ne!(randomizer, Sha2Suite::message_prf(&[1u8; 16], &[2u8; 16], b"massage"));
+        assert_ne!(Shake256Suite::message_prf(&[1u8; 16], &[2u8; 16], b"message"), randomizer);
+    }
+
+    #[test]
+    fn test_mgf1_blocks() {
+        let out = Sha2Suite::mgf1(b"seed", 64);
+        assert_eq!(out[..32], Sha2Suite::hash::<32>(&[b"seed", &0u32.to_be_bytes()]));
+        assert_eq!(out[32..], Sha2Suite::hash::<32>(&[b"seed", &1u32.to_be_bytes()]));
+    }
+
+    #[test]
+    fn test_hash_with_context() {
+        let context = HashContext { public_seed: [8;32], address: Address { level: 10, position: 15, ..Address::default() } };
+        let other_type = HashContext { public_seed: [8;32], address: context.address.with_type(AddressType::Tree) };
+        let mut other_chain = context.clone();
+        other_chain.address.chain = 1;
+
+        let hashed = Sha2Suite::hash_with_context(&[[1u8;32]], &context);
+        assert_eq!(hashed, Sha2Suite::hash(&[&[1u8;32], &context.to_bytes()]));
+        assert_ne!(hashed, Sha2Suite::hash_with_context(&[[1u8;32]], &other_type));
+        assert_ne!(hashed, Sha2Suite::hash_with_context(&[[1u8;32]], &other_chain));
     }
     
     #[test]
     fn test_to_target(){
         let initial_random:  [u8;32] = [0;32];
-        let context = HashContext { public_seed: [8;32], address: Address { level: 10, position: 15 } };
+        let context = HashContext { public_seed: [8;32], address: Address { level: 10, position: 15, ..Address::default() } };
         
         let hashed_random1 = repeat_hash(initial_random, 2, &context);
         let simulated_complete1 = complement_hash(hashed_random1, 2, &context);
@@ -111,7 +327,7 @@ mod tests {
     #[test]
     fn test_target(){
         let initial_random:  [u8;32] = [0;32];
-        let context = HashContext { public_seed: [8;32], address: Address { level: 10, position: 15 } };
+        let context = HashContext { public_seed: [8;32], address: Address { level: 10, position: 15, ..Address::default() } };
         
         let target_hash = repeat_hash(initial_random, 255, &context);
         let hashed_random = repeat_hash(initial_random, 2, &context);
@@ -124,7 +340,7 @@ mod tests {
     #[test]
     fn test_hash_vector() {
         let mut generator = RandomGeneratorSha256::new([3;32]);
-        let hashes = generator.get_keys::<4>(&Address{level: 3, position: 9}, InnerKeyRole::MessageKey);
+        let hashes = generator.get_keys::<4>(&Address { level: 3, position: 9, ..Address::default() }, InnerKeyRole::MessageKey);
         let out1 = hash_array(&hashes);
         
         let new_hashes = vec![ hashes[1], hashes[0], hashes[2], hashes[3], ];
@@ -142,9 +358,9 @@ mod tests {
     fn test_change_vector_seed() {
         let mut random_initial = RandomGeneratorSha256::new([3;32]);
         
-        let address = &Address { level: 10, position: 15 };
-        let to_hash = random_initial.get_keys::<1>(&address, InnerKeyRole::MessageKey)[0];
-        let to_hash_clone = to_hash.clone();
+        let address = &Address { level: 10, position: 15, ..Address::default() };
+        let to_hash = random_initial.get_keys::<1>(address, InnerKeyRole::MessageKey)[0];
+        let to_hash_clone = to_hash;
         
         let context1 = HashContext { public_seed: [8;32], address: address.clone() };
         let repeat1 = repeat_hash(to_hash, 5, &context1);
@@ -160,10 +376,10 @@ mod tests {
     fn test_change_vector_position() {
         let mut random_initial = RandomGeneratorSha256::new([3;32]);
         
-        let address1 = &Address { level: 10, position: 15 };
-        let address2 = &Address { level: 10, position: 16 };
-        let to_hash = random_initial.get_keys::<1>(&address1, InnerKeyRole::MessageKey)[0];
-        let to_hash_clone = to_hash.clone();
+        let address1 = &Address { level: 10, position: 15, ..Address::default() };
+        let address2 = &Address { level: 10, position: 16, ..Address::default() };
+        let to_hash = random_initial.get_keys::<1>(address1, InnerKeyRole::MessageKey)[0];
+        let to_hash_clone = to_hash;
         
         let context1 = HashContext { public_seed: [8;32], address: address1.clone() };
         let repeat1 = repeat_hash(to_hash, 5, &context1);
@@ -180,10 +396,10 @@ mod tests {
     fn test_change_vector_level() {
         let mut random_initial = RandomGeneratorSha256::new([3;32]);
         
-        let address1 = &Address { level: 10, position: 15 };
-        let address2 = &Address { level: 11, position: 15 };
+        let address1 = &Address { level: 10, position: 15, ..Address::default() };
+        let address2 = &Address { level: 11, position: 15, ..Address::default() };
         let to_hash = random_initial.get_keys::<1>(address1, InnerKeyRole::MessageKey)[0];
-        let to_hash_clone = to_hash.clone();
+        let to_hash_clone = to_hash;
         
         let context1 = HashContext { public_seed: [8;32], address: address1.clone() };
         let repeat1 = repeat_hash(to_hash, 5, &context1);
@@ -200,11 +416,11 @@ mod tests {
     fn test_same_vector_address_and_seed() {
         let mut random_initial = RandomGeneratorSha256::new([3;32]);
         
-        let address1 = &Address { level: 10, position: 15 };
-        let address2 = &Address { level: 10, position: 15 };
+        let address1 = &Address { level: 10, position: 15, ..Address::default() };
+        let address2 = &Address { level: 10, position: 15, ..Address::default() };
 
         let to_hash = random_initial.get_keys::<1>(address1, InnerKeyRole::MessageKey)[0];
-        let to_hash_clone = to_hash.clone();
+        let to_hash_clone = to_hash;
         
         let context1 = HashContext { public_seed: [8;32], address: address1.clone() };
         let repeat1 = repeat_hash(to_hash, 5, &context1);
@@ -220,14 +436,14 @@ mod tests {
     #[test]
     fn test_context_to_from_bytes() {
 
-        let context = HashContext { public_seed: [9u8;32], address: Address{level: 11, position: 64} };
-        let other_context = HashContext { public_seed: [10u8;32], address: Address{level: 12, position: 64} };
+        let context = HashContext { public_seed: [9u8;32], address: Address { level: 11, position: 64, ..Address::default() } };
+        let other_context = HashContext { public_seed: [10u8;32], address: Address { level: 12, position: 64, ..Address::default() } };
 
         let bytes_dump = context.to_bytes();
         let other_bytes_dump = other_context.to_bytes();
 
-        let new_context = HashContext::from_bytes(bytes_dump);
-        let new_other_context = HashContext::from_bytes(other_bytes_dump);
+        let new_context = HashContext::<32>::from_bytes(&bytes_dump).unwrap();
+        let new_other_context = HashContext::<32>::from_bytes(&other_bytes_dump).unwrap();
 
         assert_eq!(new_context.public_seed, context.public_seed);
         assert_eq!(new_context.address.level, context.address.level);
@@ -235,4 +451,22 @@ mod tests {
         assert_ne!(new_context.public_seed, new_other_context.public_seed);
         assert_ne!(new_context.address.level, new_other_context.address.level);
     }
+
+    #[test]
+    fn test_short_hashes() {
+        let full = Sha2Suite::hash_message::<32>(b"short");
+        let short = Sha2Suite::hash_message::<16>(b"short");
+        assert_eq!(short, full[..16]);
+        assert_eq!(Shake256Suite::hash_message::<24>(b"short"), Shake256Suite::hash_message::<32>(b"short")[..24]);
+
+        let context = HashContext::<16> { public_seed: [8;16], address: Address { level: 10, position: 15, ..Address::default() } };
+        assert_eq!(context.to_bytes().len(), HashContext::<16>::size());
+        assert_eq!(HashContext::<16>::from_bytes(&context.to_bytes()), Ok(context.clone()));
+
+        let mut unknown_type = context.to_bytes();
+        unknown_type[16 + 18] = 7;
+        assert_eq!(HashContext::<16>::from_bytes(&unknown_type), Err(DecodeError::UnknownAddressType(7)));
+        assert_eq!(HashContext::<16>::from_bytes(&unknown_type[1..]), Err(DecodeError::WrongLength(50, 49)));
+        assert_eq!(Sha2Suite::complement_hash(Sha2Suite::repeat_hash(short, 3, &context), 3, &context), Sha2Suite::repeat_hash(short, 255, &context));
+    }
 }
\ No newline at end of file
@@ -1,2 +1,7 @@
 pub mod random_generator;
-pub mod hasher;
\ No newline at end of file
+pub mod hasher;
+pub mod encoding;
+pub mod der;
+pub mod cbor;
+#[cfg(feature = "serde")]
+pub mod serialization;
@@ -1,7 +1,12 @@
 
-use sha2::{Sha256, Digest, digest::Update};
-pub type HashData = [u8;32];
+use std::marker::PhantomData;
+
+use crate::lib::helpers::{encoding::DecodeError, hasher::{HashSuite, Sha2Suite, Shake256Suite}};
+
+// N is the security parameter n of the scheme, in bytes
+pub type HashData<const N: usize = 32> = [u8;N];
 pub const HASH_DATA_0:[u8;32] = [0u8;32];
+pub const ADDRESS_SIZE: usize = 34;
 
 pub fn byte_array_to_hex(data: &[u8]) -> String{
     data.iter().map(|b| format!("{:02x}", b)).collect()
@@ -10,7 +15,8 @@ pub fn byte_array_to_hex(data: &[u8]) -> String{
 pub enum InnerKeyRole {
     MessageKey,
     ChecksumKey,
-    Fors
+    Fors,
+    MessagePrf
 }
 
 impl InnerKeyRole {
@@ -25,41 +31,81 @@ impl InnerKeyRole {
             InnerKeyRole::Fors => {
                 [3]
             }
+            InnerKeyRole::MessagePrf => {
+                [4]
+            }
+        }
+    }
+}
+// What a hash call is used for, the same values as the ADRS types of FIPS 205
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AddressType {
+    #[default]
+    WotsHash = 0,
+    WotsPk = 1,
+    Tree = 2,
+    ForsTree = 3,
+    ForsRoots = 4,
+    WotsPrf = 5,
+    ForsPrf = 6,
+}
+
+impl AddressType {
+    fn from_u32(value: u32) -> Result<Self, DecodeError> {
+        match value {
+            0 => Ok(AddressType::WotsHash),
+            1 => Ok(AddressType::WotsPk),
+            2 => Ok(AddressType::Tree),
+            3 => Ok(AddressType::ForsTree),
+            4 => Ok(AddressType::ForsRoots),
+            5 => Ok(AddressType::WotsPrf),
+            6 => Ok(AddressType::ForsPrf),
+            _ => Err(DecodeError::UnknownAddressType(value))
         }
     }
 }
+
+// level and position pick the WOTS key (or FORS instance) in the hypertree, the rest
+// of the fields make sure no two hash calls in the scheme share the same tweak
 #[derive(Default, Clone, Debug, PartialEq)]
 pub struct Address {
     pub level: u16,
-    pub position: u128
+    pub position: u128,
+    pub kind: AddressType,
+    pub key_pair: u32, // the FORS tree
+    pub chain: u32,    // the WOTS chain, or the height of a tree node
+    pub hash: u32,     // the step in a WOTS chain, or the index of a tree node
 }
 
 impl Address {
-    pub fn to_bytes(&self) -> [u8;18]{
-        let mut out = [0u8;18];
+    // Same level and position, used for another kind of hash call
+    pub fn with_type(&self, kind: AddressType) -> Self {
+        Self { level: self.level, position: self.position, kind, ..Self::default() }
+    }
+
+    pub fn to_bytes(&self) -> [u8;ADDRESS_SIZE]{
+        let mut out = [0u8;ADDRESS_SIZE];
         out[..2].copy_from_slice(&self.level.to_le_bytes());
-        out[2..].copy_from_slice(&self.position.to_le_bytes());
+        out[2..18].copy_from_slice(&self.position.to_le_bytes());
+        out[18..22].copy_from_slice(&(self.kind as u32).to_le_bytes());
+        out[22..26].copy_from_slice(&self.key_pair.to_le_bytes());
+        out[26..30].copy_from_slice(&self.chain.to_le_bytes());
+        out[30..].copy_from_slice(&self.hash.to_le_bytes());
         out
     }
 
-    pub fn from_bytes(bytes: [u8;18]) -> Self {
+    pub fn from_bytes(bytes: [u8;ADDRESS_SIZE]) -> Result<Self, DecodeError> {
         let level_bytes:[u8;2] = bytes[0..2].try_into().expect("Got unexpected bites size?");
-        let position_bytes:[u8; 16] = bytes[2..].try_into().expect("Got unexpected bites size?");
+        let position_bytes:[u8; 16] = bytes[2..18].try_into().expect("Got unexpected bites size?");
+        let word = |start: usize| u32::from_le_bytes(bytes[start..start + 4].try_into().expect("Got unexpected bites size?"));
         let level = u16::from_le_bytes(level_bytes);
         let position = u128::from_le_bytes(position_bytes);
         
-        Self{level, position}
+        Ok(Self{level, position, kind: AddressType::from_u32(word(18))?, key_pair: word(22), chain: word(26), hash: word(30)})
     }
 }
-pub fn get_key(seed: HashData, address: &Address, role: &InnerKeyRole, role_pos: usize) -> HashData {
-    let mut hasher = Sha256::default();
-    Update::update(&mut hasher, &seed);
-    Update::update(&mut hasher, &address.to_bytes());
-    Update::update(&mut hasher, &role.to_bytes());
-    Update::update(&mut hasher, &role_pos.to_le_bytes());
-
-    let result = hasher.finalize();
-    result.into()
+pub fn get_key<H: HashSuite, const N: usize>(seed: HashData<N>, address: &Address, role: &InnerKeyRole, role_pos: usize) -> HashData<N> {
+    H::hash(&[&seed, &address.to_bytes(), &role.to_bytes(), &role_pos.to_le_bytes()])
 }
 
 pub trait RandomGeneratorTrait {
@@ -68,17 +114,21 @@ pub trait RandomGeneratorTrait {
 }
 
 #[derive(Clone, Debug)]
-pub struct RandomGeneratorSha256 {
-    seed: HashData,
+pub struct RandomGenerator<const N: usize = 32, H: HashSuite = Sha2Suite> {
+    seed: HashData<N>,
+    suite: PhantomData<H>,
 }
 
-impl RandomGeneratorSha256 {
-    pub fn new(seed: HashData) -> Self {
-        RandomGeneratorSha256 { seed }
+pub type RandomGeneratorSha256 = RandomGenerator<32, Sha2Suite>;
+pub type RandomGeneratorShake256 = RandomGenerator<32, Shake256Suite>;
+
+impl<const N: usize, H: HashSuite> RandomGenerator<N, H> {
+    pub fn new(seed: HashData<N>) -> Self {
+        RandomGenerator { seed, suite: PhantomData }
     }
 
-    pub fn get_keys<const NUM_KEYS: usize>(&mut self, address: &Address, role: InnerKeyRole) -> [HashData;NUM_KEYS] {
-        core::array::from_fn(|i| get_key(self.seed, address, &role, i))
+    pub fn get_keys<const NUM_KEYS: usize>(&mut self, address: &Address, role: InnerKeyRole) -> [HashData<N>;NUM_KEYS] {
+        core::array::from_fn(|i| get_key::<H, N>(self.seed, address, &role, i))
     }
 }
 
@@ -86,20 +136,30 @@ impl RandomGeneratorSha256 {
 mod tests {
     use crate::lib::helpers::random_generator::InnerKeyRole;
 
-    use super::{RandomGeneratorSha256, Address, HashData};
+    use super::{RandomGeneratorSha256, Address, AddressType, HashData};
+
+    #[test]
+    fn test_address_to_from_bytes() {
+        let address = Address { level: 3, position: 1 << 70, kind: AddressType::ForsTree, key_pair: 7, chain: 2, hash: 9 };
+        assert_eq!(Address::from_bytes(address.to_bytes()), Ok(address.clone()));
+
+        let wots_pk = address.with_type(AddressType::WotsPk);
+        assert_eq!(wots_pk, Address { level: 3, position: 1 << 70, kind: AddressType::WotsPk, ..Address::default() });
+        assert_ne!(wots_pk.to_bytes(), address.with_type(AddressType::Tree).to_bytes());
+    }
 
     #[test]
     fn test_effect_of_position() {
         let seed:HashData = [0;32];
         let mut generator = RandomGeneratorSha256::new(seed);
         
-        let address1 = Address {level: 0, position: 19};
+        let address1 = Address { level: 0, position: 19, ..Address::default() };
         let key_list1 = generator.get_keys::<2>(&address1, InnerKeyRole::ChecksumKey);
         
         assert_eq!(key_list1.len(), 2);
         assert_ne!(key_list1[0], key_list1[1]);
         
-        let address2 = Address {level: 0, position: 20};
+        let address2 = Address { level: 0, position: 20, ..Address::default() };
         let key_list2 = generator.get_keys::<2>(&address2, InnerKeyRole::ChecksumKey);
         
         assert_eq!(key_list2.len(), 2);
@@ -114,13 +174,13 @@ mod tests {
         let seed:HashData = [0;32];
         let mut generator = RandomGeneratorSha256::new(seed);
         
-        let address1 = Address {level: 0, position: 19};
+        let address1 = Address { level: 0, position: 19, ..Address::default() };
         let key_list1 = generator.get_keys::<2>(&address1, InnerKeyRole::MessageKey);
         
         assert_eq!(key_list1.len(), 2);
         assert_ne!(key_list1[0], key_list1[1]);
         
-        let address2 = Address {level: 1, position: 19};
+        let address2 = Address { level: 1, position: 19, ..Address::default() };
         let key_list2 = generator.get_keys::<2>(&address2, InnerKeyRole::MessageKey);
         
         assert_eq!(key_list2.len(), 2);
@@ -137,7 +197,7 @@ mod tests {
         let seed1:HashData = [0;32];
         let mut generator1 = RandomGeneratorSha256::new(seed1);
         
-        let address = Address {level: 0, position: 19};
+        let address = Address { level: 0, position: 19, ..Address::default() };
         let key_list1 = generator1.get_keys::<2>(&address, InnerKeyRole::MessageKey);
         
         assert_eq!(key_list1.len(), 2);
@@ -163,7 +223,7 @@ mod tests {
         let seed1:HashData = [0;32];
         let mut generator1 = RandomGeneratorSha256::new(seed1);
         
-        let address = Address {level: 0, position: 19};
+        let address = Address { level: 0, position: 19, ..Address::default() };
         let key_list1 = generator1.get_keys::<2>(&address, InnerKeyRole::MessageKey);
         
         assert_eq!(key_list1.len(), 2);
@@ -0,0 +1,189 @@
+// serde support, behind the `serde` feature. Every type goes through its canonical
+// byte encoding: raw bytes in binary formats (CBOR, bincode, postcard) and a
+// lowercase hex string in human readable ones (JSON, TOML, YAML).
+use std::fmt;
+
+use serde::{Deserializer, Serializer, de::{self, Visitor}};
+
+use crate::lib::{
+    components::{
+        fors::public::ForsSignature, hypertree::public::HyperTreeSignature, merkle_tree::proof::MerkleProof,
+        slh_dsa::{hash::SlhDsaHash, public::SlhDsaPublic, signature::SlhDsaSignature},
+        sphincs::{compact::CompactSphincsSignature, public::SphincsPublic, signature::SphincsSignature},
+        wots_plus::signature::WotsPlusSignature
+    },
+    helpers::{encoding::DecodeError, hasher::{HashContext, HashSuite}, random_generator::{ADDRESS_SIZE, Address}}
+};
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok()).collect()
+}
+
+pub(crate) fn serialize_bytes<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    match serializer.is_human_readable() {
+        true => serializer.serialize_str(&to_hex(bytes)),
+        false => serializer.serialize_bytes(bytes),
+    }
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a byte string or a hex string")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Vec<u8>, E> {
+        from_hex(value).ok_or_else(|| E::invalid_value(de::Unexpected::Str(value), &self))
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Vec<u8>, E> {
+        Ok(value.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, value: Vec<u8>) -> Result<Vec<u8>, E> {
+        Ok(value)
+    }
+
+    // Formats without a byte string type hand over a sequence of u8
+    fn visit_seq<S: de::SeqAccess<'de>>(self, mut seq: S) -> Result<Vec<u8>, S::Error> {
+        let mut out = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            out.push(byte);
+        }
+        Ok(out)
+    }
+}
+
+pub(crate) fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    match deserializer.is_human_readable() {
+        true => deserializer.deserialize_str(BytesVisitor),
+        false => deserializer.deserialize_byte_buf(BytesVisitor),
+    }
+}
+
+// Implements Serialize and Deserialize through to_bytes and from_bytes
+macro_rules! serde_via_bytes {
+    ([$($generics:tt)*] $ty:ty) => {
+        impl<$($generics)*> serde::Serialize for $ty {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serialize_bytes(&self.to_bytes(), serializer)
+            }
+        }
+
+        impl<'de, $($generics)*> serde::Deserialize<'de> for $ty {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let bytes = deserialize_bytes(deserializer)?;
+                <$ty>::from_bytes(&bytes).map_err(|err| de::Error::custom(format!("{err:?}")))
+            }
+        }
+    };
+}
+
+serde_via_bytes!([const N: usize] HashContext<N>);
+serde_via_bytes!([const N: usize, const W: usize, H: HashSuite] WotsPlusSignature<N, W, H>);
+serde_via_bytes!([const HEIGHT: usize, const N: usize, const W: usize, H: HashSuite] MerkleProof<HEIGHT, N, W, H>);
+serde_via_bytes!([const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite] HyperTreeSignature<LAYERS, TREE_HEIGHT, N, W, H>);
+serde_via_bytes!([const K: usize, const A: usize, const N: usize, H: HashSuite] ForsSignature<K, A, N, H>);
+serde_via_bytes!([const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite] SphincsPublic<K, A, LAYERS, TREE_HEIGHT, N, W, H>);
+serde_via_bytes!([const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite] SphincsSignature<K, A, LAYERS, TREE_HEIGHT, N, W, H>);
+serde_via_bytes!([const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, const N: usize, const W: usize, H: HashSuite] CompactSphincsSignature<K, A, LAYERS, TREE_HEIGHT, N, W, H>);
+serde_via_bytes!([const N: usize, const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, H: SlhDsaHash] SlhDsaPublic<N, K, A, LAYERS, TREE_HEIGHT, H>);
+serde_via_bytes!([const N: usize, const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, H: SlhDsaHash] SlhDsaSignature<N, K, A, LAYERS, TREE_HEIGHT, H>);
+
+// Address::from_bytes takes a fixed size array
+impl serde::Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(&self.to_bytes(), serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserialize_bytes(deserializer)?;
+        let array: [u8; ADDRESS_SIZE] = bytes.as_slice().try_into()
+            .map_err(|_| de::Error::custom(format!("{:?}", DecodeError::WrongLength(ADDRESS_SIZE, bytes.len()))))?;
+        Address::from_bytes(array).map_err(|err| de::Error::custom(format!("{err:?}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::components::{parameters::{SlhDsaSha2_128fPublic, SlhDsaSha2_128fSignature, SlhDsaSha2_128fSigner}, sphincs::secret::SphincsSigner};
+
+    type Signer = SphincsSigner<4, 4, 2, 3>;
+    type Signature = SphincsSignature<4, 4, 2, 3>;
+
+    #[test]
+    fn test_json_uses_hex() {
+        let context = HashContext::<16> { public_seed: [0xab; 16], address: Address { level: 1, position: 2, ..Address::default() } };
+        let json = serde_json::to_string(&context).unwrap();
+        assert_eq!(json, format!("\"{}\"", to_hex(&context.to_bytes())));
+        assert_eq!(serde_json::from_str::<HashContext<16>>(&json).unwrap(), context);
+
+        let address = context.address.clone();
+        assert_eq!(serde_json::from_str::<Address>(&serde_json::to_string(&address).unwrap()).unwrap(), address);
+
+        assert!(serde_json::from_str::<HashContext<16>>("\"zz\"").is_err());
+        assert!(serde_json::from_str::<HashContext<16>>("\"abcd\"").is_err());
+    }
+
+    #[test]
+    fn test_signature_round_trips() {
+        let signer = Signer::new([1u8; 32], [2u8; 32]);
+        let public_key = signer.public_key();
+        let signature = signer.sign(b"Serialize me!");
+
+        let json = serde_json::to_string(&signature).unwrap();
+        let from_json: Signature = serde_json::from_str(&json).unwrap();
+        assert_eq!(from_json, signature);
+
+        // CBOR keeps the raw bytes, so the encoding is barely larger than to_bytes
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&signature, &mut cbor).unwrap();
+        assert!(cbor.len() < Signature::size() + 8);
+        let from_cbor: Signature = ciborium::from_reader(cbor.as_slice()).unwrap();
+        assert_eq!(from_cbor, signature);
+        assert!(from_cbor.validate(b"Serialize me!", &public_key).is_ok());
+
+        let public_json = serde_json::to_string(&public_key).unwrap();
+        assert_eq!(serde_json::from_str::<SphincsPublic<4, 4, 2, 3>>(&public_json).unwrap(), public_key);
+
+        let compact = signature.to_compact();
+        let compact_json = serde_json::to_string(&compact).unwrap();
+        assert_eq!(serde_json::from_str::<CompactSphincsSignature<4, 4, 2, 3>>(&compact_json).unwrap(), compact);
+
+        // The component signatures go through their own encodings
+        let fors_json = serde_json::to_string(&signature.fors).unwrap();
+        assert_eq!(serde_json::from_str::<ForsSignature<4, 4>>(&fors_json).unwrap(), signature.fors);
+        let mut hyper_tree_cbor = Vec::new();
+        ciborium::into_writer(&signature.hyper_tree, &mut hyper_tree_cbor).unwrap();
+        assert_eq!(ciborium::from_reader::<HyperTreeSignature<2, 3>, _>(hyper_tree_cbor.as_slice()).unwrap(), signature.hyper_tree);
+
+        // A wrong length is reported as a decode error
+        assert!(serde_json::from_str::<Signature>(&fors_json).is_err());
+    }
+
+    #[test]
+    fn test_slh_dsa_round_trips() {
+        let signer = SlhDsaSha2_128fSigner::new([1u8; 16], [2u8; 16], [3u8; 16]);
+        let signature = signer.sign_deterministic(b"Serialize me!", b"").unwrap();
+
+        let json = serde_json::to_string(&signer.public_key()).unwrap();
+        assert_eq!(serde_json::from_str::<SlhDsaSha2_128fPublic>(&json).unwrap(), signer.public_key());
+
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&signature, &mut cbor).unwrap();
+        assert_eq!(ciborium::from_reader::<SlhDsaSha2_128fSignature, _>(cbor.as_slice()).unwrap(), signature);
+    }
+}
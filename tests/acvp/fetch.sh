@@ -0,0 +1,40 @@
+#!/bin/sh
+# Downloads the NIST ACVP SLH-DSA keyGen, sigGen and sigVer vectors (FIPS 205
+# revision) from usnistgov/ACVP-Server and keeps a subset of them in this
+# directory, for tests/slh_dsa_acvp.rs. Only the pure interface and the internal
+# one are kept, both deterministic and hedged, with at most two cases per test
+# group, so the files stay small enough to vendor.
+set -e
+cd "$(dirname "$0")"
+base=https://raw.githubusercontent.com/usnistgov/ACVP-Server/master/gen-val/json-files
+tmp=$(mktemp -d)
+trap 'rm -rf "$tmp"' EXIT
+
+for mode in keyGen sigGen sigVer; do
+    curl -fsSL "$base/SLH-DSA-$mode-FIPS205/internalProjection.json" -o "$tmp/$mode.json"
+    python3 - "$tmp/$mode.json" "SLH-DSA-$mode.json" <<'EOF'
+import json, sys
+
+source, target = sys.argv[1:]
+with open(source) as f:
+    vectors = json.load(f)
+
+groups = []
+for group in vectors["testGroups"]:
+    if group.get("preHash", "pure") != "pure":
+        continue
+    tests = group["tests"]
+    if "testPassed" in tests[0]:
+        # One valid and one invalid signature per group
+        picked = [next((t for t in tests if t["testPassed"] == passed), None) for passed in (True, False)]
+        group["tests"] = [t for t in picked if t]
+    else:
+        group["tests"] = tests[:2]
+    groups.append(group)
+
+vectors["testGroups"] = groups
+with open(target, "w") as f:
+    json.dump(vectors, f, indent=1)
+    f.write("\n")
+EOF
+done
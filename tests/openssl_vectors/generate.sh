@@ -0,0 +1,49 @@
+#!/bin/sh
+# Regenerates the SLH-DSA cross-check files in this directory with OpenSSL 3.5 or
+# newer. They are not NIST vectors. Every file holds one deterministic and one
+# hedged (addrnd) signature, both over the pure interface.
+set -e
+cd "$(dirname "$0")"
+tmp=$(mktemp -d)
+trap 'rm -rf "$tmp"' EXIT
+
+hex() { od -An -v -tx1 "$1" | tr -d ' \n'; }
+
+for name in SHA2-128s SHA2-128f SHA2-192s SHA2-192f SHA2-256s SHA2-256f \
+            SHAKE-128s SHAKE-128f SHAKE-192s SHAKE-192f SHAKE-256s SHAKE-256f; do
+    alg="SLH-DSA-$name"
+    n=${name#*-}; n=${n%?}; n=$(( n / 8 ))
+    out="$alg.rsp"
+    echo "# $alg" > "$out"
+    echo "# Generated by generate.sh with $(openssl version | cut -d' ' -f1-2)" >> "$out"
+    for count in 0 1; do
+        seed=$(openssl rand -hex $(( 3 * n )))
+        openssl genpkey -algorithm "$alg" -pkeyopt hexseed:"$seed" -out "$tmp/key.pem"
+        openssl pkey -in "$tmp/key.pem" -outform DER -out "$tmp/key.der"
+        openssl pkey -in "$tmp/key.pem" -pubout -outform DER -out "$tmp/pub.der"
+        # The raw keys are the last 4n and 2n bytes of the DER encodings
+        sk=$(hex "$tmp/key.der" | tail -c $(( 8 * n )))
+        pk=$(hex "$tmp/pub.der" | tail -c $(( 4 * n )))
+        openssl rand -out "$tmp/msg" $(( 16 + count * 17 ))
+        msg=$(hex "$tmp/msg")
+        echo "" >> "$out"
+        echo "count = $count" >> "$out"
+        echo "seed = $seed" >> "$out"
+        echo "pk = $pk" >> "$out"
+        echo "sk = $sk" >> "$out"
+        echo "msg = $msg" >> "$out"
+        if [ "$count" = 0 ]; then
+            echo "ctx = " >> "$out"
+            openssl pkeyutl -sign -rawin -inkey "$tmp/key.pem" -in "$tmp/msg" -out "$tmp/sig" \
+                -pkeyopt deterministic:1
+        else
+            ctx=$(openssl rand -hex 8)
+            addrnd=$(openssl rand -hex "$n")
+            echo "ctx = $ctx" >> "$out"
+            echo "addrnd = $addrnd" >> "$out"
+            openssl pkeyutl -sign -rawin -inkey "$tmp/key.pem" -in "$tmp/msg" -out "$tmp/sig" \
+                -pkeyopt hexcontext-string:"$ctx" -pkeyopt hextest-entropy:"$addrnd"
+        fi
+        echo "sig = $(hex "$tmp/sig")" >> "$out"
+    done
+done
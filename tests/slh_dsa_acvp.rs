@@ -0,0 +1,135 @@
+// The NIST ACVP vectors for SLH-DSA (FIPS 205): keyGen, sigGen and sigVer for all
+// twelve parameter sets, over the pure and the internal interface, deterministic
+// and hedged. The JSON files in tests/acvp are a subset of the ACVP-Server
+// internalProjection files, made by tests/acvp/fetch.sh. The OpenSSL
+// cross-checks in tests/slh_dsa_openssl.rs run next to these as a supplement.
+use std::{fs, path::Path};
+
+use serde_json::Value;
+use rust_sphincs::lib::{
+    components::slh_dsa::{hash::SlhDsaHash, public::SlhDsaPublic, secret::SlhDsaSigner, signature::{SlhDsaSignature, encode_message}},
+    helpers::hasher::{Sha2Suite, Shake256Suite}
+};
+
+const SETS: [&str; 12] = [
+    "SLH-DSA-SHA2-128s", "SLH-DSA-SHA2-128f", "SLH-DSA-SHA2-192s", "SLH-DSA-SHA2-192f", "SLH-DSA-SHA2-256s", "SLH-DSA-SHA2-256f",
+    "SLH-DSA-SHAKE-128s", "SLH-DSA-SHAKE-128f", "SLH-DSA-SHAKE-192s", "SLH-DSA-SHAKE-192f", "SLH-DSA-SHAKE-256s", "SLH-DSA-SHAKE-256f",
+];
+
+// Calls `$check` with the parameters of the named set
+macro_rules! with_set {
+    ($set:expr, $check:ident, $($arg:expr),*) => {
+        match $set {
+            "SLH-DSA-SHA2-128s" => $check::<16, 14, 12, 7, 9, Sha2Suite>($($arg),*),
+            "SLH-DSA-SHA2-128f" => $check::<16, 33, 6, 22, 3, Sha2Suite>($($arg),*),
+            "SLH-DSA-SHA2-192s" => $check::<24, 17, 14, 7, 9, Sha2Suite>($($arg),*),
+            "SLH-DSA-SHA2-192f" => $check::<24, 33, 8, 22, 3, Sha2Suite>($($arg),*),
+            "SLH-DSA-SHA2-256s" => $check::<32, 22, 14, 8, 8, Sha2Suite>($($arg),*),
+            "SLH-DSA-SHA2-256f" => $check::<32, 35, 9, 17, 4, Sha2Suite>($($arg),*),
+            "SLH-DSA-SHAKE-128s" => $check::<16, 14, 12, 7, 9, Shake256Suite>($($arg),*),
+            "SLH-DSA-SHAKE-128f" => $check::<16, 33, 6, 22, 3, Shake256Suite>($($arg),*),
+            "SLH-DSA-SHAKE-192s" => $check::<24, 17, 14, 7, 9, Shake256Suite>($($arg),*),
+            "SLH-DSA-SHAKE-192f" => $check::<24, 33, 8, 22, 3, Shake256Suite>($($arg),*),
+            "SLH-DSA-SHAKE-256s" => $check::<32, 22, 14, 8, 8, Shake256Suite>($($arg),*),
+            "SLH-DSA-SHAKE-256f" => $check::<32, 35, 9, 17, 4, Shake256Suite>($($arg),*),
+            other => panic!("Unknown parameter set {other}"),
+        }
+    };
+}
+
+// The test groups of one file, after checking that every set has one
+fn read_groups(file: &str) -> Vec<Value> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/acvp").join(file);
+    let text = fs::read_to_string(&path).unwrap_or_else(|err| panic!("Can not read {}, run tests/acvp/fetch.sh: {err}", path.display()));
+    let vectors: Value = serde_json::from_str(&text).unwrap_or_else(|err| panic!("{file} is not JSON: {err}"));
+    let groups = vectors["testGroups"].as_array().unwrap_or_else(|| panic!("{file} has no testGroups")).clone();
+
+    for set in SETS {
+        assert!(groups.iter().any(|group| group["parameterSet"] == set), "{file} has no {set} group");
+    }
+    groups
+}
+
+fn from_hex(value: &str) -> Vec<u8> {
+    assert!(value.len().is_multiple_of(2), "Odd length hex string");
+    (0..value.len()).step_by(2).map(|i| u8::from_str_radix(&value[i..i + 2], 16).expect("Invalid hex string")).collect()
+}
+
+fn field(case: &Value, name: &str) -> Vec<u8> {
+    from_hex(case[name].as_str().unwrap_or_else(|| panic!("tcId {}: missing {name}", case["tcId"])))
+}
+
+fn array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    bytes.try_into().expect("Field has the wrong length")
+}
+
+// The internal interface signs M' as given, the pure one encodes the context first
+fn is_internal(group: &Value) -> bool {
+    group["signatureInterface"] == "internal"
+}
+
+fn check_key_gen<const N: usize, const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, H: SlhDsaHash>(group: &Value) {
+    for case in group["tests"].as_array().expect("tests") {
+        let signer = SlhDsaSigner::<N, K, A, LAYERS, TREE_HEIGHT, H>::new(array(&field(case, "skSeed")), array(&field(case, "skPrf")), array(&field(case, "pkSeed")));
+        assert_eq!(signer.to_bytes(), field(case, "sk"), "tcId {}: secret key differs", case["tcId"]);
+        assert_eq!(signer.public_key().to_bytes(), field(case, "pk"), "tcId {}: public key differs", case["tcId"]);
+    }
+}
+
+fn check_sig_gen<const N: usize, const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, H: SlhDsaHash>(group: &Value) {
+    let deterministic = group["deterministic"].as_bool().expect("deterministic");
+    for case in group["tests"].as_array().expect("tests") {
+        let signer = SlhDsaSigner::<N, K, A, LAYERS, TREE_HEIGHT, H>::from_bytes(&field(case, "sk")).expect("ACVP secret keys decode");
+        let message = field(case, "message");
+        let encoded = match is_internal(group) {
+            true => message,
+            false => encode_message(&message, &field(case, "context")).expect("Context is at most 255 bytes"),
+        };
+        let additional_random = (!deterministic).then(|| array(&field(case, "additionalRandomness")));
+
+        let signature = signer.sign_internal(&encoded, additional_random);
+        assert!(signature.to_bytes() == field(case, "signature"), "tcId {}: signature differs", case["tcId"]);
+    }
+}
+
+fn check_sig_ver<const N: usize, const K: usize, const A: usize, const LAYERS: usize, const TREE_HEIGHT: usize, H: SlhDsaHash>(group: &Value) {
+    for case in group["tests"].as_array().expect("tests") {
+        let public_key = SlhDsaPublic::<N, K, A, LAYERS, TREE_HEIGHT, H>::from_bytes(&field(case, "pk"));
+        let signature = SlhDsaSignature::<N, K, A, LAYERS, TREE_HEIGHT, H>::from_bytes(&field(case, "signature"));
+        let message = field(case, "message");
+
+        // A key or signature of the wrong length is a failed verification
+        let valid = match (public_key, signature) {
+            (Ok(public_key), Ok(signature)) => match is_internal(group) {
+                true => signature.validate_internal(&message, &public_key).is_ok(),
+                false => signature.validate(&message, &field(case, "context"), &public_key).is_ok(),
+            },
+            _ => false,
+        };
+        assert_eq!(Some(valid), case["testPassed"].as_bool(), "tcId {}: {}", case["tcId"], case["reason"]);
+    }
+}
+
+#[test]
+#[ignore = "needs the NIST files in tests/acvp, made by tests/acvp/fetch.sh"]
+fn acvp_key_gen() {
+    for group in read_groups("SLH-DSA-keyGen.json") {
+        with_set!(group["parameterSet"].as_str().expect("parameterSet"), check_key_gen, &group);
+    }
+}
+
+#[test]
+#[ignore = "needs the NIST files in tests/acvp, made by tests/acvp/fetch.sh"]
+fn acvp_sig_gen() {
+    for group in read_groups("SLH-DSA-sigGen.json") {
+        with_set!(group["parameterSet"].as_str().expect("parameterSet"), check_sig_gen, &group);
+    }
+}
+
+#[test]
+#[ignore = "needs the NIST files in tests/acvp, made by tests/acvp/fetch.sh"]
+fn acvp_sig_ver() {
+    for group in read_groups("SLH-DSA-sigVer.json") {
+        with_set!(group["parameterSet"].as_str().expect("parameterSet"), check_sig_ver, &group);
+    }
+}
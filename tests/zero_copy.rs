@@ -0,0 +1,66 @@
+// Verifying through SphincsSignatureRef must not touch the heap. The allocator of
+// this test binary counts the allocations of every thread, so tests running in
+// parallel do not disturb each other.
+use std::{alloc::{GlobalAlloc, Layout, System}, cell::Cell};
+
+use rust_sphincs::lib::{
+    components::{parameters::SphincsSha2_128fSigner, sphincs::{secret::SphincsSigner, signature_ref::SphincsSignatureRef}},
+    helpers::hasher::Shake256Suite
+};
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations<T>(run: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let out = run();
+    (out, ALLOCATIONS.with(Cell::get) - before)
+}
+
+#[test]
+fn test_validate_from_bytes_does_not_allocate() {
+    let signer = SphincsSha2_128fSigner::new([1u8; 16], [2u8; 16]);
+    let public_key = signer.public_key();
+    let bytes = signer.sign(b"gateway request").to_bytes();
+
+    let (valid, count) = allocations(|| SphincsSignatureRef::<33, 6, 22, 3, 16>::new(&bytes).unwrap().validate(b"gateway request", &public_key).is_ok());
+    assert!(valid);
+    assert_eq!(count, 0);
+
+    let (valid, count) = allocations(|| SphincsSignatureRef::<33, 6, 22, 3, 16>::new(&bytes).unwrap().validate(b"gateway reply", &public_key).is_ok());
+    assert!(!valid);
+    assert_eq!(count, 0);
+
+    // The owned signature has to be decoded first, and that alone allocates
+    let (_, count) = allocations(|| SphincsSignatureRef::<33, 6, 22, 3, 16>::new(&bytes).unwrap().to_signature());
+    assert!(count > 0);
+}
+
+#[test]
+fn test_validate_from_bytes_does_not_allocate_shake() {
+    let signer = SphincsSigner::<33, 6, 22, 3, 16, 256, Shake256Suite>::new([1u8; 16], [2u8; 16]);
+    let public_key = signer.public_key();
+    let bytes = signer.sign(b"gateway request").to_bytes();
+
+    type View<'a> = SphincsSignatureRef<'a, 33, 6, 22, 3, 16, 256, Shake256Suite>;
+    let (valid, count) = allocations(|| View::new(&bytes).unwrap().validate(b"gateway request", &public_key).is_ok());
+    assert!(valid);
+    assert_eq!(count, 0);
+}